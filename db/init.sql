@@ -2,14 +2,37 @@
 -- ОПТИМИЗИРОВАННАЯ СХЕМА БАЗЫ ДАННЫХ "Кассиопея"
 -- =====================================================
 
+-- Реестр отслеживаемых спутников (25544 — МКС)
+CREATE TABLE IF NOT EXISTS satellites (
+    norad_id BIGINT PRIMARY KEY,
+    name TEXT NOT NULL,
+    source_url TEXT NOT NULL,
+    interval_seconds INT NOT NULL DEFAULT 120 CHECK (interval_seconds > 0),
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
 -- ISS fetch log с партицированием
 CREATE TABLE IF NOT EXISTS iss_fetch_log (
     id BIGSERIAL,
+    norad_id BIGINT NOT NULL DEFAULT 25544,
     fetched_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     source_url TEXT NOT NULL,
     payload JSONB NOT NULL
 ) PARTITION BY RANGE (fetched_at);
 
+-- Для уже развёрнутых БД, созданных до появления реестра спутников
+ALTER TABLE iss_fetch_log ADD COLUMN IF NOT EXISTS norad_id BIGINT NOT NULL DEFAULT 25544;
+
+-- Источник позиции (wheretheiss, opennotify, tle); у старых записей пусто
+ALTER TABLE iss_fetch_log ADD COLUMN IF NOT EXISTS provider TEXT;
+
+-- Страна или океан под спутником (офлайн-геокодирование в сервисе)
+ALTER TABLE iss_fetch_log ADD COLUMN IF NOT EXISTS geo_kind TEXT CHECK (geo_kind IN ('country', 'ocean'));
+ALTER TABLE iss_fetch_log ADD COLUMN IF NOT EXISTS geo_code TEXT;
+ALTER TABLE iss_fetch_log ADD COLUMN IF NOT EXISTS geo_name TEXT;
+
 -- Создание партиций (автоматизировать через pg_cron)
 DO $$
 DECLARE
@@ -32,6 +55,8 @@ END $$;
 
 -- Индексы для ISS
 CREATE INDEX IF NOT EXISTS idx_iss_fetched_at ON iss_fetch_log(fetched_at DESC);
+CREATE INDEX IF NOT EXISTS idx_iss_norad_fetched_at ON iss_fetch_log(norad_id, fetched_at DESC);
+CREATE INDEX IF NOT EXISTS idx_iss_norad_keyset ON iss_fetch_log(norad_id, fetched_at DESC, id DESC);
 CREATE INDEX IF NOT EXISTS idx_iss_payload_gin ON iss_fetch_log USING GIN(payload);
 
 -- OSDR items с UPSERT-friendly структурой
@@ -52,6 +77,125 @@ CREATE INDEX IF NOT EXISTS idx_osdr_inserted ON osdr_items(inserted_at DESC);
 CREATE INDEX IF NOT EXISTS idx_osdr_raw_gin ON osdr_items USING GIN(raw);
 CREATE INDEX IF NOT EXISTS idx_osdr_organism ON osdr_items(organism);
 CREATE INDEX IF NOT EXISTS idx_osdr_study_type ON osdr_items(study_type);
+CREATE INDEX IF NOT EXISTS idx_osdr_status ON osdr_items(status);
+-- Ключ курсорной пагинации списка (updated_at, id). NULL в любом направлении идёт
+-- в конец, поэтому по убыванию он заменяется на -infinity, по возрастанию — на infinity
+CREATE INDEX IF NOT EXISTS idx_osdr_keyset_desc ON osdr_items((COALESCE(updated_at, '-infinity'::timestamptz)) DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_osdr_keyset_asc ON osdr_items((COALESCE(updated_at, 'infinity'::timestamptz)), id);
+
+-- Текст для полнотекстового поиска: к исходному добавляется копия без дефисов
+-- внутри слов, чтобы "micro-gravity" находилось по "microgravity" и наоборот
+CREATE OR REPLACE FUNCTION osdr_search_text(src TEXT) RETURNS TEXT AS $$
+    SELECT COALESCE(src, '') || ' ' || regexp_replace(COALESCE(src, ''), '(\w)-(?=\w)', '\1', 'g');
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- Поисковый вектор: идентификатор и заголовок (A), организм и тип исследования (B),
+-- описание и ключевые слова из raw (C)
+ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', osdr_search_text(COALESCE(dataset_id, '') || ' ' || COALESCE(title, ''))), 'A')
+    || setweight(to_tsvector('english', osdr_search_text(COALESCE(organism, '') || ' ' || COALESCE(study_type, ''))), 'B')
+    || setweight(to_tsvector('english', osdr_search_text(
+        COALESCE(raw->>'description', raw->>'summary', raw->>'abstract', '') || ' ' ||
+        COALESCE(raw->>'keywords', '') || ' ' || COALESCE(raw->>'project_title', '')
+    )), 'C')
+) STORED;
+CREATE INDEX IF NOT EXISTS idx_osdr_search ON osdr_items USING GIN(search_vector);
+
+-- Отзыв наборов: last_seen_at — начало последней полной синхронизации, в которой
+-- набор был в выдаче; после OSDR_WITHDRAW_AFTER_SYNCS пропусков подряд он помечается отозванным
+ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS last_seen_at TIMESTAMPTZ;
+ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS missed_syncs INT NOT NULL DEFAULT 0;
+ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS withdrawn_at TIMESTAMPTZ;
+ALTER TABLE osdr_items ADD COLUMN IF NOT EXISTS withdrawn_reason TEXT;
+CREATE INDEX IF NOT EXISTS idx_osdr_withdrawn ON osdr_items(withdrawn_at) WHERE withdrawn_at IS NOT NULL;
+
+-- История изменений записей OSDR: новая версия только при изменении полей или raw
+CREATE TABLE IF NOT EXISTS osdr_item_versions (
+    id BIGSERIAL PRIMARY KEY,
+    item_id BIGINT NOT NULL REFERENCES osdr_items(id) ON DELETE CASCADE,
+    version INT NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- {"поле": {"old": .., "new": ..}}, изменения raw — как "raw.<ключ>"
+    diff JSONB NOT NULL,
+    UNIQUE (item_id, version)
+);
+
+-- Отчёты прогонов синхронизации OSDR
+CREATE TABLE IF NOT EXISTS osdr_sync_reports (
+    id BIGSERIAL PRIMARY KEY,
+    mode TEXT NOT NULL CHECK (mode IN ('full', 'incremental')),
+    status TEXT NOT NULL CHECK (status IN ('completed', 'failed')),
+    started_at TIMESTAMPTZ NOT NULL,
+    finished_at TIMESTAMPTZ NOT NULL,
+    pages INT NOT NULL DEFAULT 0,
+    items_seen BIGINT NOT NULL DEFAULT 0,
+    inserted BIGINT NOT NULL DEFAULT 0,
+    updated BIGINT NOT NULL DEFAULT 0,
+    unchanged BIGINT NOT NULL DEFAULT 0,
+    skipped BIGINT NOT NULL DEFAULT 0,
+    failed BIGINT NOT NULL DEFAULT 0,
+    truncated BOOLEAN NOT NULL DEFAULT FALSE,
+    -- Сколько наборов помечено отозванными по итогам полной синхронизации
+    withdrawn BIGINT NOT NULL DEFAULT 0,
+    watermark_before TIMESTAMPTZ,
+    watermark_after TIMESTAMPTZ,
+    error TEXT,
+    -- [{"page": .., "dataset_id": .., "reason": ..}]
+    errors JSONB NOT NULL DEFAULT '[]',
+    -- Итог стадии обогащения (null — не запускалась)
+    enrichment JSONB
+);
+
+CREATE INDEX IF NOT EXISTS idx_osdr_sync_reports_started ON osdr_sync_reports(started_at DESC);
+
+-- Обогащение OSDR: подробная карточка набора; source_updated_at — updated_at набора
+-- на момент чтения, при его смене карточка, ассаи и файлы перечитываются
+CREATE TABLE IF NOT EXISTS osdr_item_details (
+    item_id BIGINT PRIMARY KEY REFERENCES osdr_items(id) ON DELETE CASCADE,
+    source_updated_at TIMESTAMPTZ,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    detail JSONB,
+    -- Последняя ошибка чтения; такие наборы повторяются не чаще раза в сутки
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_osdr_item_details_fetched ON osdr_item_details(fetched_at);
+
+-- Ассаи набора (тип измерения, технология, платформа)
+CREATE TABLE IF NOT EXISTS osdr_assays (
+    id BIGSERIAL PRIMARY KEY,
+    item_id BIGINT NOT NULL REFERENCES osdr_items(id) ON DELETE CASCADE,
+    name TEXT,
+    measurement_type TEXT,
+    technology_type TEXT,
+    platform TEXT,
+    raw JSONB NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_osdr_assays_item ON osdr_assays(item_id);
+CREATE INDEX IF NOT EXISTS idx_osdr_assays_measurement ON osdr_assays(measurement_type);
+
+-- Файлы набора
+CREATE TABLE IF NOT EXISTS osdr_files (
+    id BIGSERIAL PRIMARY KEY,
+    item_id BIGINT NOT NULL REFERENCES osdr_items(id) ON DELETE CASCADE,
+    file_name TEXT NOT NULL,
+    category TEXT,
+    subcategory TEXT,
+    size_bytes BIGINT,
+    url TEXT,
+    raw JSONB NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_osdr_files_item ON osdr_files(item_id, file_name);
+
+-- Состояние синхронизации OSDR: водяной знак updated_at по URL источника
+CREATE TABLE IF NOT EXISTS osdr_sync_state (
+    source TEXT PRIMARY KEY,
+    watermark TIMESTAMPTZ,
+    last_full_sync_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
 
 -- Space cache для APOD, NEO, DONKI, SpaceX
 CREATE TABLE IF NOT EXISTS space_cache (
@@ -63,8 +207,149 @@ CREATE TABLE IF NOT EXISTS space_cache (
 
 -- Composite индекс для оптимизации запросов WHERE source=X ORDER BY fetched_at
 CREATE INDEX IF NOT EXISTS idx_space_cache_source_time ON space_cache(source, fetched_at DESC);
+-- Курсорная пагинация истории источника: (fetched_at, id)
+CREATE INDEX IF NOT EXISTS idx_space_cache_source_keyset ON space_cache(source, fetched_at DESC, id DESC);
 CREATE INDEX IF NOT EXISTS idx_space_cache_payload_gin ON space_cache USING GIN(payload);
 
+-- TLE наборы орбитальных элементов (Celestrak / локальный файл)
+CREATE TABLE IF NOT EXISTS tle_sets (
+    id BIGSERIAL PRIMARY KEY,
+    norad_id BIGINT NOT NULL,
+    object_name TEXT,
+    line1 TEXT NOT NULL,
+    line2 TEXT NOT NULL,
+    epoch TIMESTAMPTZ NOT NULL,
+    source TEXT NOT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (norad_id, epoch)
+);
+
+CREATE INDEX IF NOT EXISTS idx_tle_sets_norad_epoch ON tle_sets(norad_id, epoch DESC);
+
+-- Сравнение SGP4-прогноза с фактическими позициями (контроль устаревания TLE)
+CREATE TABLE IF NOT EXISTS tle_checks (
+    id BIGSERIAL PRIMARY KEY,
+    checked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    norad_id BIGINT NOT NULL,
+    tle_epoch TIMESTAMPTZ NOT NULL,
+    fix_at TIMESTAMPTZ NOT NULL,
+    observed_lat DOUBLE PRECISION NOT NULL,
+    observed_lon DOUBLE PRECISION NOT NULL,
+    observed_alt DOUBLE PRECISION NOT NULL,
+    predicted_lat DOUBLE PRECISION NOT NULL,
+    predicted_lon DOUBLE PRECISION NOT NULL,
+    predicted_alt DOUBLE PRECISION NOT NULL,
+    error_km DOUBLE PRECISION NOT NULL,
+    stale BOOLEAN NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_tle_checks_norad_time ON tle_checks(norad_id, checked_at DESC);
+
+-- Неудачные опросы источников позиций (для объяснения пропусков в логе)
+CREATE TABLE IF NOT EXISTS iss_fetch_errors (
+    id BIGSERIAL PRIMARY KEY,
+    norad_id BIGINT NOT NULL,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    code TEXT NOT NULL,
+    message TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_iss_fetch_errors_norad_time ON iss_fetch_errors(norad_id, occurred_at DESC);
+
+-- Аномалии орбиты, найденные по логу позиций (скачки, выбросы скорости, подъём/снижение)
+CREATE TABLE IF NOT EXISTS iss_anomalies (
+    id BIGSERIAL PRIMARY KEY,
+    norad_id BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    fetch_id BIGINT NOT NULL,
+    observed_at TIMESTAMPTZ NOT NULL,
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    value DOUBLE PRECISION NOT NULL,
+    threshold DOUBLE PRECISION NOT NULL,
+    details JSONB NOT NULL DEFAULT '{}'::jsonb,
+    UNIQUE (norad_id, fetch_id, kind)
+);
+
+CREATE INDEX IF NOT EXISTS idx_iss_anomalies_norad_time ON iss_anomalies(norad_id, observed_at DESC);
+
+-- Последняя строка iss_fetch_log, проверенная детектором аномалий
+CREATE TABLE IF NOT EXISTS iss_anomaly_state (
+    norad_id BIGINT PRIMARY KEY,
+    last_fetch_id BIGINT NOT NULL,
+    processed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Агрегаты метрик позиций (минута/час/сутки): суммы и суммы квадратов,
+-- чтобы интервалы можно было сливать и восстанавливать среднее и отклонение
+CREATE TABLE IF NOT EXISTS iss_rollup_minute (
+    norad_id BIGINT NOT NULL,
+    bucket_start TIMESTAMPTZ NOT NULL,
+    cnt BIGINT NOT NULL,
+    lat_cnt BIGINT NOT NULL,
+    lat_sum DOUBLE PRECISION NOT NULL,
+    lat_sumsq DOUBLE PRECISION NOT NULL,
+    lat_min DOUBLE PRECISION,
+    lat_max DOUBLE PRECISION,
+    lon_cnt BIGINT NOT NULL,
+    lon_sin DOUBLE PRECISION NOT NULL,
+    lon_cos DOUBLE PRECISION NOT NULL,
+    lon_min DOUBLE PRECISION,
+    lon_max DOUBLE PRECISION,
+    alt_cnt BIGINT NOT NULL,
+    alt_sum DOUBLE PRECISION NOT NULL,
+    alt_sumsq DOUBLE PRECISION NOT NULL,
+    alt_min DOUBLE PRECISION,
+    alt_max DOUBLE PRECISION,
+    vel_cnt BIGINT NOT NULL,
+    vel_sum DOUBLE PRECISION NOT NULL,
+    vel_sumsq DOUBLE PRECISION NOT NULL,
+    vel_min DOUBLE PRECISION,
+    vel_max DOUBLE PRECISION,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (norad_id, bucket_start)
+);
+
+CREATE TABLE IF NOT EXISTS iss_rollup_hour (LIKE iss_rollup_minute INCLUDING ALL);
+CREATE TABLE IF NOT EXISTS iss_rollup_day (LIKE iss_rollup_minute INCLUDING ALL);
+
+CREATE INDEX IF NOT EXISTS idx_iss_rollup_minute_bucket ON iss_rollup_minute(bucket_start);
+CREATE INDEX IF NOT EXISTS idx_iss_rollup_hour_bucket ON iss_rollup_hour(bucket_start);
+CREATE INDEX IF NOT EXISTS idx_iss_rollup_day_bucket ON iss_rollup_day(bucket_start);
+
+-- Геозоны: круг {type, lat, lon, radius_km} или многоугольник {type, coordinates: [[lat, lon], ...]}
+CREATE TABLE IF NOT EXISTS geofences (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    shape JSONB NOT NULL,
+    norad_id BIGINT,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Находится ли спутник внутри зоны по последней проверке
+CREATE TABLE IF NOT EXISTS geofence_state (
+    geofence_id BIGINT NOT NULL REFERENCES geofences(id) ON DELETE CASCADE,
+    norad_id BIGINT NOT NULL,
+    inside BOOLEAN NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (geofence_id, norad_id)
+);
+
+-- Входы и выходы спутников
+CREATE TABLE IF NOT EXISTS geofence_events (
+    id BIGSERIAL PRIMARY KEY,
+    geofence_id BIGINT NOT NULL REFERENCES geofences(id) ON DELETE CASCADE,
+    norad_id BIGINT NOT NULL,
+    event TEXT NOT NULL CHECK (event IN ('enter', 'exit')),
+    fetch_id BIGINT NOT NULL,
+    occurred_at TIMESTAMPTZ NOT NULL,
+    latitude DOUBLE PRECISION NOT NULL,
+    longitude DOUBLE PRECISION NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_geofence_events_fence_time ON geofence_events(geofence_id, occurred_at DESC);
+
 -- Telemetry legacy (из Pascal)
 CREATE TABLE IF NOT EXISTS telemetry_legacy (
     id BIGSERIAL PRIMARY KEY,
@@ -135,8 +420,10 @@ CREATE UNIQUE INDEX IF NOT EXISTS idx_dashboard_metrics ON dashboard_metrics(met
 CREATE OR REPLACE FUNCTION cleanup_old_data() RETURNS void AS $$
 BEGIN
     -- Удаление данных старше retention period
-    DELETE FROM iss_fetch_log WHERE fetched_at < NOW() - INTERVAL '90 days';
+    -- iss_fetch_log и агрегаты iss_rollup_* чистит rust-iss по RAW_RETENTION_DAYS / ROLLUP_*_RETENTION_DAYS
     DELETE FROM space_cache WHERE fetched_at < NOW() - INTERVAL '30 days';
+    DELETE FROM tle_checks WHERE checked_at < NOW() - INTERVAL '90 days';
+    DELETE FROM iss_fetch_errors WHERE occurred_at < NOW() - INTERVAL '90 days';
     DELETE FROM telemetry_legacy WHERE recorded_at < NOW() - INTERVAL '180 days';
     
     -- Обновление материализованного представления
@@ -160,6 +447,15 @@ CREATE TRIGGER update_cms_pages_updated_at
     FOR EACH ROW
     EXECUTE FUNCTION update_updated_at_column();
 
+CREATE TRIGGER update_satellites_updated_at
+    BEFORE UPDATE ON satellites
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER update_geofences_updated_at
+    BEFORE UPDATE ON geofences
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
 -- Таблица CMS блоков для динамических вставок на страницах
 CREATE TABLE IF NOT EXISTS cms_blocks (
     id SERIAL PRIMARY KEY,
@@ -218,10 +514,47 @@ INSERT INTO osdr_items (dataset_id, title, organism, study_type, status, updated
 ('OSD-682', 'Radiation Effects on Human Cells in Deep Space Simulation', 'Homo sapiens', 'Radiation Biology', 'Active', NOW() - INTERVAL '14 days', '{"accession": "OSD-682", "doi": "10.26030/example20"}'::jsonb)
 ON CONFLICT (dataset_id) DO NOTHING;
 
+-- Начальная версия для записей, появившихся до ведения истории
+INSERT INTO osdr_item_versions (item_id, version, changed_at, diff)
+SELECT i.id, 1, i.inserted_at,
+    COALESCE((
+        SELECT jsonb_object_agg(f.key, jsonb_build_object('old', NULL, 'new', f.value))
+        FROM jsonb_each(jsonb_build_object(
+            'title', i.title, 'organism', i.organism, 'study_type', i.study_type,
+            'status', i.status, 'updated_at', i.updated_at
+        )) f
+        WHERE f.value <> 'null'::jsonb
+    ), '{}'::jsonb)
+    || COALESCE((
+        SELECT jsonb_object_agg('raw.' || r.key, jsonb_build_object('old', NULL, 'new', r.value))
+        FROM jsonb_each(CASE WHEN jsonb_typeof(i.raw) = 'object' THEN i.raw ELSE '{}'::jsonb END) r
+        WHERE r.value <> 'null'::jsonb
+    ), '{}'::jsonb)
+FROM osdr_items i
+WHERE NOT EXISTS (SELECT 1 FROM osdr_item_versions v WHERE v.item_id = i.id);
+
 -- Комментарии для документации
 COMMENT ON TABLE iss_fetch_log IS 'Логи запросов к ISS API с партицированием по дням';
+COMMENT ON TABLE satellites IS 'Реестр спутников для фонового опроса позиций';
 COMMENT ON TABLE osdr_items IS 'Данные из NASA OSDR (Open Science Data Repository)';
+COMMENT ON TABLE osdr_item_versions IS 'История изменений записей OSDR';
+COMMENT ON TABLE osdr_item_details IS 'Подробные карточки наборов OSDR (стадия обогащения)';
+COMMENT ON TABLE osdr_assays IS 'Ассаи наборов OSDR';
+COMMENT ON TABLE osdr_files IS 'Файлы наборов OSDR';
+COMMENT ON TABLE osdr_sync_reports IS 'Отчёты синхронизации OSDR: счётчики и причины ошибок';
+COMMENT ON TABLE osdr_sync_state IS 'Водяной знак инкрементальной синхронизации OSDR';
 COMMENT ON TABLE space_cache IS 'Универсальный кэш для космических данных (APOD, NEO, DONKI, SpaceX)';
+COMMENT ON TABLE tle_sets IS 'Наборы TLE с эпохами для SGP4-прогноза';
+COMMENT ON TABLE tle_checks IS 'Сверка SGP4-прогноза с фактическими позициями МКС';
+COMMENT ON TABLE iss_fetch_errors IS 'Ошибки опроса источников позиций';
+COMMENT ON TABLE iss_anomalies IS 'Аномалии орбиты по данным лога позиций';
+COMMENT ON TABLE iss_anomaly_state IS 'Прогресс детектора аномалий по каждому спутнику';
+COMMENT ON TABLE iss_rollup_minute IS 'Поминутные агрегаты метрик позиций';
+COMMENT ON TABLE iss_rollup_hour IS 'Почасовые агрегаты метрик позиций';
+COMMENT ON TABLE iss_rollup_day IS 'Суточные агрегаты метрик позиций';
+COMMENT ON TABLE geofences IS 'Геозоны для отслеживания входа и выхода спутников';
+COMMENT ON TABLE geofence_state IS 'Текущее положение спутников относительно геозон';
+COMMENT ON TABLE geofence_events IS 'События входа и выхода из геозон';
 COMMENT ON TABLE telemetry_legacy IS 'Телеметрия от legacy Pascal сервиса';
 COMMENT ON TABLE cms_pages IS 'Статические страницы CMS';
 
@@ -229,7 +562,7 @@ COMMENT ON TABLE cms_pages IS 'Статические страницы CMS';
 DO $$
 BEGIN
     RAISE NOTICE '=== Database initialization completed ===';
-    RAISE NOTICE 'Tables: iss_fetch_log (partitioned), osdr_items, space_cache, telemetry_legacy, cms_pages, cms_blocks';
+    RAISE NOTICE 'Tables: satellites, iss_fetch_log (partitioned), osdr_items, osdr_item_versions, osdr_item_details, osdr_assays, osdr_files, osdr_sync_reports, osdr_sync_state, space_cache, tle_sets, tle_checks, iss_fetch_errors, iss_anomalies, iss_anomaly_state, iss_rollup_minute, iss_rollup_hour, iss_rollup_day, geofences, geofence_state, geofence_events, telemetry_legacy, cms_pages, cms_blocks';
     RAISE NOTICE 'Indexes: Created for all tables';
     RAISE NOTICE 'Materialized View: dashboard_metrics';
     RAISE NOTICE 'Functions: cleanup_old_data(), update_updated_at_column()';
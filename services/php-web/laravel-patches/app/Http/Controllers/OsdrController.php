@@ -25,6 +25,7 @@ class OsdrController extends Controller
             'limit' => $limit,
             'offset' => $offset,
             'search' => $search ?: null,
+            'include_total' => 'true',
         ]));
 
         $cacheKey = 'osdr_list_' . md5($params);
@@ -26,6 +26,8 @@ class ProxyController extends Controller
             'limit' => $request->get('limit', 20),
             'offset' => $request->get('offset', 0),
             'search' => $request->get('search', ''),
+            'cursor' => $request->get('cursor'),
+            'include_total' => $request->get('include_total'),
         ]);
         return $this->pipe('/api/osdr?' . $params);
     }
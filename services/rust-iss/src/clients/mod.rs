@@ -1,3 +1,3 @@
-pub mod nasa_client;
+pub mod position_provider;
 
-pub use nasa_client::NasaClient;
+pub use position_provider::{build_providers, IssPositionProvider};
@@ -0,0 +1,206 @@
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde_json::Value;
+
+use crate::domain::orbit::Propagator;
+use crate::domain::{parse_number, NormalizedPosition, Satellite, ISS_NORAD_ID};
+use crate::errors::ApiError;
+use crate::repo::TleRepository;
+
+/// Источник текущей позиции спутника
+#[async_trait]
+pub trait IssPositionProvider: Send + Sync {
+    /// Имя, которое пишется в iss_fetch_log.provider
+    fn name(&self) -> &'static str;
+
+    fn supports(&self, _norad_id: i64) -> bool {
+        true
+    }
+
+    async fn fetch(&self, sat: &Satellite) -> Result<NormalizedPosition, ApiError>;
+}
+
+/// Собирает провайдеры в порядке из конфигурации
+pub fn build_providers<T: TleRepository + 'static>(
+    names: &[String],
+    open_notify_url: &str,
+    tle_repo: Arc<T>,
+) -> Result<Vec<Arc<dyn IssPositionProvider>>, ApiError> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(20))
+        .user_agent("KosmoStars-Space/1.0")
+        .build()
+        .expect("Failed to build HTTP client");
+
+    let providers = names
+        .iter()
+        .map(|name| -> Result<Arc<dyn IssPositionProvider>, ApiError> {
+            match name.as_str() {
+                "wheretheiss" => Ok(Arc::new(WhereTheIssProvider::new(client.clone()))),
+                "opennotify" => Ok(Arc::new(OpenNotifyProvider::new(
+                    client.clone(),
+                    open_notify_url.to_string(),
+                ))),
+                "tle" => Ok(Arc::new(TlePositionProvider::new(tle_repo.clone()))),
+                other => Err(ApiError::validation(format!("Unknown position provider: {}", other))),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if providers.is_empty() {
+        return Err(ApiError::validation("At least one position provider is required"));
+    }
+    Ok(providers)
+}
+
+/// Формат api.wheretheiss.at; URL берётся из реестра спутников
+pub struct WhereTheIssProvider {
+    client: Client,
+}
+
+impl WhereTheIssProvider {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl IssPositionProvider for WhereTheIssProvider {
+    fn name(&self) -> &'static str {
+        "wheretheiss"
+    }
+
+    async fn fetch(&self, sat: &Satellite) -> Result<NormalizedPosition, ApiError> {
+        let raw = get_json(&self.client, &sat.source_url).await?;
+        let (latitude, longitude) = coordinates(&raw, &raw)?;
+
+        Ok(NormalizedPosition {
+            latitude,
+            longitude,
+            altitude: parse_number(&raw["altitude"]),
+            velocity: parse_number(&raw["velocity"]),
+            visibility: raw["visibility"].as_str().map(|s| s.to_string()),
+            timestamp: unix_timestamp(&raw["timestamp"]).unwrap_or_else(Utc::now),
+            source: sat.source_url.clone(),
+            raw,
+        })
+    }
+}
+
+/// Формат open-notify: {"timestamp": .., "iss_position": {"latitude": "..", "longitude": ".."}};
+/// отдаёт только МКС и без высоты/скорости
+pub struct OpenNotifyProvider {
+    client: Client,
+    url: String,
+}
+
+impl OpenNotifyProvider {
+    pub fn new(client: Client, url: String) -> Self {
+        Self { client, url }
+    }
+}
+
+#[async_trait]
+impl IssPositionProvider for OpenNotifyProvider {
+    fn name(&self) -> &'static str {
+        "opennotify"
+    }
+
+    fn supports(&self, norad_id: i64) -> bool {
+        norad_id == ISS_NORAD_ID
+    }
+
+    async fn fetch(&self, _sat: &Satellite) -> Result<NormalizedPosition, ApiError> {
+        let raw = get_json(&self.client, &self.url).await?;
+        if let Some(message) = raw["message"].as_str() {
+            if message != "success" {
+                return Err(ApiError::upstream(502, format!("open-notify returned {}", message)));
+            }
+        }
+        let (latitude, longitude) = coordinates(&raw["iss_position"], &raw)?;
+
+        Ok(NormalizedPosition {
+            latitude,
+            longitude,
+            altitude: None,
+            velocity: None,
+            visibility: None,
+            timestamp: unix_timestamp(&raw["timestamp"]).unwrap_or_else(Utc::now),
+            source: self.url.clone(),
+            raw,
+        })
+    }
+}
+
+/// Расчёт по последнему загруженному TLE (SGP4), без сети
+pub struct TlePositionProvider<T: TleRepository> {
+    tle_repo: Arc<T>,
+}
+
+impl<T: TleRepository> TlePositionProvider<T> {
+    pub fn new(tle_repo: Arc<T>) -> Self {
+        Self { tle_repo }
+    }
+}
+
+#[async_trait]
+impl<T: TleRepository> IssPositionProvider for TlePositionProvider<T> {
+    fn name(&self) -> &'static str {
+        "tle"
+    }
+
+    async fn fetch(&self, sat: &Satellite) -> Result<NormalizedPosition, ApiError> {
+        let tle = self
+            .tle_repo
+            .get_latest(sat.norad_id)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("No TLE loaded for NORAD {}", sat.norad_id)))?;
+        let propagator = Propagator::from_tle(tle.object_name, &tle.line1, &tle.line2)?;
+        let position = propagator.position_at(Utc::now())?;
+
+        Ok(NormalizedPosition {
+            latitude: position.latitude,
+            longitude: position.longitude,
+            altitude: Some(position.altitude),
+            velocity: Some(position.velocity),
+            visibility: None,
+            timestamp: position.timestamp,
+            source: format!("tle:{}", tle.epoch.to_rfc3339()),
+            raw: Value::Null,
+        })
+    }
+}
+
+async fn get_json(client: &Client, url: &str) -> Result<Value, ApiError> {
+    let response = client.get(url).send().await?;
+    if !response.status().is_success() {
+        return Err(ApiError::upstream(
+            response.status().as_u16(),
+            format!("{} returned {}", url, response.status()),
+        ));
+    }
+    Ok(response.json().await?)
+}
+
+/// Широта/долгота из `obj`; строки и числа принимаются одинаково
+fn coordinates(obj: &Value, raw: &Value) -> Result<(f64, f64), ApiError> {
+    let lat = parse_number(&obj["latitude"]);
+    let lon = parse_number(&obj["longitude"]);
+    match (lat, lon) {
+        (Some(lat), Some(lon)) if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) => {
+            Ok((lat, lon))
+        }
+        _ => Err(ApiError::upstream(
+            502,
+            format!("Position payload has no valid coordinates: {}", raw),
+        )),
+    }
+}
+
+fn unix_timestamp(v: &Value) -> Option<DateTime<Utc>> {
+    let ts = v.as_i64().or_else(|| parse_number(v).map(|x| x as i64))?;
+    DateTime::from_timestamp(ts, 0)
+}
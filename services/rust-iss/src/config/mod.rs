@@ -13,6 +13,9 @@ pub struct AppConfig {
     pub nasa_api_url: String,
     pub nasa_api_key: String,
     pub where_iss_url: String,
+    pub open_notify_url: String,
+    pub tle_url: String,
+    pub tle_file: Option<String>,
     
     // Intervals (seconds)
     pub iss_every_seconds: u64,
@@ -21,6 +24,58 @@ pub struct AppConfig {
     pub neo_every_seconds: u64,
     pub donki_every_seconds: u64,
     pub spacex_every_seconds: u64,
+    pub tle_every_seconds: u64,
+    pub tle_check_every_seconds: u64,
+    pub anomaly_every_seconds: u64,
+    pub retention_every_seconds: u64,
+
+    // OSDR
+    /// Query-параметр upstream для фильтра по updated_at; пусто — фильтрация на нашей стороне
+    pub osdr_since_param: Option<String>,
+    /// Предел страниц за один прогон синхронизации
+    pub osdr_max_pages: u32,
+    /// JSON с правилами нормализации; пусто — встроенные правила
+    pub osdr_mapping_file: Option<String>,
+    /// Через сколько полных синхронизаций без набора в выдаче он считается отозванным; 0 — никогда
+    pub osdr_withdraw_after: u32,
+    /// Как часто плановая синхронизация проходит каталог целиком; 0 — только по запросу
+    pub osdr_full_sync_every_seconds: u64,
+    /// Стадия обогащения: карточка, ассаи и файлы каждого набора
+    pub osdr_enrich: bool,
+    /// Шаблоны URL обогащения: `{id}` — dataset_id, `{number}` — его номер
+    pub osdr_detail_url: String,
+    /// Пусто — ассаи берутся из карточки
+    pub osdr_assays_url: Option<String>,
+    pub osdr_files_url: String,
+    pub osdr_enrich_concurrency: usize,
+    /// Наборов за один прогон обогащения
+    pub osdr_enrich_batch: i64,
+    /// Каталог плановых выгрузок; пусто — выгрузка выключена
+    pub osdr_export_dir: Option<String>,
+    pub osdr_export_every_seconds: u64,
+    /// csv, xlsx, ndjson
+    pub osdr_export_formats: Vec<String>,
+
+    // Позиции спутников
+    /// Порядок опроса источников позиции: wheretheiss, opennotify, tle
+    pub iss_providers: Vec<String>,
+    /// Позиция старше стольких секунд считается устаревшей
+    pub iss_stale_seconds: i64,
+
+    // Хранение истории позиций, дни (0 — бессрочно)
+    pub raw_retention_days: i64,
+    pub rollup_minute_retention_days: i64,
+    pub rollup_hour_retention_days: i64,
+    pub rollup_day_retention_days: i64,
+
+    // Orbit
+    pub tle_stale_km: f64,
+
+    // Anomalies
+    pub anomaly_jump_factor: f64,
+    pub anomaly_velocity_z: f64,
+    pub anomaly_reboost_km: f64,
+    pub anomaly_decay_km_per_day: f64,
 }
 
 impl AppConfig {
@@ -38,6 +93,11 @@ impl AppConfig {
                 .unwrap_or_default(),
             where_iss_url: env::var("WHERE_ISS_URL")
                 .unwrap_or_else(|_| "https://api.wheretheiss.at/v1/satellites/25544".to_string()),
+            open_notify_url: env::var("OPEN_NOTIFY_URL")
+                .unwrap_or_else(|_| "http://api.open-notify.org/iss-now.json".to_string()),
+            tle_url: env::var("TLE_URL")
+                .unwrap_or_else(|_| "https://celestrak.org/NORAD/elements/gp.php?CATNR={norad_id}&FORMAT=tle".to_string()),
+            tle_file: env::var("TLE_FILE").ok().filter(|s| !s.is_empty()),
             
             iss_every_seconds: parse_env("ISS_EVERY_SECONDS", 120),
             osdr_every_seconds: parse_env("FETCH_EVERY_SECONDS", 600),
@@ -45,6 +105,52 @@ impl AppConfig {
             neo_every_seconds: parse_env("NEO_EVERY_SECONDS", 7200),
             donki_every_seconds: parse_env("DONKI_EVERY_SECONDS", 3600),
             spacex_every_seconds: parse_env("SPACEX_EVERY_SECONDS", 3600),
+            tle_every_seconds: parse_env("TLE_EVERY_SECONDS", 21600),
+            tle_check_every_seconds: parse_env("TLE_CHECK_EVERY_SECONDS", 1800),
+            anomaly_every_seconds: parse_env("ANOMALY_EVERY_SECONDS", 300),
+            retention_every_seconds: parse_env("RETENTION_EVERY_SECONDS", 3600),
+
+            osdr_since_param: env::var("OSDR_SINCE_PARAM").ok().filter(|s| !s.is_empty()),
+            osdr_max_pages: parse_env("OSDR_MAX_PAGES", 500),
+            osdr_mapping_file: env::var("OSDR_MAPPING_FILE").ok().filter(|s| !s.is_empty()),
+            osdr_withdraw_after: parse_env("OSDR_WITHDRAW_AFTER_SYNCS", 3),
+            osdr_full_sync_every_seconds: parse_env("OSDR_FULL_SYNC_EVERY_SECONDS", 86400),
+            osdr_enrich: parse_env("OSDR_ENRICH", false),
+            osdr_detail_url: env::var("OSDR_DETAIL_URL")
+                .unwrap_or_else(|_| "https://osdr.nasa.gov/osdr/data/osd/meta/{number}".to_string()),
+            osdr_assays_url: env::var("OSDR_ASSAYS_URL").ok().filter(|s| !s.is_empty()),
+            osdr_files_url: env::var("OSDR_FILES_URL")
+                .unwrap_or_else(|_| "https://osdr.nasa.gov/osdr/data/osd/files/{number}".to_string()),
+            osdr_enrich_concurrency: parse_env("OSDR_ENRICH_CONCURRENCY", 4),
+            osdr_enrich_batch: parse_env("OSDR_ENRICH_BATCH", 100),
+            osdr_export_dir: env::var("OSDR_EXPORT_DIR").ok().filter(|s| !s.is_empty()),
+            osdr_export_every_seconds: parse_env("OSDR_EXPORT_EVERY_SECONDS", 3600),
+            osdr_export_formats: env::var("OSDR_EXPORT_FORMATS")
+                .unwrap_or_else(|_| "csv".to_string())
+                .split(',')
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect(),
+
+            iss_providers: env::var("ISS_PROVIDERS")
+                .unwrap_or_else(|_| "wheretheiss,opennotify,tle".to_string())
+                .split(',')
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            iss_stale_seconds: parse_env("ISS_STALE_SECONDS", 300),
+
+            raw_retention_days: parse_env("RAW_RETENTION_DAYS", 90),
+            rollup_minute_retention_days: parse_env("ROLLUP_MINUTE_RETENTION_DAYS", 365),
+            rollup_hour_retention_days: parse_env("ROLLUP_HOUR_RETENTION_DAYS", 1825),
+            rollup_day_retention_days: parse_env("ROLLUP_DAY_RETENTION_DAYS", 0),
+
+            tle_stale_km: parse_env("TLE_STALE_KM", 50.0),
+
+            anomaly_jump_factor: parse_env("ANOMALY_JUMP_FACTOR", 1.5),
+            anomaly_velocity_z: parse_env("ANOMALY_VELOCITY_Z", 5.0),
+            anomaly_reboost_km: parse_env("ANOMALY_REBOOST_KM", 1.0),
+            anomaly_decay_km_per_day: parse_env("ANOMALY_DECAY_KM_PER_DAY", 0.5),
         })
     }
 }
@@ -0,0 +1,458 @@
+//! Поиск аномалий в ряду позиций: скачки координат, выбросы скорости, подъём и ускоренное снижение орбиты
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+use crate::domain::haversine_distance_km;
+use crate::domain::orbit::WGS84_A;
+
+/// Гравитационный параметр Земли, км³/с²
+const EARTH_MU: f64 = 398_600.441_8;
+/// Скорость, если источник её не сообщает, км/ч (с запасом для НОО)
+const FALLBACK_VELOCITY_KMH: f64 = 28_000.0;
+/// Сколько предыдущих точек берётся для оценки выброса скорости
+const VELOCITY_WINDOW: usize = 30;
+const VELOCITY_MIN_SAMPLES: usize = 10;
+/// Отклонения скорости меньше этого не считаются выбросом даже при малом σ, км/ч
+const VELOCITY_MIN_DEVIATION_KMH: f64 = 25.0;
+/// Доля витка, которую должны покрывать точки для усреднения высоты
+const ORBIT_COVERAGE: f64 = 0.8;
+const ORBIT_MIN_SAMPLES: usize = 5;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PositionSample {
+    pub fetch_id: i64,
+    pub at: DateTime<Utc>,
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: Option<f64>,
+    pub velocity: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AnomalyThresholds {
+    /// Во сколько раз наземная скорость между точками может превышать орбитальную
+    pub jump_factor: f64,
+    /// Порог выброса скорости в стандартных отклонениях
+    pub velocity_z: f64,
+    /// Рост средней за виток высоты, считающийся подъёмом орбиты, км
+    pub reboost_km: f64,
+    /// Скорость снижения орбиты, считающаяся аномальной, км/сутки
+    pub decay_km_per_day: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyKind {
+    PositionJump,
+    VelocityOutlier,
+    Reboost,
+    RapidDecay,
+}
+
+impl AnomalyKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::PositionJump => "position_jump",
+            Self::VelocityOutlier => "velocity_outlier",
+            Self::Reboost => "reboost",
+            Self::RapidDecay => "rapid_decay",
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "position_jump" => Some(Self::PositionJump),
+            "velocity_outlier" => Some(Self::VelocityOutlier),
+            "reboost" => Some(Self::Reboost),
+            "rapid_decay" => Some(Self::RapidDecay),
+            _ => None,
+        }
+    }
+}
+
+/// Найденная аномалия: `value` сравнивается с `threshold` в единицах конкретного вида
+#[derive(Debug, Clone)]
+pub struct DetectedAnomaly {
+    pub kind: AnomalyKind,
+    pub fetch_id: i64,
+    pub observed_at: DateTime<Utc>,
+    pub value: f64,
+    pub threshold: f64,
+    pub details: Value,
+}
+
+/// Ищет скачки, выбросы скорости и подъёмы орбиты в ряду, отсортированном по времени
+pub fn detect(samples: &[PositionSample], t: &AnomalyThresholds) -> Vec<DetectedAnomaly> {
+    let mut found = Vec::new();
+    found.extend(position_jumps(samples, t));
+    found.extend(velocity_outliers(samples, t));
+    found.extend(reboosts(samples, t));
+    found.sort_by_key(|a| (a.observed_at, a.fetch_id));
+    found
+}
+
+fn position_jumps(samples: &[PositionSample], t: &AnomalyThresholds) -> Vec<DetectedAnomaly> {
+    samples
+        .windows(2)
+        .filter_map(|w| {
+            let (prev, cur) = (&w[0], &w[1]);
+            let distance = haversine_distance_km(prev.lat, prev.lon, cur.lat, cur.lon);
+            let dt = ((cur.at - prev.at).num_milliseconds() as f64 / 1000.0).max(1.0);
+            let implied = distance / dt * 3600.0;
+
+            let velocity = cur
+                .velocity
+                .or(prev.velocity)
+                .unwrap_or(FALLBACK_VELOCITY_KMH);
+            let limit = velocity * t.jump_factor;
+
+            (implied > limit).then(|| DetectedAnomaly {
+                kind: AnomalyKind::PositionJump,
+                fetch_id: cur.fetch_id,
+                observed_at: cur.at,
+                value: implied,
+                threshold: limit,
+                details: json!({
+                    "previous_fetch_id": prev.fetch_id,
+                    "distance_km": distance,
+                    "seconds": dt,
+                }),
+            })
+        })
+        .collect()
+}
+
+fn velocity_outliers(samples: &[PositionSample], t: &AnomalyThresholds) -> Vec<DetectedAnomaly> {
+    let mut found = Vec::new();
+
+    for (i, cur) in samples.iter().enumerate() {
+        let Some(v) = cur.velocity else { continue };
+        let history: Vec<f64> = samples[i.saturating_sub(VELOCITY_WINDOW)..i]
+            .iter()
+            .filter_map(|s| s.velocity)
+            .collect();
+        if history.len() < VELOCITY_MIN_SAMPLES {
+            continue;
+        }
+
+        let (mean, std) = mean_std(&history);
+        let deviation = (v - mean).abs();
+        if deviation < VELOCITY_MIN_DEVIATION_KMH || std <= 0.0 {
+            continue;
+        }
+        let z = deviation / std;
+        if z > t.velocity_z {
+            found.push(DetectedAnomaly {
+                kind: AnomalyKind::VelocityOutlier,
+                fetch_id: cur.fetch_id,
+                observed_at: cur.at,
+                value: z,
+                threshold: t.velocity_z,
+                details: json!({
+                    "velocity": v,
+                    "mean": mean,
+                    "stddev": std,
+                }),
+            });
+        }
+    }
+
+    found
+}
+
+/// Подъём орбиты: средняя высота за последний виток выше средней за предыдущий
+fn reboosts(samples: &[PositionSample], t: &AnomalyThresholds) -> Vec<DetectedAnomaly> {
+    let with_alt: Vec<(&PositionSample, f64)> = samples
+        .iter()
+        .filter_map(|s| s.alt.map(|alt| (s, alt)))
+        .collect();
+    if with_alt.len() < ORBIT_MIN_SAMPLES * 2 {
+        return Vec::new();
+    }
+
+    let mut alts: Vec<f64> = with_alt.iter().map(|(_, alt)| *alt).collect();
+    alts.sort_by(|a, b| a.total_cmp(b));
+    let period = orbital_period_seconds(alts[alts.len() / 2]);
+
+    // Средняя высота за виток, заканчивающийся на каждой точке (через префиксные суммы)
+    let mut prefix = vec![0.0];
+    for (_, alt) in &with_alt {
+        prefix.push(prefix.last().copied().unwrap_or(0.0) + alt);
+    }
+    let secs = |i: usize| with_alt[i].0.at.timestamp_millis() as f64 / 1000.0;
+
+    let mut orbit_avg: Vec<Option<f64>> = Vec::with_capacity(with_alt.len());
+    let mut start = 0;
+    for i in 0..with_alt.len() {
+        while secs(i) - secs(start) > period {
+            start += 1;
+        }
+        let covered = secs(i) - secs(start) >= period * ORBIT_COVERAGE;
+        let count = i + 1 - start;
+        orbit_avg.push(
+            (covered && count >= ORBIT_MIN_SAMPLES)
+                .then(|| (prefix[i + 1] - prefix[start]) / count as f64),
+        );
+    }
+
+    let mut found = Vec::new();
+    let mut was_raised = false;
+    let mut prev_orbit = 0;
+    for i in 0..with_alt.len() {
+        // Последняя точка, отстоящая от текущей хотя бы на виток
+        while prev_orbit + 1 < i && secs(i) - secs(prev_orbit + 1) >= period {
+            prev_orbit += 1;
+        }
+        let delta = match (orbit_avg[i], orbit_avg[prev_orbit]) {
+            (Some(cur), Some(prev)) if secs(i) - secs(prev_orbit) >= period => cur - prev,
+            _ => {
+                was_raised = false;
+                continue;
+            }
+        };
+
+        let raised = delta > t.reboost_km;
+        if raised && !was_raised {
+            let sample = with_alt[i].0;
+            found.push(DetectedAnomaly {
+                kind: AnomalyKind::Reboost,
+                fetch_id: sample.fetch_id,
+                observed_at: sample.at,
+                value: delta,
+                threshold: t.reboost_km,
+                details: json!({
+                    "orbit_avg_altitude": orbit_avg[i],
+                    "previous_orbit_avg_altitude": orbit_avg[prev_orbit],
+                    "orbit_period_seconds": period,
+                }),
+            });
+        }
+        was_raised = raised;
+    }
+
+    found
+}
+
+/// Скорость снижения орбиты по линейной регрессии высоты, км/сутки (положительная — снижение)
+///
+/// Окно должно покрывать много витков, иначе колебания высоты за виток искажают наклон.
+pub fn decay_rate_km_per_day(samples: &[PositionSample]) -> Option<f64> {
+    let points: Vec<(f64, f64)> = samples
+        .iter()
+        .filter_map(|s| s.alt.map(|alt| (s.at.timestamp() as f64, alt)))
+        .collect();
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+    let mean_a = points.iter().map(|(_, a)| a).sum::<f64>() / n;
+    let (mut cov, mut var) = (0.0, 0.0);
+    for (t, a) in &points {
+        cov += (t - mean_t) * (a - mean_a);
+        var += (t - mean_t).powi(2);
+    }
+    if var <= 0.0 {
+        return None;
+    }
+
+    Some(-(cov / var) * 86_400.0)
+}
+
+fn orbital_period_seconds(altitude_km: f64) -> f64 {
+    let a = WGS84_A + altitude_km.max(0.0);
+    2.0 * std::f64::consts::PI * (a.powi(3) / EARTH_MU).sqrt()
+}
+
+fn mean_std(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+    (mean, var.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    const STEP_SECONDS: i64 = 60;
+    /// Сдвиг по долготе за шаг на экваторе: ~445 км за минуту, ~26 700 км/ч
+    const LON_STEP: f64 = 4.0;
+    const VELOCITY: f64 = 27_600.0;
+
+    const THRESHOLDS: AnomalyThresholds = AnomalyThresholds {
+        jump_factor: 1.5,
+        velocity_z: 4.0,
+        reboost_km: 1.0,
+        decay_km_per_day: 0.5,
+    };
+
+    /// Ряд по экватору с шагом в минуту; `alt` и `velocity` — от номера точки
+    fn series(
+        n: usize,
+        alt: impl Fn(usize) -> Option<f64>,
+        velocity: impl Fn(usize) -> Option<f64>,
+    ) -> Vec<PositionSample> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        (0..n)
+            .map(|i| PositionSample {
+                fetch_id: i as i64,
+                at: start + Duration::seconds(i as i64 * STEP_SECONDS),
+                lat: 0.0,
+                lon: wrap(i as f64 * LON_STEP),
+                alt: alt(i),
+                velocity: velocity(i),
+            })
+            .collect()
+    }
+
+    fn wrap(lon: f64) -> f64 {
+        (lon + 180.0).rem_euclid(360.0) - 180.0
+    }
+
+    /// Колебание высоты за виток, как у реальной эллиптической орбиты
+    fn wobble(i: usize) -> f64 {
+        let period = orbital_period_seconds(410.0);
+        5.0 * (2.0 * std::f64::consts::PI * (i as i64 * STEP_SECONDS) as f64 / period).sin()
+    }
+
+    /// Скорость с небольшим шумом, чтобы σ была ненулевой
+    fn noisy_velocity(i: usize) -> Option<f64> {
+        Some(VELOCITY + [-5.0, 0.0, 5.0][i % 3])
+    }
+
+    fn kinds(found: &[DetectedAnomaly], kind: AnomalyKind) -> Vec<&DetectedAnomaly> {
+        found.iter().filter(|a| a.kind == kind).collect()
+    }
+
+    #[test]
+    fn steady_series_has_no_anomalies() {
+        let samples = series(300, |i| Some(410.0 + wobble(i)), noisy_velocity);
+        assert!(detect(&samples, &THRESHOLDS).is_empty());
+
+        // Без скорости от источника используется запасная
+        let samples = series(50, |_| None, |_| None);
+        assert!(detect(&samples, &THRESHOLDS).is_empty());
+    }
+
+    #[test]
+    fn position_jump() {
+        let mut samples = series(30, |_| Some(410.0), noisy_velocity);
+        for s in &mut samples[10..] {
+            s.lon = wrap(s.lon + 60.0);
+        }
+
+        let found = detect(&samples, &THRESHOLDS);
+        let jumps = kinds(&found, AnomalyKind::PositionJump);
+        assert_eq!(jumps.len(), 1);
+        let jump = jumps[0];
+        assert_eq!(jump.fetch_id, 10);
+        assert_eq!(jump.details["previous_fetch_id"], 9);
+        assert!((jump.threshold - VELOCITY * 1.5).abs() < 10.0);
+        // 64° по экватору за минуту
+        let expected = 64.0 * 111.195 * 60.0;
+        assert!((jump.value - expected).abs() / expected < 0.01, "{}", jump.value);
+    }
+
+    #[test]
+    fn velocity_outlier() {
+        let samples = series(40, |_| Some(410.0), |i| if i == 20 { Some(VELOCITY + 100.0) } else { noisy_velocity(i) });
+
+        let found = detect(&samples, &THRESHOLDS);
+        let outliers = kinds(&found, AnomalyKind::VelocityOutlier);
+        assert_eq!(outliers.len(), 1);
+        assert_eq!(outliers[0].fetch_id, 20);
+        assert!(outliers[0].value > 20.0, "z = {}", outliers[0].value);
+        assert_eq!(outliers[0].threshold, THRESHOLDS.velocity_z);
+    }
+
+    #[test]
+    fn velocity_outlier_needs_history() {
+        // До VELOCITY_MIN_SAMPLES точек с историей выброс не оценивается
+        let early = VELOCITY_MIN_SAMPLES - 1;
+        let samples = series(20, |_| None, |i| if i == early { Some(VELOCITY + 500.0) } else { noisy_velocity(i) });
+        assert!(kinds(&detect(&samples, &THRESHOLDS), AnomalyKind::VelocityOutlier).is_empty());
+
+        let samples = series(20, |_| None, |i| {
+            if i == VELOCITY_MIN_SAMPLES {
+                Some(VELOCITY + 500.0)
+            } else {
+                noisy_velocity(i)
+            }
+        });
+        assert_eq!(kinds(&detect(&samples, &THRESHOLDS), AnomalyKind::VelocityOutlier).len(), 1);
+    }
+
+    #[test]
+    fn velocity_outlier_needs_minimum_deviation() {
+        // σ почти нулевая, z огромный, но отклонение меньше VELOCITY_MIN_DEVIATION_KMH
+        let tiny = |i: usize| Some(VELOCITY + [-0.1, 0.0, 0.1][i % 3]);
+        let samples = series(40, |_| None, |i| if i == 20 { Some(VELOCITY + 20.0) } else { tiny(i) });
+        assert!(kinds(&detect(&samples, &THRESHOLDS), AnomalyKind::VelocityOutlier).is_empty());
+
+        // Постоянная скорость: σ = 0, деления на ноль нет
+        let samples = series(40, |_| None, |i| Some(if i == 20 { VELOCITY + 100.0 } else { VELOCITY }));
+        assert!(kinds(&detect(&samples, &THRESHOLDS), AnomalyKind::VelocityOutlier).is_empty());
+    }
+
+    #[test]
+    fn reboost_step() {
+        let step_at = 150;
+        let samples = series(400, |i| Some(410.0 + wobble(i) + if i >= step_at { 3.0 } else { 0.0 }), noisy_velocity);
+
+        let found = detect(&samples, &THRESHOLDS);
+        let reboosts = kinds(&found, AnomalyKind::Reboost);
+        assert_eq!(reboosts.len(), 1, "{:?}", reboosts);
+        let reboost = reboosts[0];
+        let period_steps = (orbital_period_seconds(410.0) / STEP_SECONDS as f64).ceil() as i64;
+        assert!(
+            reboost.fetch_id > step_at as i64 && reboost.fetch_id <= step_at as i64 + period_steps,
+            "fetch {}",
+            reboost.fetch_id
+        );
+        assert!(reboost.value > THRESHOLDS.reboost_km && reboost.value <= 3.5, "{}", reboost.value);
+    }
+
+    #[test]
+    fn lowering_is_not_a_reboost() {
+        let samples = series(400, |i| Some(410.0 + wobble(i) - if i >= 150 { 3.0 } else { 0.0 }), noisy_velocity);
+        assert!(kinds(&detect(&samples, &THRESHOLDS), AnomalyKind::Reboost).is_empty());
+        // Меньше двух витков по ORBIT_MIN_SAMPLES точек — не с чем сравнивать
+        let samples = series(2 * ORBIT_MIN_SAMPLES - 1, |i| Some(400.0 + i as f64 * 10.0), |_| None);
+        assert!(kinds(&detect(&samples, &THRESHOLDS), AnomalyKind::Reboost).is_empty());
+    }
+
+    #[test]
+    fn decay_rate_of_linear_descent() {
+        let per_step = STEP_SECONDS as f64 / 86_400.0;
+        // Три дня, 0.2 км/сутки вниз поверх колебаний за виток
+        let samples = series(3 * 1440, |i| Some(420.0 - 0.2 * i as f64 * per_step + wobble(i)), |_| None);
+        let rate = decay_rate_km_per_day(&samples).unwrap();
+        assert!((rate - 0.2).abs() < 0.01, "{}", rate);
+
+        let samples = series(100, |i| Some(420.0 + 1.5 * i as f64 * per_step), |_| None);
+        assert!((decay_rate_km_per_day(&samples).unwrap() + 1.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn decay_rate_needs_spread() {
+        assert!(decay_rate_km_per_day(&series(1, |_| Some(410.0), |_| None)).is_none());
+        assert!(decay_rate_km_per_day(&series(10, |_| None, |_| None)).is_none());
+
+        let mut samples = series(5, |i| Some(410.0 + i as f64), |_| None);
+        let at = samples[0].at;
+        for s in &mut samples {
+            s.at = at;
+        }
+        assert!(decay_rate_km_per_day(&samples).is_none());
+    }
+
+    #[test]
+    fn period_of_iss_orbit() {
+        let minutes = orbital_period_seconds(410.0) / 60.0;
+        assert!((minutes - 92.8).abs() < 0.2, "{}", minutes);
+    }
+}
@@ -0,0 +1,225 @@
+//! Офлайн-геокодирование подспутниковой точки: страна или океан
+
+use country_boundaries::{CountryBoundaries, LatLon, BOUNDARIES_ODBL_360X180};
+use isocountry::CountryCode;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::geofence::polygon_contains;
+use crate::errors::ApiError;
+
+/// Атлантика с Карибским морем и Мексиканским заливом к западу от 20° в.д., [lat, lon].
+/// Западная граница идёт по суше Америк: Скалистые горы, перешейки Теуантепек
+/// и Панамский, Анды, меридиан мыса Горн; всё, что западнее, — Тихий океан
+const ATLANTIC: [[f64; 2]; 31] = [
+    [66.5, 20.0],
+    [66.5, -141.0],
+    [60.0, -130.0],
+    [55.0, -125.0],
+    [49.0, -120.0],
+    [40.0, -115.0],
+    [30.0, -108.0],
+    [23.0, -104.0],
+    [18.0, -100.0],
+    [17.0, -95.0],
+    [16.0, -92.0],
+    [15.0, -89.0],
+    [14.0, -86.5],
+    [12.0, -85.0],
+    [10.0, -84.0],
+    [8.7, -82.0],
+    [8.7, -80.0],
+    [9.2, -79.0],
+    [8.5, -77.8],
+    [7.0, -76.0],
+    [4.0, -74.0],
+    [0.0, -70.0],
+    [-10.0, -65.0],
+    [-20.0, -63.0],
+    [-30.0, -66.0],
+    [-40.0, -69.0],
+    [-50.0, -71.0],
+    [-54.0, -68.5],
+    [-56.0, -67.27],
+    [-60.0, -67.27],
+    [-60.0, 20.0],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeoKind {
+    Country,
+    Ocean,
+}
+
+impl GeoKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Country => "country",
+            Self::Ocean => "ocean",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "country" => Some(Self::Country),
+            "ocean" => Some(Self::Ocean),
+            _ => None,
+        }
+    }
+}
+
+/// Над чем находится спутник; code — ISO 3166-1 alpha-2 для стран
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeoLocation {
+    pub kind: GeoKind,
+    pub code: Option<String>,
+    pub name: String,
+}
+
+/// Границы стран OpenStreetMap (сетка 360x180), встроенные в бинарник
+pub struct ReverseGeocoder {
+    boundaries: CountryBoundaries,
+}
+
+impl ReverseGeocoder {
+    pub fn embedded() -> Result<Self, ApiError> {
+        let boundaries = CountryBoundaries::from_reader(BOUNDARIES_ODBL_360X180)
+            .map_err(|e| ApiError::internal(format!("Cannot load country boundaries: {}", e)))?;
+        Ok(Self { boundaries })
+    }
+
+    pub fn locate(&self, lat: f64, lon: f64) -> Option<GeoLocation> {
+        let position = LatLon::new(lat, normalize_lon(lon)).ok()?;
+        // ids отсортированы от меньшей территории к большей: регионы раньше стран
+        let country = self
+            .boundaries
+            .ids(position)
+            .into_iter()
+            .find(|id| !id.contains('-'));
+
+        Some(match country {
+            Some(code) => GeoLocation {
+                kind: GeoKind::Country,
+                code: Some(code.to_string()),
+                name: CountryCode::for_alpha2(code)
+                    .map(|c| c.name().to_string())
+                    .unwrap_or_else(|_| code.to_string()),
+            },
+            None => GeoLocation {
+                kind: GeoKind::Ocean,
+                code: None,
+                name: ocean_name(lat, normalize_lon(lon)).to_string(),
+            },
+        })
+    }
+}
+
+fn normalize_lon(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// Деление Мирового океана для точек вне суши; внутренние моря относятся
+/// к ближайшему океану
+fn ocean_name(lat: f64, lon: f64) -> &'static str {
+    if lat >= 66.5 {
+        return "Arctic Ocean";
+    }
+    if lat <= -60.0 {
+        return "Southern Ocean";
+    }
+    // Индийский: между Африкой и Австралией, южнее Азии
+    if (20.0..100.0).contains(&lon) && lat < 30.0 {
+        return "Indian Ocean";
+    }
+    if (100.0..147.0).contains(&lon) && lat < -10.0 {
+        return "Indian Ocean";
+    }
+    // Восточнее Африки севернее 30°: Средиземное, Чёрное, Каспийское моря
+    if (20.0..100.0).contains(&lon) {
+        return "Atlantic Ocean";
+    }
+    if lon < 20.0 && polygon_contains(&ATLANTIC, lat, lon) {
+        "Atlantic Ocean"
+    } else {
+        "Pacific Ocean"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ocean_names() {
+        let cases = [
+            // Центральная Америка: море по обе стороны перешейков
+            (14.0, -83.2, "Atlantic Ocean"),
+            (12.5, -83.0, "Atlantic Ocean"),
+            (11.5, -87.5, "Pacific Ocean"),
+            (13.2, -87.7, "Pacific Ocean"),
+            (16.2, -88.2, "Atlantic Ocean"),
+            (13.5, -91.0, "Pacific Ocean"),
+            (9.5, -79.9, "Atlantic Ocean"),
+            (8.5, -79.3, "Pacific Ocean"),
+            (7.5, -78.5, "Pacific Ocean"),
+            (9.0, -77.0, "Atlantic Ocean"),
+            (15.5, -95.0, "Pacific Ocean"),
+            (19.0, -95.0, "Atlantic Ocean"),
+            (25.0, -90.0, "Atlantic Ocean"),
+            (27.0, -111.0, "Pacific Ocean"),
+            // Южная Америка и пролив Дрейка
+            (-2.0, -82.0, "Pacific Ocean"),
+            (-10.0, -30.0, "Atlantic Ocean"),
+            (-58.0, -70.0, "Pacific Ocean"),
+            (-58.0, -62.0, "Atlantic Ocean"),
+            // Северная Америка
+            (60.0, -85.0, "Atlantic Ocean"),
+            (57.0, -145.0, "Pacific Ocean"),
+            (58.0, -175.0, "Pacific Ocean"),
+            (40.0, -40.0, "Atlantic Ocean"),
+            // Европа и Азия
+            (35.0, 18.0, "Atlantic Ocean"),
+            (43.0, 34.0, "Atlantic Ocean"),
+            (-20.0, 70.0, "Indian Ocean"),
+            (15.0, 88.0, "Indian Ocean"),
+            (15.0, 65.0, "Indian Ocean"),
+            (-36.0, 130.0, "Indian Ocean"),
+            (-40.0, 10.0, "Atlantic Ocean"),
+            (0.0, -150.0, "Pacific Ocean"),
+            (0.0, 170.0, "Pacific Ocean"),
+            (-40.0, 160.0, "Pacific Ocean"),
+            (12.0, 113.0, "Pacific Ocean"),
+            (80.0, 0.0, "Arctic Ocean"),
+            (70.0, -150.0, "Arctic Ocean"),
+            (-65.0, 0.0, "Southern Ocean"),
+            (-70.0, -100.0, "Southern Ocean"),
+        ];
+        for (lat, lon, expected) in cases {
+            assert_eq!(ocean_name(lat, lon), expected, "[{}, {}]", lat, lon);
+        }
+    }
+
+    #[test]
+    fn locate_land_and_water() {
+        let geocoder = ReverseGeocoder::embedded().unwrap();
+        let cases = [
+            (50.45, 30.52, GeoKind::Country, "UA"),
+            (-33.87, 151.21, GeoKind::Country, "AU"),
+            (12.1, -86.3, GeoKind::Country, "NI"),
+            (14.0, -82.5, GeoKind::Ocean, "Atlantic Ocean"),
+            (11.0, -87.5, GeoKind::Ocean, "Pacific Ocean"),
+            (0.0, -140.0, GeoKind::Ocean, "Pacific Ocean"),
+            // Долгота за пределами ±180 приводится к диапазону
+            (0.0, 220.0, GeoKind::Ocean, "Pacific Ocean"),
+        ];
+        for (lat, lon, kind, expected) in cases {
+            let location = geocoder.locate(lat, lon).unwrap();
+            assert_eq!(location.kind, kind, "[{}, {}]", lat, lon);
+            match kind {
+                GeoKind::Country => assert_eq!(location.code.as_deref(), Some(expected)),
+                GeoKind::Ocean => assert_eq!(location.name, expected),
+            }
+        }
+        assert!(geocoder.locate(91.0, 0.0).is_none());
+    }
+}
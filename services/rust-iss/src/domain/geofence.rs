@@ -0,0 +1,196 @@
+//! Геозоны: круги и многоугольники на поверхности Земли
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::haversine_distance_km;
+use crate::errors::ApiError;
+
+/// Форма зоны; вершины многоугольника задаются как [lat, lon]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum GeofenceShape {
+    Circle { lat: f64, lon: f64, radius_km: f64 },
+    Polygon { coordinates: Vec<[f64; 2]> },
+}
+
+impl GeofenceShape {
+    pub fn validate(&self) -> Result<(), ApiError> {
+        match self {
+            Self::Circle { lat, lon, radius_km } => {
+                check_point(*lat, *lon)?;
+                if !(radius_km.is_finite() && *radius_km > 0.0) {
+                    return Err(ApiError::validation("radius_km must be positive"));
+                }
+            }
+            Self::Polygon { coordinates } => {
+                if coordinates.len() < 3 {
+                    return Err(ApiError::validation("Polygon needs at least 3 vertices"));
+                }
+                for [lat, lon] in coordinates {
+                    check_point(*lat, *lon)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        match self {
+            Self::Circle { lat: c_lat, lon: c_lon, radius_km } => {
+                haversine_distance_km(*c_lat, *c_lon, lat, lon) <= *radius_km
+            }
+            Self::Polygon { coordinates } => polygon_contains(coordinates, lat, lon),
+        }
+    }
+}
+
+fn check_point(lat: f64, lon: f64) -> Result<(), ApiError> {
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(ApiError::validation(format!("Invalid coordinates: [{}, {}]", lat, lon)));
+    }
+    Ok(())
+}
+
+/// Ray casting в плоскости lat/lon; многоугольник «разворачивается» относительно
+/// первой вершины, поэтому зоны через антимеридиан тоже работают
+pub(crate) fn polygon_contains(vertices: &[[f64; 2]], lat: f64, lon: f64) -> bool {
+    let base = vertices[0][1];
+    let mut prev_lon = base;
+    let unwrapped: Vec<(f64, f64)> = vertices
+        .iter()
+        .map(|[v_lat, v_lon]| {
+            let lon = prev_lon + wrap180(v_lon - prev_lon);
+            prev_lon = lon;
+            (*v_lat, lon)
+        })
+        .collect();
+
+    // Точку приводим к той же «ветви» долготы, что и многоугольник
+    let (min_lon, max_lon) = unwrapped
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(lo, hi), (_, l)| (lo.min(*l), hi.max(*l)));
+    let center = (min_lon + max_lon) / 2.0;
+    let lon = center + wrap180(lon - center);
+
+    let mut inside = false;
+    let n = unwrapped.len();
+    for i in 0..n {
+        let (lat_i, lon_i) = unwrapped[i];
+        let (lat_j, lon_j) = unwrapped[(i + n - 1) % n];
+        if (lat_i > lat) != (lat_j > lat) {
+            let cross_lon = lon_i + (lat - lat_i) / (lat_j - lat_i) * (lon_j - lon_i);
+            if lon < cross_lon {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+fn wrap180(deg: f64) -> f64 {
+    (deg + 180.0).rem_euclid(360.0) - 180.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polygon(coordinates: &[[f64; 2]]) -> GeofenceShape {
+        GeofenceShape::Polygon { coordinates: coordinates.to_vec() }
+    }
+
+    fn square(lon_from: f64, lon_to: f64) -> GeofenceShape {
+        polygon(&[[0.0, lon_from], [0.0, lon_to], [10.0, lon_to], [10.0, lon_from]])
+    }
+
+    #[test]
+    fn circle_contains() {
+        let circle = GeofenceShape::Circle { lat: 0.0, lon: 0.0, radius_km: 100.0 };
+        assert!(circle.contains(0.0, 0.0));
+        assert!(circle.contains(0.5, 0.5));
+        assert!(!circle.contains(0.0, 1.0));
+        assert!(!circle.contains(0.0, 180.0));
+    }
+
+    #[test]
+    fn circle_boundary_is_inside() {
+        let radius_km = haversine_distance_km(40.0, 20.0, 40.0, 21.0);
+        let circle = GeofenceShape::Circle { lat: 40.0, lon: 20.0, radius_km };
+        assert!(circle.contains(40.0, 21.0));
+        assert!(!circle.contains(40.0, 21.001));
+    }
+
+    #[test]
+    fn circle_across_antimeridian() {
+        let circle = GeofenceShape::Circle { lat: 0.0, lon: 179.9, radius_km: 50.0 };
+        assert!(circle.contains(0.0, -179.9));
+        assert!(!circle.contains(0.0, -179.0));
+    }
+
+    #[test]
+    fn polygon_contains() {
+        let square = square(0.0, 10.0);
+        for (lat, lon, inside) in [
+            (5.0, 5.0, true),
+            (0.1, 9.9, true),
+            (15.0, 5.0, false),
+            (5.0, -1.0, false),
+            (5.0, 11.0, false),
+            (-5.0, -5.0, false),
+        ] {
+            assert_eq!(square.contains(lat, lon), inside, "[{}, {}]", lat, lon);
+        }
+    }
+
+    #[test]
+    fn concave_polygon() {
+        // Буква «Г»: вырез в правом верхнем углу снаружи
+        let shape = polygon(&[[0.0, 0.0], [0.0, 10.0], [5.0, 10.0], [5.0, 5.0], [10.0, 5.0], [10.0, 0.0]]);
+        assert!(shape.contains(2.0, 8.0));
+        assert!(shape.contains(8.0, 2.0));
+        assert!(!shape.contains(8.0, 8.0));
+    }
+
+    #[test]
+    fn polygon_across_antimeridian() {
+        let shapes = [
+            polygon(&[[-10.0, 170.0], [-10.0, -170.0], [10.0, -170.0], [10.0, 170.0]]),
+            // Та же зона, обход начинается с западной стороны ±180°
+            polygon(&[[-10.0, -170.0], [10.0, -170.0], [10.0, 170.0], [-10.0, 170.0]]),
+            polygon(&[[10.0, -170.0], [-10.0, -170.0], [-10.0, 170.0], [10.0, 170.0]]),
+        ];
+        for shape in &shapes {
+            for lon in [180.0, -180.0, 175.0, -175.0, 170.5, -170.5] {
+                assert!(shape.contains(0.0, lon), "{:?} lon {}", shape, lon);
+            }
+            for lon in [0.0, 160.0, -160.0, 90.0, -90.0] {
+                assert!(!shape.contains(0.0, lon), "{:?} lon {}", shape, lon);
+            }
+            assert!(!shape.contains(20.0, 180.0));
+        }
+    }
+
+    #[test]
+    fn polygon_boundary_belongs_to_one_side() {
+        // Ray casting полуоткрыт: южная и западная стороны внутри, северная и восточная — нет,
+        // поэтому точка на общей стороне соседних зон попадает ровно в одну
+        let west = square(0.0, 10.0);
+        let east = square(10.0, 20.0);
+        assert!(west.contains(5.0, 0.0));
+        assert!(west.contains(0.0, 5.0));
+        assert!(!west.contains(10.0, 5.0));
+        assert!(!west.contains(5.0, 10.0));
+        for lat in [0.0, 2.5, 5.0, 9.9] {
+            assert!(west.contains(lat, 10.0) != east.contains(lat, 10.0), "lat {}", lat);
+        }
+    }
+
+    #[test]
+    fn validate_shapes() {
+        assert!(GeofenceShape::Circle { lat: 0.0, lon: 0.0, radius_km: 0.0 }.validate().is_err());
+        assert!(GeofenceShape::Circle { lat: 91.0, lon: 0.0, radius_km: 1.0 }.validate().is_err());
+        assert!(polygon(&[[0.0, 0.0], [1.0, 1.0]]).validate().is_err());
+        assert!(polygon(&[[0.0, 0.0], [1.0, 181.0], [2.0, 0.0]]).validate().is_err());
+        assert!(square(170.0, 180.0).validate().is_ok());
+    }
+}
@@ -1,10 +1,23 @@
+pub mod anomaly;
+pub mod geocode;
+pub mod geofence;
 pub mod models;
+pub mod orbit;
+pub mod osdr;
+pub mod osdr_enrichment;
+pub mod osdr_export;
+pub mod osdr_mapping;
+pub mod pagination;
+pub mod track;
+pub mod xlsx;
 
 pub use models::*;
 
-use chrono::{DateTime, Utc};
 use serde_json::Value;
 
+/// NORAD ID МКС — спутник по умолчанию для /api/iss/*
+pub const ISS_NORAD_ID: i64 = 25544;
+
 pub fn parse_number(v: &Value) -> Option<f64> {
     if let Some(x) = v.as_f64() {
         return Some(x);
@@ -42,23 +55,3 @@ pub fn extract_string(json: &Value, keys: &[&str]) -> Option<String> {
     }
     None
 }
-
-pub fn extract_timestamp(json: &Value, keys: &[&str]) -> Option<DateTime<Utc>> {
-    use chrono::NaiveDateTime;
-    
-    for key in keys {
-        if let Some(val) = json.get(*key) {
-            if let Some(s) = val.as_str() {
-                if let Ok(dt) = s.parse::<DateTime<Utc>>() {
-                    return Some(dt);
-                }
-                if let Ok(ndt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S") {
-                    return Some(DateTime::from_naive_utc_and_offset(ndt, Utc));
-                }
-            } else if let Some(n) = val.as_i64() {
-                return DateTime::from_timestamp(n, 0);
-            }
-        }
-    }
-    None
-}
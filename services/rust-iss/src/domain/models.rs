@@ -1,55 +1,426 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
+
+use super::geocode::{GeoKind, GeoLocation};
+use super::geofence::GeofenceShape;
+use super::parse_number;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IssFetchLog {
     pub id: i64,
+    pub norad_id: i64,
     pub fetched_at: DateTime<Utc>,
     pub source_url: String,
     pub payload: Value,
+    /// Источник позиции: wheretheiss, opennotify, tle
+    pub provider: Option<String>,
+    /// Страна или океан под спутником на момент опроса
+    pub location: Option<GeoLocation>,
 }
 
 impl IssFetchLog {
     pub fn lat(&self) -> Option<f64> {
-        Self::parse_number(&self.payload["latitude"])
+        parse_number(&self.payload["latitude"])
     }
     
     pub fn lon(&self) -> Option<f64> {
-        Self::parse_number(&self.payload["longitude"])
+        parse_number(&self.payload["longitude"])
     }
     
     pub fn altitude(&self) -> Option<f64> {
-        Self::parse_number(&self.payload["altitude"])
+        parse_number(&self.payload["altitude"])
     }
     
     pub fn velocity(&self) -> Option<f64> {
-        Self::parse_number(&self.payload["velocity"])
+        parse_number(&self.payload["velocity"])
     }
     
     pub fn visibility(&self) -> Option<String> {
         self.payload["visibility"].as_str().map(|s| s.to_string())
     }
-    
-    fn parse_number(v: &Value) -> Option<f64> {
-        if let Some(x) = v.as_f64() {
-            return Some(x);
+
+    /// Момент фиксации позиции по данным источника (fallback — время запроса)
+    pub fn position_at(&self) -> DateTime<Utc> {
+        self.payload["timestamp"]
+            .as_i64()
+            .and_then(|ts| DateTime::from_timestamp(ts, 0))
+            .unwrap_or(self.fetched_at)
+    }
+
+    /// Краткое представление позиции для потоковых каналов (WS/SSE)
+    pub fn position_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "norad_id": self.norad_id,
+            "latitude": self.lat(),
+            "longitude": self.lon(),
+            "altitude": self.altitude(),
+            "velocity": self.velocity(),
+            "visibility": self.visibility(),
+            "provider": self.provider,
+            "location": self.location,
+            "timestamp": self.fetched_at.to_string(),
+        })
+    }
+}
+
+/// Позиция от любого источника, приведённая к формату wheretheiss.at
+#[derive(Debug, Clone)]
+pub struct NormalizedPosition {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// км
+    pub altitude: Option<f64>,
+    /// км/ч
+    pub velocity: Option<f64>,
+    pub visibility: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    /// URL запроса или описание расчёта
+    pub source: String,
+    /// Ответ источника как есть
+    pub raw: Value,
+}
+
+impl NormalizedPosition {
+    /// payload для iss_fetch_log: поля wheretheiss.at плюс исходный ответ в raw
+    pub fn to_payload(&self) -> Value {
+        json!({
+            "latitude": self.latitude,
+            "longitude": self.longitude,
+            "altitude": self.altitude,
+            "velocity": self.velocity,
+            "visibility": self.visibility,
+            "timestamp": self.timestamp.timestamp(),
+            "raw": self.raw,
+        })
+    }
+}
+
+/// Спутник из реестра фонового опроса
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Satellite {
+    pub norad_id: i64,
+    pub name: String,
+    pub source_url: String,
+    pub interval_seconds: i32,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Шаг агрегации тренда
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrendBucket {
+    #[serde(rename = "minute")]
+    Minute,
+    #[serde(rename = "5min")]
+    FiveMinutes,
+    #[serde(rename = "hour")]
+    Hour,
+    #[serde(rename = "day")]
+    Day,
+}
+
+impl TrendBucket {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_lowercase().as_str() {
+            "minute" | "1min" => Some(Self::Minute),
+            "5min" | "5minute" => Some(Self::FiveMinutes),
+            "hour" | "1h" => Some(Self::Hour),
+            "day" | "1d" => Some(Self::Day),
+            _ => None,
+        }
+    }
+
+    pub fn seconds(self) -> i64 {
+        match self {
+            Self::Minute => 60,
+            Self::FiveMinutes => 300,
+            Self::Hour => 3600,
+            Self::Day => 86400,
+        }
+    }
+}
+
+/// Разрешение агрегатов, которые ведутся параллельно сырому логу
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RollupResolution {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl RollupResolution {
+    pub const ALL: [RollupResolution; 3] = [Self::Minute, Self::Hour, Self::Day];
+
+    pub fn seconds(self) -> i64 {
+        match self {
+            Self::Minute => 60,
+            Self::Hour => 3600,
+            Self::Day => 86400,
+        }
+    }
+
+    pub fn table(self) -> &'static str {
+        match self {
+            Self::Minute => "iss_rollup_minute",
+            Self::Hour => "iss_rollup_hour",
+            Self::Day => "iss_rollup_day",
+        }
+    }
+
+    /// Самые крупные агрегаты, из которых ещё собирается интервал тренда
+    pub fn for_bucket(bucket: TrendBucket) -> Self {
+        match bucket {
+            TrendBucket::Minute | TrendBucket::FiveMinutes => Self::Minute,
+            TrendBucket::Hour => Self::Hour,
+            TrendBucket::Day => Self::Day,
+        }
+    }
+}
+
+/// Сроки хранения в днях; 0 — хранить бессрочно
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    pub raw_days: i64,
+    pub minute_days: i64,
+    pub hour_days: i64,
+    pub day_days: i64,
+}
+
+impl RetentionPolicy {
+    pub fn rollup_days(&self, resolution: RollupResolution) -> i64 {
+        match resolution {
+            RollupResolution::Minute => self.minute_days,
+            RollupResolution::Hour => self.hour_days,
+            RollupResolution::Day => self.day_days,
+        }
+    }
+}
+
+/// Итог пересборки агрегатов одного разрешения
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollupRebuild {
+    pub resolution: RollupResolution,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub buckets: u64,
+}
+
+/// Показатель позиции, по которому строится тренд
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrendMetric {
+    Latitude,
+    Longitude,
+    Altitude,
+    Velocity,
+}
+
+impl TrendMetric {
+    pub const ALL: [TrendMetric; 4] = [
+        Self::Latitude,
+        Self::Longitude,
+        Self::Altitude,
+        Self::Velocity,
+    ];
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_lowercase().as_str() {
+            "latitude" | "lat" => Some(Self::Latitude),
+            "longitude" | "lon" => Some(Self::Longitude),
+            "altitude" | "alt" => Some(Self::Altitude),
+            "velocity" | "vel" => Some(Self::Velocity),
+            _ => None,
         }
-        if let Some(s) = v.as_str() {
-            return s.parse::<f64>().ok();
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Latitude => "latitude",
+            Self::Longitude => "longitude",
+            Self::Altitude => "altitude",
+            Self::Velocity => "velocity",
+        }
+    }
+
+    /// Имя поля со средним в прежнем формате ответа `/trend`
+    pub fn legacy_avg_field(self) -> &'static str {
+        match self {
+            Self::Latitude => "avg_lat",
+            Self::Longitude => "avg_lon",
+            Self::Altitude => "avg_altitude",
+            Self::Velocity => "avg_velocity",
         }
-        None
     }
 }
 
+/// Статистика показателя внутри интервала; для долготы avg и stddev — круговые
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricStats {
+    pub avg: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub stddev: Option<f64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IssTrend {
-    pub hour: String,
-    pub avg_lat: f64,
-    pub avg_lon: f64,
-    pub avg_altitude: f64,
-    pub avg_velocity: f64,
+    pub bucket_start: DateTime<Utc>,
     pub cnt: i64,
+    /// Доля ожидаемых по интервалу опроса точек; None, если интервал неизвестен
+    pub coverage: Option<f64>,
+    pub latitude: MetricStats,
+    pub longitude: MetricStats,
+    pub altitude: MetricStats,
+    pub velocity: MetricStats,
+}
+
+impl IssTrend {
+    pub fn metric(&self, metric: TrendMetric) -> &MetricStats {
+        match metric {
+            TrendMetric::Latitude => &self.latitude,
+            TrendMetric::Longitude => &self.longitude,
+            TrendMetric::Altitude => &self.altitude,
+            TrendMetric::Velocity => &self.velocity,
+        }
+    }
+}
+
+impl IssTrend {
+    /// Пустой интервал, в котором не было ни одной точки
+    pub fn empty(bucket_start: DateTime<Utc>) -> Self {
+        Self {
+            bucket_start,
+            cnt: 0,
+            coverage: Some(0.0),
+            latitude: MetricStats::default(),
+            longitude: MetricStats::default(),
+            altitude: MetricStats::default(),
+            velocity: MetricStats::default(),
+        }
+    }
+}
+
+/// Заполненность интервала относительно интервала опроса спутника
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverageBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub samples: i64,
+    pub expected: f64,
+    pub coverage: f64,
+}
+
+/// Пропуск в логе позиций с наиболее вероятной причиной
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataGap {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub duration_seconds: i64,
+    pub missing_samples: i64,
+    /// upstream_error, rate_limited, database_error, polling_disabled, collector_down
+    pub cause: String,
+    pub error_count: i64,
+    pub error_codes: Vec<String>,
+    /// Пропуск ещё не закрыт новой точкой
+    pub open: bool,
+}
+
+/// Отчёт о полноте лога позиций за окно
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverageReport {
+    pub norad_id: i64,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub interval_seconds: i32,
+    pub samples: i64,
+    pub expected: f64,
+    pub coverage: f64,
+    pub buckets: Vec<CoverageBucket>,
+    pub gaps: Vec<DataGap>,
+}
+
+/// Неудачный опрос источника позиций
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetchError {
+    pub occurred_at: DateTime<Utc>,
+    pub code: String,
+    pub message: String,
+}
+
+/// Сохранённая аномалия орбиты
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssAnomaly {
+    pub id: i64,
+    pub norad_id: i64,
+    pub kind: String,
+    pub fetch_id: i64,
+    pub observed_at: DateTime<Utc>,
+    pub detected_at: DateTime<Utc>,
+    pub value: f64,
+    pub threshold: f64,
+    pub details: Value,
+}
+
+/// Геозона; без norad_id применяется ко всем спутникам
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Geofence {
+    pub id: i64,
+    pub name: String,
+    pub shape: GeofenceShape,
+    pub norad_id: Option<i64>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Вход спутника в геозону или выход из неё
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeofenceEvent {
+    pub id: i64,
+    pub geofence_id: i64,
+    pub norad_id: i64,
+    /// enter или exit
+    pub event: String,
+    pub fetch_id: i64,
+    pub occurred_at: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Вход или выход, который записывается вместе с новым состоянием зоны
+#[derive(Debug, Clone)]
+pub struct GeofenceCrossing {
+    pub event: &'static str,
+    pub fetch_id: i64,
+    pub occurred_at: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Время, проведённое спутником над страной или океаном
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DwellTime {
+    /// None — позиции без аннотации
+    pub kind: Option<GeoKind>,
+    pub code: Option<String>,
+    pub name: Option<String>,
+    pub seconds: f64,
+    pub share: f64,
+    pub samples: i64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Распределение времени по странам и океанам за окно
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DwellReport {
+    pub norad_id: i64,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub total_seconds: f64,
+    pub regions: Vec<DwellTime>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,6 +434,222 @@ pub struct OsdrItem {
     pub updated_at: Option<DateTime<Utc>>,
     pub inserted_at: DateTime<Utc>,
     pub raw: Value,
+    /// Начало последней синхронизации, в выдаче которой был набор
+    pub last_seen_at: Option<DateTime<Utc>>,
+    /// Набор пропал из выдачи OSDR; когда и почему помечен
+    pub withdrawn_at: Option<DateTime<Utc>>,
+    pub withdrawn_reason: Option<String>,
+    /// Заполняется только в результатах полнотекстового поиска
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<OsdrSearchHit>,
+}
+
+/// Релевантность и подсветка совпадений (`<mark>`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrSearchHit {
+    pub rank: f32,
+    pub title: Option<String>,
+    pub snippet: Option<String>,
+}
+
+/// Значение фасета и число наборов с ним
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+/// Счётчики для боковой панели фильтров
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OsdrFacets {
+    pub organism: Vec<FacetCount>,
+    pub study_type: Vec<FacetCount>,
+    pub status: Vec<FacetCount>,
+}
+
+/// Связанный набор OSDR и причина связи
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrRelated {
+    pub dataset_id: String,
+    pub title: Option<String>,
+    /// referenced — упомянут в raw; similar — тот же организм и тип исследования
+    pub relation: String,
+}
+
+/// Ассай набора OSDR
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrAssay {
+    pub name: Option<String>,
+    pub measurement_type: Option<String>,
+    pub technology_type: Option<String>,
+    pub platform: Option<String>,
+    pub raw: Value,
+}
+
+/// Файл набора OSDR
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrFile {
+    pub file_name: String,
+    pub category: Option<String>,
+    pub subcategory: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub url: Option<String>,
+    pub raw: Value,
+}
+
+/// Итог стадии обогащения
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OsdrEnrichReport {
+    pub datasets: u64,
+    pub assays: u64,
+    pub files: u64,
+    pub failed: u64,
+    pub errors: Vec<OsdrEnrichError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrEnrichError {
+    pub dataset_id: String,
+    pub reason: String,
+}
+
+/// Записи без dataset_id, которые получат один и тот же идентификатор
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrDuplicateGroup {
+    pub dataset_id: String,
+    pub ids: Vec<i64>,
+    /// Строка, в которую слиты остальные (после применения)
+    pub survivor: Option<i64>,
+}
+
+/// Итог разовой дедупликации osdr_items
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrDedupReport {
+    pub applied: bool,
+    /// Записей без dataset_id до запуска
+    pub unidentified: u64,
+    /// Удалено дублей
+    pub merged: u64,
+    pub groups: Vec<OsdrDuplicateGroup>,
+}
+
+/// Чем закончилась запись одного набора при синхронизации
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OsdrWriteOutcome {
+    Inserted,
+    Updated,
+    Unchanged,
+}
+
+/// Версия записи OSDR: что изменилось по сравнению с предыдущей
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrItemVersion {
+    pub version: i32,
+    pub changed_at: DateTime<Utc>,
+    pub diff: Value,
+}
+
+/// Состояние синхронизации источника из osdr_sync_state
+#[derive(Debug, Clone, Default)]
+pub struct OsdrSyncState {
+    pub watermark: Option<DateTime<Utc>>,
+    pub last_full_sync_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OsdrSyncMode {
+    /// Весь каталог, водяной знак не учитывается
+    Full,
+    /// Только записи новее сохранённого водяного знака updated_at
+    Incremental,
+}
+
+impl OsdrSyncMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Incremental => "incremental",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "full" => Some(Self::Full),
+            "incremental" => Some(Self::Incremental),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OsdrSyncStatus {
+    Completed,
+    /// Прогон прерван ошибкой upstream или БД; записанные страницы остаются
+    Failed,
+}
+
+impl OsdrSyncStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "completed" => Some(Self::Completed),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Запись, которую не удалось сохранить
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrSyncItemError {
+    pub page: u32,
+    pub dataset_id: Option<String>,
+    pub reason: String,
+}
+
+/// Итог одного прогона синхронизации OSDR
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrSyncReport {
+    /// Появляется после сохранения в osdr_sync_reports
+    pub id: Option<i64>,
+    pub mode: OsdrSyncMode,
+    pub status: OsdrSyncStatus,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub pages: u32,
+    pub items_seen: u64,
+    pub inserted: u64,
+    pub updated: u64,
+    pub unchanged: u64,
+    /// Не изменились с прошлого прогона (updated_at старше водяного знака)
+    pub skipped: u64,
+    pub failed: u64,
+    /// Остановились на лимите страниц, водяной знак не сдвигался
+    pub truncated: bool,
+    /// Помечено отозванными по итогам полной синхронизации
+    pub withdrawn: u64,
+    /// Стадия обогащения; None — выключена или не запускалась
+    pub enrichment: Option<OsdrEnrichReport>,
+    pub watermark_before: Option<DateTime<Utc>>,
+    pub watermark_after: Option<DateTime<Utc>>,
+    /// Причина остановки для status = failed
+    pub error: Option<String>,
+    pub errors: Vec<OsdrSyncItemError>,
+}
+
+impl OsdrSyncReport {
+    pub fn written(&self) -> u64 {
+        self.inserted + self.updated
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -73,9 +660,72 @@ pub struct SpaceCache {
     pub payload: Value,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Health {
-    pub status: String,
-    pub now: DateTime<Utc>,
-    pub version: String,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TleSet {
+    pub id: i64,
+    pub norad_id: i64,
+    pub object_name: Option<String>,
+    pub line1: String,
+    pub line2: String,
+    pub epoch: DateTime<Utc>,
+    pub source: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TleCheck {
+    pub id: i64,
+    pub checked_at: DateTime<Utc>,
+    pub norad_id: i64,
+    pub tle_epoch: DateTime<Utc>,
+    pub fix_at: DateTime<Utc>,
+    pub observed: GeoPoint,
+    pub predicted: GeoPoint,
+    pub error_km: f64,
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+}
+
+/// Позиция спутника, рассчитанная SGP4 по TLE
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrbitPosition {
+    pub timestamp: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+    pub velocity: f64,
+}
+
+/// Точка наблюдателя на поверхности Земли
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Observer {
+    pub lat: f64,
+    pub lon: f64,
+    /// Высота над уровнем моря, м
+    pub alt_m: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PassPoint {
+    pub time: DateTime<Utc>,
+    pub azimuth: f64,
+    pub elevation: f64,
+}
+
+/// Пролёт спутника над наблюдателем (от восхода до захода за горизонт)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SatellitePass {
+    pub rise: PassPoint,
+    pub culmination: PassPoint,
+    pub set: PassPoint,
+    pub max_elevation: f64,
+    pub duration_seconds: i64,
+    /// Спутник освещён Солнцем, а у наблюдателя темно
+    pub visible: bool,
 }
@@ -0,0 +1,426 @@
+//! Орбитальная механика: разбор TLE, SGP4-прогноз и перевод TEME → географические координаты
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::domain::{Observer, OrbitPosition, PassPoint, SatellitePass};
+use crate::errors::ApiError;
+
+/// Экваториальный радиус WGS84, км
+pub const WGS84_A: f64 = 6378.137;
+/// Сжатие WGS84
+pub const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+/// Строки одного TLE, найденные в тексте (2LE или 3LE)
+#[derive(Debug, Clone)]
+pub struct TleLines {
+    pub name: Option<String>,
+    pub line1: String,
+    pub line2: String,
+}
+
+/// Разбирает текст в формате 2LE/3LE (как отдаёт Celestrak)
+pub fn parse_tle_text(text: &str) -> Vec<TleLines> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|l| l.trim_end())
+        .filter(|l| !l.trim().is_empty())
+        .collect();
+
+    let mut result = Vec::new();
+    let mut name: Option<String> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if line.starts_with("1 ") && i + 1 < lines.len() && lines[i + 1].starts_with("2 ") {
+            result.push(TleLines {
+                name: name.take(),
+                line1: line.to_string(),
+                line2: lines[i + 1].to_string(),
+            });
+            i += 2;
+            continue;
+        }
+        name = Some(line.trim().trim_start_matches("0 ").to_string());
+        i += 1;
+    }
+    result
+}
+
+/// Готовый к расчётам SGP4-пропагатор для одного TLE
+pub struct Propagator {
+    elements: sgp4::Elements,
+    constants: sgp4::Constants,
+}
+
+impl Propagator {
+    pub fn from_tle(name: Option<String>, line1: &str, line2: &str) -> Result<Self, ApiError> {
+        let elements = sgp4::Elements::from_tle(name, line1.as_bytes(), line2.as_bytes())
+            .map_err(|e| ApiError::validation(format!("Invalid TLE: {}", e)))?;
+        let constants = sgp4::Constants::from_elements(&elements)
+            .map_err(|e| ApiError::validation(format!("Unsupported TLE elements: {}", e)))?;
+
+        Ok(Self { elements, constants })
+    }
+
+    pub fn norad_id(&self) -> u64 {
+        self.elements.norad_id
+    }
+
+    pub fn object_name(&self) -> Option<String> {
+        self.elements.object_name.clone()
+    }
+
+    pub fn epoch(&self) -> DateTime<Utc> {
+        self.elements.datetime.and_utc()
+    }
+
+    /// Положение и скорость в системе TEME (км, км/с)
+    pub fn teme_at(&self, at: DateTime<Utc>) -> Result<([f64; 3], [f64; 3]), ApiError> {
+        let minutes = self
+            .elements
+            .datetime_to_minutes_since_epoch(&at.naive_utc())
+            .map_err(|e| ApiError::validation(e.to_string()))?;
+        let prediction = self
+            .constants
+            .propagate(minutes)
+            .map_err(|e| ApiError::internal(format!("SGP4 propagation failed: {}", e)))?;
+
+        Ok((prediction.position, prediction.velocity))
+    }
+
+    /// Положение спутника в координатах ECEF (км)
+    pub fn ecef_at(&self, at: DateTime<Utc>) -> Result<[f64; 3], ApiError> {
+        let (position, _) = self.teme_at(at)?;
+        Ok(teme_to_ecef(position, gmst(at)))
+    }
+
+    pub fn position_at(&self, at: DateTime<Utc>) -> Result<OrbitPosition, ApiError> {
+        let (position, velocity) = self.teme_at(at)?;
+        let (latitude, longitude, altitude) = ecef_to_geodetic(teme_to_ecef(position, gmst(at)));
+        let speed_kms = (velocity[0].powi(2) + velocity[1].powi(2) + velocity[2].powi(2)).sqrt();
+
+        Ok(OrbitPosition {
+            timestamp: at,
+            latitude,
+            longitude,
+            altitude,
+            // км/ч, как у wheretheiss.at
+            velocity: speed_kms * 3600.0,
+        })
+    }
+}
+
+/// Гринвичское среднее звёздное время, рад
+pub fn gmst(at: DateTime<Utc>) -> f64 {
+    sgp4::iau_epoch_to_sidereal_time(sgp4::julian_years_since_j2000(&at.naive_utc()))
+}
+
+pub fn teme_to_ecef(r: [f64; 3], gmst: f64) -> [f64; 3] {
+    let (s, c) = gmst.sin_cos();
+    [c * r[0] + s * r[1], -s * r[0] + c * r[1], r[2]]
+}
+
+/// ECEF (км) → широта/долгота (градусы) и высота над эллипсоидом (км)
+pub fn ecef_to_geodetic(r: [f64; 3]) -> (f64, f64, f64) {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let p = (r[0].powi(2) + r[1].powi(2)).sqrt();
+    let lon = r[1].atan2(r[0]);
+
+    let mut lat = r[2].atan2(p * (1.0 - e2));
+    let mut alt = 0.0;
+    for _ in 0..5 {
+        let n = WGS84_A / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+        alt = p / lat.cos() - n;
+        lat = r[2].atan2(p * (1.0 - e2 * n / (n + alt)));
+    }
+
+    (lat.to_degrees(), lon.to_degrees(), alt)
+}
+
+/// Географические координаты (градусы, км) → ECEF (км)
+pub fn geodetic_to_ecef(lat: f64, lon: f64, alt: f64) -> [f64; 3] {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+    let n = WGS84_A / (1.0 - e2 * sin_lat.powi(2)).sqrt();
+
+    [
+        (n + alt) * cos_lat * cos_lon,
+        (n + alt) * cos_lat * sin_lon,
+        (n * (1.0 - e2) + alt) * sin_lat,
+    ]
+}
+
+/// Азимут и угол места (градусы) цели в ECEF для наблюдателя в точке lat/lon
+pub fn look_angles(observer: [f64; 3], lat: f64, lon: f64, target: [f64; 3]) -> (f64, f64) {
+    let d = [target[0] - observer[0], target[1] - observer[1], target[2] - observer[2]];
+    let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+
+    let south = sin_lat * cos_lon * d[0] + sin_lat * sin_lon * d[1] - cos_lat * d[2];
+    let east = -sin_lon * d[0] + cos_lon * d[1];
+    let zenith = cos_lat * cos_lon * d[0] + cos_lat * sin_lon * d[1] + sin_lat * d[2];
+    let range = (d[0].powi(2) + d[1].powi(2) + d[2].powi(2)).sqrt();
+
+    let azimuth = east.atan2(-south).to_degrees().rem_euclid(360.0);
+    let elevation = (zenith / range).asin().to_degrees();
+    (azimuth, elevation)
+}
+
+/// Положение Солнца в инерциальной системе (км), точность ~0.01°
+pub fn sun_eci(at: DateTime<Utc>) -> [f64; 3] {
+    const AU_KM: f64 = 149_597_870.7;
+
+    let jd = at.timestamp_millis() as f64 / 86_400_000.0 + 2_440_587.5;
+    let n = jd - 2_451_545.0;
+    let mean_lon = (280.460 + 0.985_647_4 * n).to_radians();
+    let g = (357.528 + 0.985_600_3 * n).to_radians();
+    let ecliptic_lon = mean_lon + (1.915 * g.sin() + 0.020 * (2.0 * g).sin()).to_radians();
+    let obliquity = (23.439 - 0.000_000_4 * n).to_radians();
+    let distance = (1.000_14 - 0.016_71 * g.cos() - 0.000_14 * (2.0 * g).cos()) * AU_KM;
+
+    [
+        distance * ecliptic_lon.cos(),
+        distance * obliquity.cos() * ecliptic_lon.sin(),
+        distance * obliquity.sin() * ecliptic_lon.sin(),
+    ]
+}
+
+/// Освещён ли спутник Солнцем (цилиндрическая модель тени Земли)
+pub fn is_sunlit(sat_eci: [f64; 3], sun_eci: [f64; 3]) -> bool {
+    let sun_norm = (sun_eci[0].powi(2) + sun_eci[1].powi(2) + sun_eci[2].powi(2)).sqrt();
+    let s = [sun_eci[0] / sun_norm, sun_eci[1] / sun_norm, sun_eci[2] / sun_norm];
+    let along = sat_eci[0] * s[0] + sat_eci[1] * s[1] + sat_eci[2] * s[2];
+    if along >= 0.0 {
+        return true;
+    }
+    let perp = [
+        sat_eci[0] - along * s[0],
+        sat_eci[1] - along * s[1],
+        sat_eci[2] - along * s[2],
+    ];
+    (perp[0].powi(2) + perp[1].powi(2) + perp[2].powi(2)).sqrt() > WGS84_A
+}
+
+/// Шаг грубого поиска пролётов, с
+const PASS_SCAN_STEP_SECONDS: i64 = 30;
+/// Солнце ниже этого угла — у наблюдателя достаточно темно (гражданские сумерки)
+const DARK_SUN_ELEVATION: f64 = -6.0;
+/// Число проверок видимости внутри пролёта
+const VISIBILITY_SAMPLES: i64 = 20;
+
+/// Ищет пролёты спутника над наблюдателем в интервале [from, to]
+///
+/// Пролёт, уже идущий в момент `from` или не завершившийся к `to`, не возвращается.
+pub fn find_passes(
+    propagator: &Propagator,
+    observer: &Observer,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<SatellitePass>, ApiError> {
+    let site = geodetic_to_ecef(observer.lat, observer.lon, observer.alt_m / 1000.0);
+    let elevation_at = |at: DateTime<Utc>| -> Result<f64, ApiError> {
+        let (_, el) = look_angles(site, observer.lat, observer.lon, propagator.ecef_at(at)?);
+        Ok(el)
+    };
+
+    let step = Duration::seconds(PASS_SCAN_STEP_SECONDS);
+    let mut passes = Vec::new();
+    let mut prev_time = from;
+    let mut prev_el = elevation_at(from)?;
+    let mut rise: Option<DateTime<Utc>> = None;
+    let mut t = from + step;
+
+    while t <= to {
+        let el = elevation_at(t)?;
+        if prev_el < 0.0 && el >= 0.0 {
+            rise = Some(refine_crossing(&elevation_at, prev_time, t)?);
+        } else if prev_el >= 0.0 && el < 0.0 {
+            if let Some(rise_at) = rise.take() {
+                let set_at = refine_crossing(&elevation_at, prev_time, t)?;
+                passes.push(build_pass(propagator, observer, site, rise_at, set_at)?);
+            }
+        }
+        prev_time = t;
+        prev_el = el;
+        t += step;
+    }
+
+    Ok(passes)
+}
+
+/// Бисекция момента пересечения горизонта
+fn refine_crossing<F>(elevation_at: &F, mut a: DateTime<Utc>, mut b: DateTime<Utc>) -> Result<DateTime<Utc>, ApiError>
+where
+    F: Fn(DateTime<Utc>) -> Result<f64, ApiError>,
+{
+    let rising = elevation_at(a)? < 0.0;
+    while (b - a).num_milliseconds() > 500 {
+        let mid = a + (b - a) / 2;
+        let above = elevation_at(mid)? >= 0.0;
+        if above == rising {
+            b = mid;
+        } else {
+            a = mid;
+        }
+    }
+    Ok(a + (b - a) / 2)
+}
+
+fn build_pass(
+    propagator: &Propagator,
+    observer: &Observer,
+    site: [f64; 3],
+    rise_at: DateTime<Utc>,
+    set_at: DateTime<Utc>,
+) -> Result<SatellitePass, ApiError> {
+    let point_at = |at: DateTime<Utc>| -> Result<PassPoint, ApiError> {
+        let (azimuth, elevation) = look_angles(site, observer.lat, observer.lon, propagator.ecef_at(at)?);
+        Ok(PassPoint { time: at, azimuth, elevation })
+    };
+
+    // Угол места внутри пролёта унимодален — тернарный поиск максимума
+    let (mut a, mut b) = (rise_at, set_at);
+    while (b - a).num_milliseconds() > 1000 {
+        let m1 = a + (b - a) / 3;
+        let m2 = b - (b - a) / 3;
+        if point_at(m1)?.elevation < point_at(m2)?.elevation {
+            a = m1;
+        } else {
+            b = m2;
+        }
+    }
+    let culmination = point_at(a + (b - a) / 2)?;
+
+    let mut visible = false;
+    let span = set_at - rise_at;
+    for i in 0..=VISIBILITY_SAMPLES {
+        let at = rise_at + span * (i as i32) / (VISIBILITY_SAMPLES as i32);
+        let sun = sun_eci(at);
+        let (_, sun_elevation) = look_angles(site, observer.lat, observer.lon, teme_to_ecef(sun, gmst(at)));
+        if sun_elevation < DARK_SUN_ELEVATION && is_sunlit(propagator.teme_at(at)?.0, sun) {
+            visible = true;
+            break;
+        }
+    }
+
+    Ok(SatellitePass {
+        rise: point_at(rise_at)?,
+        max_elevation: culmination.elevation,
+        culmination,
+        set: point_at(set_at)?,
+        duration_seconds: span.num_seconds(),
+        visible,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // Пример МКС из документации skyfield (Earth Satellites): наблюдатель в Блаффтоне, Огайо
+    const ISS_LINE1: &str = "1 25544U 98067A   14020.93268519  .00009878  00000-0  18200-3 0  5082";
+    const ISS_LINE2: &str = "2 25544  51.6498 109.4756 0003572  55.9686 274.8005 15.49815350868473";
+    const BLUFFTON: Observer = Observer {
+        lat: 40.8939,
+        lon: -83.8917,
+        alt_m: 0.0,
+    };
+
+    fn iss() -> Propagator {
+        Propagator::from_tle(None, ISS_LINE1, ISS_LINE2).unwrap()
+    }
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, s).unwrap()
+    }
+
+    fn dms(d: f64, m: f64, s: f64) -> f64 {
+        d.signum() * (d.abs() + m / 60.0 + s / 3600.0)
+    }
+
+    fn elevation(propagator: &Propagator, at: DateTime<Utc>) -> f64 {
+        let site = geodetic_to_ecef(BLUFFTON.lat, BLUFFTON.lon, 0.0);
+        look_angles(site, BLUFFTON.lat, BLUFFTON.lon, propagator.ecef_at(at).unwrap()).1
+    }
+
+    #[test]
+    fn gmst_at_j2000() {
+        // 2000-01-01 12:00 UT1: 280.46061837°
+        let gmst = gmst(utc(2000, 1, 1, 12, 0, 0));
+        assert!((gmst.to_degrees() - 280.460_618_37).abs() < 1e-3, "gmst = {}", gmst.to_degrees());
+    }
+
+    #[test]
+    fn ecef_to_geodetic_matches_vallado() {
+        // Vallado, Fundamentals of Astrodynamics, пример 3-3
+        let (lat, lon, alt) = ecef_to_geodetic([6524.834, 6862.875, 6448.296]);
+        assert!((lat - 34.352_496).abs() < 1e-4, "lat = {}", lat);
+        assert!((lon - 46.446_4).abs() < 1e-4, "lon = {}", lon);
+        assert!((alt - 5085.22).abs() < 0.01, "alt = {}", alt);
+    }
+
+    #[test]
+    fn geodetic_round_trip() {
+        for (lat, lon, alt) in [(55.7558, 37.6173, 0.156), (-33.8688, 151.2093, 420.0), (89.9, -120.0, 1.0)] {
+            let (lat2, lon2, alt2) = ecef_to_geodetic(geodetic_to_ecef(lat, lon, alt));
+            assert!((lat - lat2).abs() < 1e-9 && (lon - lon2).abs() < 1e-9 && (alt - alt2).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn subpoint_matches_skyfield() {
+        // Latitude 50°14'37.4", Longitude -86°23'23.3", elevation 420874 m
+        let position = iss().position_at(utc(2014, 1, 23, 11, 18, 7)).unwrap();
+        assert!((position.latitude - dms(50.0, 14.0, 37.4)).abs() < 0.2, "lat = {}", position.latitude);
+        assert!((position.longitude - dms(-86.0, 23.0, 23.3)).abs() < 0.2, "lon = {}", position.longitude);
+        assert!((position.altitude - 420.874).abs() < 2.0, "alt = {}", position.altitude);
+    }
+
+    #[test]
+    fn look_angles_match_skyfield() {
+        // Altitude 16°16'32.6", azimuth 350°15'20.4"
+        let iss = iss();
+        let site = geodetic_to_ecef(BLUFFTON.lat, BLUFFTON.lon, 0.0);
+        let target = iss.ecef_at(utc(2014, 1, 23, 11, 18, 7)).unwrap();
+        let (azimuth, elevation) = look_angles(site, BLUFFTON.lat, BLUFFTON.lon, target);
+        assert!((elevation - dms(16.0, 16.0, 32.6)).abs() < 1.0, "elevation = {}", elevation);
+        assert!((azimuth - dms(350.0, 15.0, 20.4)).abs() < 1.0, "azimuth = {}", azimuth);
+    }
+
+    #[test]
+    fn passes_match_skyfield() {
+        // skyfield find_events(altitude_degrees=30): подъём выше 30°, кульминация, спуск ниже 30°
+        let reference = [
+            (utc(2014, 1, 23, 6, 25, 37), utc(2014, 1, 23, 6, 26, 58), utc(2014, 1, 23, 6, 28, 19)),
+            (utc(2014, 1, 23, 12, 54, 56), utc(2014, 1, 23, 12, 56, 27), utc(2014, 1, 23, 12, 57, 58)),
+        ];
+        let iss = iss();
+        let passes = find_passes(&iss, &BLUFFTON, utc(2014, 1, 23, 0, 0, 0), utc(2014, 1, 24, 0, 0, 0)).unwrap();
+
+        for (above, culminate, below) in reference {
+            let pass = passes
+                .iter()
+                .find(|p| (p.culmination.time - culminate).num_seconds().abs() <= 60)
+                .unwrap_or_else(|| panic!("no pass culminating near {}", culminate));
+            assert!(pass.max_elevation > 30.0);
+            assert!(pass.rise.time < above && pass.set.time > below);
+            assert!(pass.rise.elevation.abs() < 0.1 && pass.set.elevation.abs() < 0.1);
+            // Моменты пересечения 30° — в пределах градуса по углу места
+            assert!((elevation(&iss, above) - 30.0).abs() < 1.0);
+            assert!((elevation(&iss, below) - 30.0).abs() < 1.0);
+        }
+        // Пролёты не пересекаются и идут по времени
+        assert!(passes.windows(2).all(|w| w[0].set.time < w[1].rise.time));
+    }
+
+    #[test]
+    fn sunlit_uses_earth_shadow() {
+        let sun = [149_597_870.7, 0.0, 0.0];
+        // На стороне Солнца, в тени за Землёй и за Землёй, но выше тени
+        assert!(is_sunlit([WGS84_A + 400.0, 0.0, 0.0], sun));
+        assert!(!is_sunlit([-(WGS84_A + 400.0), 0.0, 0.0], sun));
+        assert!(is_sunlit([-(WGS84_A + 400.0), WGS84_A + 100.0, 0.0], sun));
+    }
+}
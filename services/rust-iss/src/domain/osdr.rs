@@ -0,0 +1,561 @@
+//! Нормализованные поля записи OSDR и сравнение версий
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::domain::pagination::key_order;
+use crate::domain::OsdrItem;
+
+/// То, что синхронизация пишет в osdr_items; по этим полям считаются версии
+#[derive(Debug, Clone, PartialEq)]
+pub struct OsdrFields {
+    pub dataset_id: Option<String>,
+    pub title: Option<String>,
+    pub organism: Option<String>,
+    pub study_type: Option<String>,
+    pub status: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub raw: Value,
+}
+
+impl OsdrFields {
+    pub fn from_item(item: &OsdrItem) -> Self {
+        Self {
+            dataset_id: item.dataset_id.clone(),
+            title: item.title.clone(),
+            organism: item.organism.clone(),
+            study_type: item.study_type.clone(),
+            status: item.status.clone(),
+            updated_at: item.updated_at,
+            raw: item.raw.clone(),
+        }
+    }
+
+    fn normalized(&self) -> [(&'static str, Value); 5] {
+        [
+            ("title", json!(self.title)),
+            ("organism", json!(self.organism)),
+            ("study_type", json!(self.study_type)),
+            ("status", json!(self.status)),
+            ("updated_at", json!(self.updated_at)),
+        ]
+    }
+
+    /// Изменения относительно `old` в виде {"поле": {"old": .., "new": ..}};
+    /// raw сравнивается по ключам верхнего уровня ("raw.<ключ>").
+    /// Без `old` — все непустые поля новой записи.
+    pub fn diff(&self, old: Option<&OsdrFields>) -> Map<String, Value> {
+        let mut diff = Map::new();
+        let old_normalized = old.map(|o| o.normalized());
+
+        for (i, (field, new)) in self.normalized().into_iter().enumerate() {
+            let prev = old_normalized
+                .as_ref()
+                .map(|o| o[i].1.clone())
+                .unwrap_or(Value::Null);
+            if prev != new {
+                diff.insert(field.to_string(), json!({"old": prev, "new": new}));
+            }
+        }
+
+        let empty = Map::new();
+        let new_raw = self.raw.as_object();
+        let old_raw = old.map(|o| &o.raw);
+        match (old_raw.and_then(|r| r.as_object()), new_raw) {
+            (old_map, Some(new_map)) => {
+                let old_map = old_map.unwrap_or(&empty);
+                for key in old_map.keys().chain(new_map.keys().filter(|k| !old_map.contains_key(*k))) {
+                    let prev = old_map.get(key).cloned().unwrap_or(Value::Null);
+                    let next = new_map.get(key).cloned().unwrap_or(Value::Null);
+                    if prev != next {
+                        diff.insert(format!("raw.{}", key), json!({"old": prev, "new": next}));
+                    }
+                }
+            }
+            _ => {
+                let prev = old_raw.cloned().unwrap_or(Value::Null);
+                if prev != self.raw {
+                    diff.insert("raw".to_string(), json!({"old": prev, "new": self.raw}));
+                }
+            }
+        }
+        diff
+    }
+}
+
+/// Префиксы идентификаторов OSDR/GeneLab, по которым ищутся ссылки в raw
+const ACCESSION_PREFIXES: [&str; 2] = ["OSD-", "GLDS-"];
+
+/// Идентификаторы наборов, упомянутые в строках raw (`OSD-123`, `GLDS-47`)
+pub fn referenced_ids(raw: &Value) -> Vec<String> {
+    let mut ids = Vec::new();
+    collect_references(raw, &mut ids);
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+fn collect_references(value: &Value, ids: &mut Vec<String>) {
+    match value {
+        Value::String(s) => {
+            for prefix in ACCESSION_PREFIXES {
+                for (start, _) in s.match_indices(prefix) {
+                    let preceded = s[..start]
+                        .chars()
+                        .next_back()
+                        .is_some_and(|c| c.is_ascii_alphanumeric());
+                    let digits: String = s[start + prefix.len()..]
+                        .chars()
+                        .take_while(|c| c.is_ascii_digit())
+                        .collect();
+                    if !preceded && !digits.is_empty() {
+                        ids.push(format!("{}{}", prefix, digits));
+                    }
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect_references(v, ids)),
+        Value::Object(map) => map.values().for_each(|v| collect_references(v, ids)),
+        _ => {}
+    }
+}
+
+/// Структурные фильтры списка OSDR; пустой список значений — без фильтра
+#[derive(Debug, Clone, Default)]
+pub struct OsdrFilter {
+    pub search: Option<String>,
+    pub organisms: Vec<String>,
+    pub study_types: Vec<String>,
+    pub statuses: Vec<String>,
+    pub updated_from: Option<DateTime<Utc>>,
+    pub updated_to: Option<DateTime<Utc>>,
+    pub inserted_from: Option<DateTime<Utc>>,
+    pub inserted_to: Option<DateTime<Utc>>,
+    /// По умолчанию отозванные наборы скрыты
+    pub include_withdrawn: bool,
+}
+
+/// Измерения, по которым считаются фасеты
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OsdrFacet {
+    Organism,
+    StudyType,
+    Status,
+}
+
+impl OsdrFacet {
+    pub const ALL: [OsdrFacet; 3] = [Self::Organism, Self::StudyType, Self::Status];
+
+    pub fn column(self) -> &'static str {
+        match self {
+            Self::Organism => "organism",
+            Self::StudyType => "study_type",
+            Self::Status => "status",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OsdrSortField {
+    /// Релевантность полнотекстового поиска; без поиска — как по умолчанию
+    Relevance,
+    UpdatedAt,
+    InsertedAt,
+    Title,
+    DatasetId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OsdrSort {
+    pub field: OsdrSortField,
+    pub descending: bool,
+}
+
+impl Default for OsdrSort {
+    fn default() -> Self {
+        Self {
+            field: OsdrSortField::UpdatedAt,
+            descending: true,
+        }
+    }
+}
+
+impl OsdrSort {
+    /// Порядок по умолчанию для запроса с поиском
+    pub fn relevance() -> Self {
+        Self {
+            field: OsdrSortField::Relevance,
+            descending: true,
+        }
+    }
+
+    /// `relevance`, `updated_at`, `inserted_at`, `title`, `dataset_id`; `-` впереди — по убыванию
+    /// (релевантность всегда по убыванию, если не задан `order`).
+    /// `order` (asc/desc), если задан, важнее префикса.
+    pub fn parse(sort: &str, order: Option<&str>) -> Option<Self> {
+        let sort = sort.trim();
+        let (name, mut descending) = match sort.strip_prefix('-') {
+            Some(rest) => (rest, true),
+            None => (sort, false),
+        };
+        let field = match name {
+            "relevance" | "rank" => OsdrSortField::Relevance,
+            "updated_at" | "updated" => OsdrSortField::UpdatedAt,
+            "inserted_at" | "inserted" => OsdrSortField::InsertedAt,
+            "title" => OsdrSortField::Title,
+            "dataset_id" | "id" => OsdrSortField::DatasetId,
+            _ => return None,
+        };
+        // Релевантность без явного направления — лучшие совпадения первыми
+        if field == OsdrSortField::Relevance {
+            descending = true;
+        }
+        match order.map(|o| o.trim().to_ascii_lowercase()) {
+            Some(o) if o == "asc" => descending = false,
+            Some(o) if o == "desc" => descending = true,
+            Some(_) => return None,
+            None => {}
+        }
+        Some(Self { field, descending })
+    }
+
+    /// Курсорная пагинация — только по (updated_at, id). NULL заменяется значением,
+    /// которое при этом направлении идёт последним, как и NULLS LAST
+    pub fn keyset_null(&self) -> Option<&'static str> {
+        match self.field {
+            OsdrSortField::UpdatedAt if self.descending => Some("'-infinity'::timestamptz"),
+            OsdrSortField::UpdatedAt => Some("'infinity'::timestamptz"),
+            _ => None,
+        }
+    }
+
+    /// ORDER BY для SQL; id в конце делает порядок устойчивым
+    pub fn order_by(&self) -> String {
+        if let Some(null_as) = self.keyset_null() {
+            return key_order("updated_at", Some(null_as), self.descending);
+        }
+        let column = match self.field {
+            OsdrSortField::Relevance => "rank",
+            OsdrSortField::UpdatedAt => "updated_at",
+            OsdrSortField::InsertedAt => "inserted_at",
+            OsdrSortField::Title => "title",
+            OsdrSortField::DatasetId => "dataset_id",
+        };
+        let direction = if self.descending { "DESC" } else { "ASC" };
+        format!("{} {} NULLS LAST, id {}", column, direction, direction)
+    }
+}
+
+/// Конфигурация текстового поиска PostgreSQL, та же, что в osdr_items.search_vector
+pub const SEARCH_CONFIG: &str = "english";
+
+/// Переводит строку поиска в синтаксис to_tsquery:
+/// слова через AND, `"фраза"` — через `<->`, `слово*` — префикс, `-слово` — исключение,
+/// `or` между терминами — OR. Дефисы внутри слов убираются, как и в поисковом векторе.
+pub fn build_tsquery(input: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut pending_or = false;
+
+    for (term, quoted) in split_terms(input) {
+        if !quoted && term.eq_ignore_ascii_case("or") {
+            pending_or = !out.is_empty();
+            continue;
+        }
+
+        let (negated, term) = match term.strip_prefix('-') {
+            Some(rest) if !quoted && !rest.is_empty() => (true, rest),
+            _ => (false, term.as_str()),
+        };
+        let (prefix, term) = match term.strip_suffix('*') {
+            Some(rest) if !quoted => (true, rest),
+            _ => (false, term),
+        };
+
+        let words = lexemes(term);
+        if words.is_empty() {
+            continue;
+        }
+        let last = words.len() - 1;
+        let phrase = words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| {
+                if prefix && i == last {
+                    format!("'{}':*", w)
+                } else {
+                    format!("'{}'", w)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" <-> ");
+
+        if !out.is_empty() {
+            out.push_str(if pending_or { " | " } else { " & " });
+        }
+        if negated {
+            out.push('!');
+        }
+        if words.len() > 1 {
+            out.push_str(&format!("({})", phrase));
+        } else {
+            out.push_str(&phrase);
+        }
+        pending_or = false;
+    }
+
+    (!out.is_empty()).then_some(out)
+}
+
+/// Термины строки поиска; второй элемент — была ли фраза в кавычках
+fn split_terms(input: &str) -> Vec<(String, bool)> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                if !current.is_empty() {
+                    terms.push((std::mem::take(&mut current), quoted));
+                }
+                quoted = !quoted;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    terms.push((std::mem::take(&mut current), false));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        terms.push((current, quoted));
+    }
+    terms
+}
+
+/// Слова термина в нижнем регистре: дефис между буквами/цифрами склеивает,
+/// прочие знаки разделяют
+fn lexemes(term: &str) -> Vec<String> {
+    let chars: Vec<char> = term.chars().collect();
+    let mut words = Vec::new();
+    let mut current = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_alphanumeric() {
+            current.extend(c.to_lowercase());
+            continue;
+        }
+        let joins = c == '-'
+            && i > 0
+            && chars[i - 1].is_alphanumeric()
+            && chars.get(i + 1).is_some_and(|n| n.is_alphanumeric());
+        if !joins && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Префикс синтетических dataset_id: `auto-key-…` — по составному ключу,
+/// `auto-hash-…` — по содержимому raw
+pub const SYNTHETIC_ID_PREFIX: &str = "auto-";
+
+/// Детерминированный dataset_id для записи без идентификатора upstream.
+/// Составной ключ (заголовок, организм, тип исследования) переживает правки
+/// прочих полей; если заголовка нет, остаётся хеш всего raw.
+pub fn synthetic_dataset_id(fields: &OsdrFields) -> String {
+    let part = |v: &Option<String>| {
+        v.as_deref()
+            .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase())
+            .unwrap_or_default()
+    };
+
+    match &fields.title {
+        Some(title) if !title.trim().is_empty() => {
+            let key = [part(&fields.title), part(&fields.organism), part(&fields.study_type)].join("\u{1f}");
+            format!("{}key-{}", SYNTHETIC_ID_PREFIX, short_hash(&key))
+        }
+        _ => {
+            let mut canonical = String::new();
+            write_canonical(&fields.raw, &mut canonical);
+            format!("{}hash-{}", SYNTHETIC_ID_PREFIX, short_hash(&canonical))
+        }
+    }
+}
+
+/// Первые 16 hex-символов SHA-256
+fn short_hash(s: &str) -> String {
+    Sha256::digest(s.as_bytes())
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// JSON с ключами объектов по алфавиту, чтобы хеш не зависел от порядка полей
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<_> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(title: Option<&str>, organism: Option<&str>, study_type: Option<&str>, raw: Value) -> OsdrFields {
+        OsdrFields {
+            dataset_id: None,
+            title: title.map(str::to_string),
+            organism: organism.map(str::to_string),
+            study_type: study_type.map(str::to_string),
+            status: None,
+            updated_at: None,
+            raw,
+        }
+    }
+
+    #[test]
+    fn synthetic_id_ignores_whitespace_case_and_other_fields() {
+        let base = synthetic_dataset_id(&fields(Some("Rodent Research 1"), Some("Mus musculus"), None, json!({})));
+        assert!(base.starts_with("auto-key-"));
+        assert_eq!(base.len(), "auto-key-".len() + 16);
+
+        let variants = [
+            fields(Some("  rodent   RESEARCH\t1 "), Some("MUS musculus"), None, json!({})),
+            fields(Some("Rodent Research 1"), Some("Mus  musculus"), Some(""), json!({"status": "changed"})),
+        ];
+        for variant in variants {
+            assert_eq!(synthetic_dataset_id(&variant), base);
+        }
+
+        let other = [
+            fields(Some("Rodent Research 2"), Some("Mus musculus"), None, json!({})),
+            fields(Some("Rodent Research 1"), Some("Homo sapiens"), None, json!({})),
+            fields(Some("Rodent Research 1"), Some("Mus musculus"), Some("spaceflight"), json!({})),
+            // Разделитель полей не даёт склеить заголовок с организмом
+            fields(Some("Rodent Research 1 Mus"), Some("musculus"), None, json!({})),
+        ];
+        for variant in other {
+            assert_ne!(synthetic_dataset_id(&variant), base);
+        }
+    }
+
+    #[test]
+    fn synthetic_id_without_title_hashes_raw() {
+        let a: Value = serde_json::from_str(r#"{"b": 1, "a": {"y": [1, {"q": 2, "p": 3}], "x": null}}"#).unwrap();
+        let b: Value = serde_json::from_str(r#"{"a": {"x": null, "y": [1, {"p": 3, "q": 2}]}, "b": 1}"#).unwrap();
+
+        let id = synthetic_dataset_id(&fields(None, None, None, a.clone()));
+        assert!(id.starts_with("auto-hash-"));
+        assert_eq!(synthetic_dataset_id(&fields(None, None, None, b)), id);
+        // Пустой заголовок — как отсутствующий; прочие поля в хеш не входят
+        assert_eq!(synthetic_dataset_id(&fields(Some("  "), Some("Mus"), None, a)), id);
+
+        let reordered = json!({"a": {"x": null, "y": [{"p": 3, "q": 2}, 1]}, "b": 1});
+        assert_ne!(synthetic_dataset_id(&fields(None, None, None, reordered)), id);
+        let typed = |raw| synthetic_dataset_id(&fields(None, None, None, raw));
+        assert_ne!(typed(json!({"b": "1"})), typed(json!({"b": 1})));
+    }
+
+    #[test]
+    fn tsquery_from_search_strings() {
+        let cases = [
+            ("rodent mice", Some("'rodent' & 'mice'")),
+            ("\"bone loss\" Mice", Some("('bone' <-> 'loss') & 'mice'")),
+            ("micro*", Some("'micro':*")),
+            ("bone-loss*", Some("'boneloss':*")),
+            ("\"micro gravity*\"", Some("('micro' <-> 'gravity')")),
+            ("mice -rat", Some("'mice' & !'rat'")),
+            ("mice OR rats", Some("'mice' | 'rats'")),
+            ("mice or -rats spaceflight", Some("'mice' | !'rats' & 'spaceflight'")),
+            ("\"or\" mice", Some("'or' & 'mice'")),
+            // Висячий or и повторы ничего не ломают
+            ("or mice", Some("'mice'")),
+            ("mice or", Some("'mice'")),
+            ("mice or or rats", Some("'mice' | 'rats'")),
+            // Одинокий минус и звёздочка пропускаются
+            ("-", None),
+            ("mice - *", Some("'mice'")),
+            // Незакрытая кавычка — фраза до конца строки
+            ("\"bone loss", Some("('bone' <-> 'loss')")),
+            ("mice \"bone", Some("'mice' & 'bone'")),
+            // Дефис внутри слова склеивает, как в поисковом векторе
+            ("x-ray", Some("'xray'")),
+            ("-x-ray", Some("!'xray'")),
+            ("well - known", Some("'well' & 'known'")),
+            ("a--b", Some("('a' <-> 'b')")),
+            // Кавычки и служебные символы tsquery в лексемы не попадают
+            ("o'neil & (rat)", Some("('o' <-> 'neil') & 'rat'")),
+            ("", None),
+            ("   \"\"  ", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(build_tsquery(input).as_deref(), expected, "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn split_terms_tracks_quotes() {
+        let terms = split_terms;
+        let owned = |items: &[(&str, bool)]| items.iter().map(|&(t, q)| (t.to_string(), q)).collect::<Vec<_>>();
+
+        assert_eq!(terms("  a \"b  c\" d "), owned(&[("a", false), ("b  c", true), ("d", false)]));
+        assert_eq!(terms("a\"b\"c"), owned(&[("a", false), ("b", true), ("c", false)]));
+        assert_eq!(terms("\"a b"), owned(&[("a b", true)]));
+        assert_eq!(terms("a\tb\nc"), owned(&[("a", false), ("b", false), ("c", false)]));
+        assert!(terms(" \"\" ").is_empty());
+    }
+
+    #[test]
+    fn lexemes_join_hyphens_and_split_punctuation() {
+        let cases: [(&str, &[&str]); 7] = [
+            ("Café-au-lait", &["caféaulait"]),
+            ("CO2, H2O", &["co2", "h2o"]),
+            ("-rat-", &["rat"]),
+            ("a - b", &["a", "b"]),
+            ("ÜBER_alles", &["über", "alles"]),
+            ("12-3", &["123"]),
+            ("'':*!", &[]),
+        ];
+        for (term, expected) in cases {
+            assert_eq!(lexemes(term), expected, "term {:?}", term);
+        }
+    }
+
+    #[test]
+    fn canonical_json_sorts_keys() {
+        let mut out = String::new();
+        write_canonical(&json!({"z": [true, {"b": "x\"y", "a": 1.5}], "a": null}), &mut out);
+        assert_eq!(out, r#"{"a":null,"z":[true,{"a":1.5,"b":"x\"y"}]}"#);
+    }
+}
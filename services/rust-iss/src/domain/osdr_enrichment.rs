@@ -0,0 +1,106 @@
+//! Обогащение наборов OSDR: подробная карточка, ассаи и список файлов.
+//! Разбор терпим к форме ответа — нужные массивы ищутся по ключам на любой глубине.
+
+use serde_json::Value;
+
+use crate::domain::{OsdrAssay, OsdrFile};
+
+/// Настройки стадии обогащения. В шаблонах URL `{id}` заменяется на dataset_id
+/// ("OSD-87"), `{number}` — на его номер ("87")
+#[derive(Debug, Clone)]
+pub struct OsdrEnrichConfig {
+    pub detail_url: String,
+    /// Если не задан, ассаи берутся из карточки
+    pub assays_url: Option<String>,
+    pub files_url: String,
+    /// Сколько наборов читается одновременно
+    pub concurrency: usize,
+    /// Наборов за один прогон
+    pub batch: i64,
+}
+
+/// Прочитанное для одного набора
+#[derive(Debug, Clone)]
+pub struct OsdrEnrichment {
+    pub detail: Value,
+    pub assays: Vec<OsdrAssay>,
+    pub files: Vec<OsdrFile>,
+}
+
+pub fn expand_url(template: &str, dataset_id: &str) -> String {
+    let number = dataset_id.rsplit('-').next().unwrap_or(dataset_id);
+    template.replace("{id}", dataset_id).replace("{number}", number)
+}
+
+pub fn extract_assays(payload: &Value) -> Vec<OsdrAssay> {
+    find_array(payload, &["assays"])
+        .iter()
+        .filter(|v| v.is_object())
+        .map(|raw| OsdrAssay {
+            name: text(raw, &["name", "assay_name", "filename", "fileName"]),
+            measurement_type: text(raw, &["measurement_type", "measurementType", "measurement"]),
+            technology_type: text(raw, &["technology_type", "technologyType", "technology"]),
+            platform: text(raw, &["platform", "technology_platform", "technologyPlatform"]),
+            raw: raw.clone(),
+        })
+        .collect()
+}
+
+/// Файлы без имени пропускаются; `url` как есть, относительные ссылки
+/// разрешает вызывающий
+pub fn extract_files(payload: &Value) -> Vec<OsdrFile> {
+    find_array(payload, &["study_files", "files", "file_list"])
+        .iter()
+        .filter_map(|raw| {
+            Some(OsdrFile {
+                file_name: text(raw, &["file_name", "fileName", "name", "filename"])?,
+                category: text(raw, &["category"]),
+                subcategory: text(raw, &["subcategory", "sub_category"]),
+                size_bytes: ["file_size", "size", "size_bytes"]
+                    .iter()
+                    .find_map(|k| raw.get(*k).and_then(crate::domain::parse_number))
+                    .map(|n| n as i64),
+                url: text(raw, &["remote_url", "url", "download_url"]),
+                raw: raw.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Первый массив под одним из ключей (обход в глубину); сам ответ-массив — тоже подходит
+fn find_array<'a>(payload: &'a Value, keys: &[&str]) -> &'a [Value] {
+    fn walk<'a>(value: &'a Value, keys: &[&str]) -> Option<&'a [Value]> {
+        match value {
+            Value::Object(map) => keys
+                .iter()
+                .find_map(|k| map.get(*k).and_then(Value::as_array))
+                .map(Vec::as_slice)
+                .or_else(|| map.values().find_map(|v| walk(v, keys))),
+            Value::Array(items) => items.iter().find_map(|v| walk(v, keys)),
+            _ => None,
+        }
+    }
+
+    match payload {
+        Value::Array(items) => items,
+        other => walk(other, keys).unwrap_or_default(),
+    }
+}
+
+/// Строка под первым из ключей; у ISA-аннотаций значение лежит в annotationValue
+fn text(value: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|k| {
+        let v = value.get(*k)?;
+        let s = match v {
+            Value::String(s) => s.trim().to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::Object(_) => ["annotationValue", "value", "name"]
+                .iter()
+                .find_map(|k| v.get(*k).and_then(Value::as_str))?
+                .trim()
+                .to_string(),
+            _ => return None,
+        };
+        (!s.is_empty()).then_some(s)
+    })
+}
@@ -0,0 +1,224 @@
+//! Выгрузка наборов OSDR в CSV, XLSX и NDJSON построчно, без сборки всего файла в памяти
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::domain::xlsx::{Cell, XlsxStream};
+use crate::domain::OsdrItem;
+use crate::errors::ApiError;
+
+const COLUMNS: [&str; 10] = [
+    "id",
+    "dataset_id",
+    "title",
+    "organism",
+    "study_type",
+    "status",
+    "updated_at",
+    "inserted_at",
+    "withdrawn_at",
+    "raw_json",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OsdrExportFormat {
+    Csv,
+    Xlsx,
+    Ndjson,
+}
+
+impl OsdrExportFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "xlsx" => Some(Self::Xlsx),
+            "ndjson" | "jsonl" => Some(Self::Ndjson),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            Self::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Xlsx => "xlsx",
+            Self::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Пишет заголовок, строки и окончание файла
+pub struct OsdrExportWriter {
+    format: OsdrExportFormat,
+    xlsx: XlsxStream,
+}
+
+impl OsdrExportWriter {
+    pub fn new(format: OsdrExportFormat) -> Self {
+        Self {
+            format,
+            xlsx: XlsxStream::new(),
+        }
+    }
+
+    pub fn header(&mut self) -> Result<Vec<u8>, ApiError> {
+        match self.format {
+            OsdrExportFormat::Csv => Ok(format!("{}\n", COLUMNS.join(",")).into_bytes()),
+            OsdrExportFormat::Xlsx => {
+                let mut out = self.xlsx.start("OSDR")?;
+                out.extend(self.xlsx.row(&COLUMNS.map(Cell::Text))?);
+                Ok(out)
+            }
+            OsdrExportFormat::Ndjson => Ok(Vec::new()),
+        }
+    }
+
+    /// Ошибка — только у XLSX на лимите строк или размера; дальше писать нельзя
+    pub fn row(&mut self, item: &OsdrItem) -> Result<Vec<u8>, ApiError> {
+        if self.format == OsdrExportFormat::Ndjson {
+            let mut line = serde_json::to_vec(item).unwrap_or_default();
+            line.push(b'\n');
+            return Ok(line);
+        }
+
+        let id = item.id.to_string();
+        let updated_at = timestamp(item.updated_at);
+        let inserted_at = timestamp(Some(item.inserted_at));
+        let withdrawn_at = timestamp(item.withdrawn_at);
+        let raw = item.raw.to_string();
+        let text = [
+            Some(id.as_str()),
+            item.dataset_id.as_deref(),
+            item.title.as_deref(),
+            item.organism.as_deref(),
+            item.study_type.as_deref(),
+            item.status.as_deref(),
+            updated_at.as_deref(),
+            inserted_at.as_deref(),
+            withdrawn_at.as_deref(),
+            Some(raw.as_str()),
+        ];
+
+        match self.format {
+            OsdrExportFormat::Xlsx => {
+                let mut cells = text.map(|v| v.map(Cell::Text).unwrap_or(Cell::Empty));
+                cells[0] = Cell::Number(item.id as f64);
+                self.xlsx.row(&cells)
+            }
+            _ => {
+                let cells: Vec<String> = text.iter().map(|v| v.map(csv_escape).unwrap_or_default()).collect();
+                Ok(format!("{}\n", cells.join(",")).into_bytes())
+            }
+        }
+    }
+
+    pub fn footer(&mut self) -> Result<Vec<u8>, ApiError> {
+        match self.format {
+            OsdrExportFormat::Xlsx => self.xlsx.finish(),
+            OsdrExportFormat::Csv | OsdrExportFormat::Ndjson => Ok(Vec::new()),
+        }
+    }
+}
+
+fn timestamp(value: Option<DateTime<Utc>>) -> Option<String> {
+    value.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+fn csv_escape(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+    use crate::domain::xlsx::tests::unzip;
+
+    fn item(title: &str) -> OsdrItem {
+        OsdrItem {
+            id: 7,
+            dataset_id: Some("OSD-7".to_string()),
+            title: Some(title.to_string()),
+            organism: None,
+            study_type: None,
+            status: Some("public".to_string()),
+            updated_at: Some(Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).unwrap()),
+            inserted_at: Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap(),
+            raw: json!({"note": "a,b"}),
+            last_seen_at: None,
+            withdrawn_at: None,
+            withdrawn_reason: None,
+            search: None,
+        }
+    }
+
+    fn export(format: OsdrExportFormat, items: &[OsdrItem]) -> Vec<u8> {
+        let mut writer = OsdrExportWriter::new(format);
+        let mut out = writer.header().unwrap();
+        for item in items {
+            out.extend(writer.row(item).unwrap());
+        }
+        out.extend(writer.footer().unwrap());
+        out
+    }
+
+    #[test]
+    fn csv_escape_quotes_only_when_needed() {
+        let cases = [
+            ("plain", "plain"),
+            ("a,b", "\"a,b\""),
+            ("say \"hi\"", "\"say \"\"hi\"\"\""),
+            ("two\nlines", "\"two\nlines\""),
+            ("cr\r", "\"cr\r\""),
+            ("", ""),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(csv_escape(input), expected);
+        }
+    }
+
+    #[test]
+    fn csv_rows() {
+        let out = String::from_utf8(export(OsdrExportFormat::Csv, &[item("Mice, \"rats\"\nand more")])).unwrap();
+        assert_eq!(
+            out,
+            concat!(
+                "id,dataset_id,title,organism,study_type,status,updated_at,inserted_at,withdrawn_at,raw_json\n",
+                "7,OSD-7,\"Mice, \"\"rats\"\"\nand more\",,,public,2024-03-01T10:00:00Z,2024-03-02T00:00:00Z,,",
+                "\"{\"\"note\"\":\"\"a,b\"\"}\"\n",
+            )
+        );
+    }
+
+    #[test]
+    fn ndjson_rows() {
+        let out = String::from_utf8(export(OsdrExportFormat::Ndjson, &[item("a"), item("b\nc")])).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let second: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(second["title"], "b\nc");
+        assert_eq!(second["raw"], json!({"note": "a,b"}));
+    }
+
+    #[test]
+    fn xlsx_rows() {
+        let parts = unzip(&export(OsdrExportFormat::Xlsx, &[item("<Mice> & \"rats\"")]));
+        let sheet = &parts["xl/worksheets/sheet1.xml"];
+        assert!(sheet.contains(r#"<row r="1"><c t="inlineStr"><is><t xml:space="preserve">id</t>"#));
+        assert!(sheet.contains(r#"<row r="2"><c><v>7</v></c>"#));
+        assert!(sheet.contains("&lt;Mice&gt; &amp; &quot;rats&quot;"));
+        assert!(sheet.contains("{&quot;note&quot;:&quot;a,b&quot;}"));
+    }
+}
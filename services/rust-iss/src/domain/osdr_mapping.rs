@@ -0,0 +1,443 @@
+//! Правила приведения записей OSDR к колонкам osdr_items: JSON pointer пути
+//! с запасными вариантами, преобразования строк и форматы дат
+
+use std::collections::BTreeMap;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::domain::osdr::{synthetic_dataset_id, OsdrFields};
+use crate::errors::ApiError;
+
+/// Правила по умолчанию, если OSDR_MAPPING_FILE не задан
+pub const DEFAULT_MAPPING: &str = include_str!("../../config/osdr_mapping.json");
+
+const STRING_FIELDS: [&str; 5] = ["dataset_id", "title", "organism", "study_type", "status"];
+const TIMESTAMP_FIELD: &str = "updated_at";
+
+/// Преобразование строки; в файле — "trim", "lowercase", "uppercase"
+/// или {"split": ";", "index": 0}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Transform {
+    Named(String),
+    Split {
+        split: String,
+        #[serde(default)]
+        index: usize,
+    },
+}
+
+impl Transform {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Named(name) => match name.as_str() {
+                "trim" | "lowercase" | "uppercase" => Ok(()),
+                other => Err(format!("unknown transform {:?}", other)),
+            },
+            Self::Split { split, .. } if split.is_empty() => Err("split separator is empty".to_string()),
+            Self::Split { .. } => Ok(()),
+        }
+    }
+
+    fn apply(&self, value: String) -> String {
+        match self {
+            Self::Named(name) => match name.as_str() {
+                "trim" => value.trim().to_string(),
+                "lowercase" => value.to_lowercase(),
+                "uppercase" => value.to_uppercase(),
+                _ => value,
+            },
+            Self::Split { split, index } => value
+                .split(split.as_str())
+                .nth(*index)
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+        }
+    }
+}
+
+/// Правило для одной колонки: пути пробуются по порядку, первый непустой результат
+/// побеждает; `default` — если не подошёл ни один
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldRule {
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub transforms: Vec<Transform>,
+    /// Только для updated_at: "rfc3339", "unix", "unix_ms" или формат strftime
+    #[serde(default)]
+    pub date_formats: Vec<String>,
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+/// Откуда взялось значение колонки (для dry-run)
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldTrace {
+    pub value: Value,
+    /// Сработавший путь, "default", "synthetic" или null
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsdrMapping {
+    pub fields: BTreeMap<String, FieldRule>,
+}
+
+impl OsdrMapping {
+    /// Разбор и проверка правил; все ошибки собираются в одно сообщение
+    pub fn from_json(text: &str) -> Result<Self, ApiError> {
+        let mapping: OsdrMapping = serde_json::from_str(text)
+            .map_err(|e| ApiError::validation(format!("Invalid OSDR mapping: {}", e)))?;
+
+        let mut problems = Vec::new();
+        for name in mapping.fields.keys() {
+            if !STRING_FIELDS.contains(&name.as_str()) && name != TIMESTAMP_FIELD {
+                problems.push(format!("{}: unknown field", name));
+            }
+        }
+        for name in STRING_FIELDS.iter().chain([&TIMESTAMP_FIELD]) {
+            let Some(rule) = mapping.fields.get(*name) else {
+                problems.push(format!("{}: rule is missing", name));
+                continue;
+            };
+            if rule.paths.is_empty() {
+                problems.push(format!("{}: at least one path is required", name));
+            }
+            for path in &rule.paths {
+                if !path.starts_with('/') {
+                    problems.push(format!("{}: {:?} is not a JSON pointer", name, path));
+                }
+            }
+            for transform in &rule.transforms {
+                if let Err(e) = transform.validate() {
+                    problems.push(format!("{}: {}", name, e));
+                }
+            }
+            if *name == TIMESTAMP_FIELD {
+                if rule.date_formats.is_empty() {
+                    problems.push(format!("{}: at least one date format is required", name));
+                }
+                for format in &rule.date_formats {
+                    if !valid_date_format(format) {
+                        problems.push(format!("{}: invalid date format {:?}", name, format));
+                    }
+                }
+                if let Some(default) = &rule.default {
+                    if parse_date(default, &rule.date_formats).is_none() {
+                        problems.push(format!("{}: default {:?} matches no date format", name, default));
+                    }
+                }
+            } else if !rule.date_formats.is_empty() {
+                problems.push(format!("{}: date_formats apply only to {}", name, TIMESTAMP_FIELD));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(mapping)
+        } else {
+            Err(ApiError::validation(format!("Invalid OSDR mapping: {}", problems.join("; "))))
+        }
+    }
+
+    /// Колонки записи; без идентификатора upstream dataset_id синтетический,
+    /// чтобы повторная синхронизация попадала в ту же строку
+    pub fn normalize(&self, item: &Value) -> OsdrFields {
+        let string = |name: &str| self.string_field(name, item).0;
+        let mut fields = OsdrFields {
+            dataset_id: string("dataset_id"),
+            title: string("title"),
+            organism: string("organism"),
+            study_type: string("study_type"),
+            status: string("status"),
+            updated_at: self.timestamp_field(item).0,
+            raw: item.clone(),
+        };
+        if fields.dataset_id.is_none() {
+            fields.dataset_id = Some(synthetic_dataset_id(&fields));
+        }
+        fields
+    }
+
+    /// Значение и источник каждой колонки
+    pub fn explain(&self, item: &Value) -> BTreeMap<String, FieldTrace> {
+        let mut traces = BTreeMap::new();
+        for name in STRING_FIELDS {
+            let (value, source) = self.string_field(name, item);
+            traces.insert(name.to_string(), FieldTrace { value: json!(value), source });
+        }
+        let (value, source) = self.timestamp_field(item);
+        traces.insert(TIMESTAMP_FIELD.to_string(), FieldTrace { value: json!(value), source });
+        if traces["dataset_id"].source.is_none() {
+            traces.insert(
+                "dataset_id".to_string(),
+                FieldTrace {
+                    value: json!(self.normalize(item).dataset_id),
+                    source: Some("synthetic".to_string()),
+                },
+            );
+        }
+        traces
+    }
+
+    fn string_field(&self, name: &str, item: &Value) -> (Option<String>, Option<String>) {
+        let Some(rule) = self.fields.get(name) else {
+            return (None, None);
+        };
+        for path in &rule.paths {
+            let Some(raw) = item.pointer(path).and_then(scalar_string) else {
+                continue;
+            };
+            let value = rule.transforms.iter().fold(raw, |v, t| t.apply(v));
+            if !value.is_empty() {
+                return (Some(value), Some(path.clone()));
+            }
+        }
+        match &rule.default {
+            Some(default) => (Some(default.clone()), Some("default".to_string())),
+            None => (None, None),
+        }
+    }
+
+    fn timestamp_field(&self, item: &Value) -> (Option<DateTime<Utc>>, Option<String>) {
+        let Some(rule) = self.fields.get(TIMESTAMP_FIELD) else {
+            return (None, None);
+        };
+        for path in &rule.paths {
+            let Some(value) = item.pointer(path).map(first_scalar) else {
+                continue;
+            };
+            let parsed = match value {
+                Value::Number(n) => n.as_i64().and_then(|n| parse_unix(n, &rule.date_formats)),
+                Value::String(s) => {
+                    let s = rule.transforms.iter().fold(s.clone(), |v, t| t.apply(v));
+                    parse_date(&s, &rule.date_formats)
+                }
+                _ => None,
+            };
+            if let Some(ts) = parsed {
+                return (Some(ts), Some(path.clone()));
+            }
+        }
+        match rule.default.as_deref().and_then(|d| parse_date(d, &rule.date_formats)) {
+            Some(ts) => (Some(ts), Some("default".to_string())),
+            None => (None, None),
+        }
+    }
+}
+
+/// Из массива берётся первый непустой элемент
+fn first_scalar(value: &Value) -> &Value {
+    match value {
+        Value::Array(items) => items.iter().find(|v| !v.is_null()).unwrap_or(&Value::Null),
+        other => other,
+    }
+}
+
+fn scalar_string(value: &Value) -> Option<String> {
+    match first_scalar(value) {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn valid_date_format(format: &str) -> bool {
+    matches!(format, "rfc3339" | "unix" | "unix_ms")
+        || !StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+}
+
+fn parse_unix(n: i64, formats: &[String]) -> Option<DateTime<Utc>> {
+    formats.iter().find_map(|format| match format.as_str() {
+        "unix" => DateTime::from_timestamp(n, 0),
+        "unix_ms" => DateTime::from_timestamp_millis(n),
+        _ => None,
+    })
+}
+
+fn parse_date(s: &str, formats: &[String]) -> Option<DateTime<Utc>> {
+    formats.iter().find_map(|format| match format.as_str() {
+        "rfc3339" => s.parse::<DateTime<Utc>>().ok(),
+        "unix" | "unix_ms" => s.parse::<i64>().ok().and_then(|n| parse_unix(n, std::slice::from_ref(format))),
+        strftime => DateTime::parse_from_str(s, strftime)
+            .map(|dt| dt.with_timezone(&Utc))
+            .ok()
+            .or_else(|| NaiveDateTime::parse_from_str(s, strftime).ok().map(|dt| dt.and_utc()))
+            .or_else(|| {
+                NaiveDate::parse_from_str(s, strftime)
+                    .ok()
+                    .and_then(|d| d.and_hms_opt(0, 0, 0))
+                    .map(|dt| dt.and_utc())
+            }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_mapping() -> OsdrMapping {
+        OsdrMapping::from_json(DEFAULT_MAPPING).unwrap()
+    }
+
+    fn ts(s: &str) -> Option<DateTime<Utc>> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn default_mapping_over_sample_payloads() {
+        let mapping = default_mapping();
+        // (запись, dataset_id, title, status, updated_at)
+        let cases = [
+            (
+                json!({"dataset_id": " OSD-1 ", "title": "Rodent Research", "status": "public", "updated": "2024-03-01T10:00:00Z"}),
+                Some("OSD-1"),
+                Some("Rodent Research"),
+                Some("public"),
+                ts("2024-03-01T10:00:00Z"),
+            ),
+            // Запасные пути и unix-время числом
+            (
+                json!({"accession": "OSD-2", "name": "Plant growth", "state": "draft", "modified": 1709287200}),
+                Some("OSD-2"),
+                Some("Plant growth"),
+                Some("draft"),
+                ts("2024-03-01T10:00:00Z"),
+            ),
+            // Числовой id, массив с null и формат strftime
+            (
+                json!({"id": 379, "title": [null, "Bone loss"], "lastUpdated": "2024-03-01 10:00:00"}),
+                Some("379"),
+                Some("Bone loss"),
+                None,
+                ts("2024-03-01T10:00:00Z"),
+            ),
+            // Пустая строка пропускается, непарсящаяся дата — не ошибка
+            (
+                json!({"dataset_id": "   ", "uuid": "u-3", "title": "", "label": "Microbes", "updated": "yesterday"}),
+                Some("u-3"),
+                Some("Microbes"),
+                None,
+                None,
+            ),
+        ];
+
+        for (item, dataset_id, title, status, updated_at) in cases {
+            let fields = mapping.normalize(&item);
+            assert_eq!(fields.dataset_id.as_deref(), dataset_id, "{}", item);
+            assert_eq!(fields.title.as_deref(), title, "{}", item);
+            assert_eq!(fields.status.as_deref(), status, "{}", item);
+            assert_eq!(fields.updated_at, updated_at, "{}", item);
+            assert_eq!(fields.raw, item);
+        }
+    }
+
+    #[test]
+    fn missing_keys_give_synthetic_id() {
+        let mapping = default_mapping();
+        let item = json!({"description": "no known keys"});
+        let fields = mapping.normalize(&item);
+        assert!(fields.dataset_id.unwrap().starts_with("auto-hash-"));
+        assert_eq!((fields.title, fields.organism, fields.updated_at), (None, None, None));
+
+        let traces = mapping.explain(&item);
+        assert_eq!(traces["dataset_id"].source.as_deref(), Some("synthetic"));
+        assert_eq!(traces["title"].source, None);
+        assert_eq!(traces["title"].value, Value::Null);
+    }
+
+    #[test]
+    fn nested_paths_transforms_and_defaults() {
+        let mapping = OsdrMapping::from_json(
+            r#"{"fields": {
+                "dataset_id": {"paths": ["/meta/ids/0", "/id"]},
+                "title": {"paths": ["/study/info/title"], "transforms": ["trim", "uppercase"]},
+                "organism": {"paths": ["/study/organisms"], "transforms": [{"split": ";", "index": 1}]},
+                "study_type": {"paths": ["/type"], "default": "unknown"},
+                "status": {"paths": ["/a~1b"]},
+                "updated_at": {"paths": ["/dates/ms"], "date_formats": ["unix_ms"]}
+            }}"#,
+        )
+        .unwrap();
+        let item = json!({
+            "meta": {"ids": ["OSD-9"]},
+            "study": {"info": {"title": "  mice  "}, "organisms": "Homo sapiens; Mus musculus"},
+            "a/b": "public",
+            "dates": {"ms": 1709287200000_i64}
+        });
+
+        let fields = mapping.normalize(&item);
+        assert_eq!(fields.dataset_id.as_deref(), Some("OSD-9"));
+        assert_eq!(fields.title.as_deref(), Some("MICE"));
+        assert_eq!(fields.organism.as_deref(), Some("Mus musculus"));
+        assert_eq!(fields.study_type.as_deref(), Some("unknown"));
+        assert_eq!(fields.status.as_deref(), Some("public"));
+        assert_eq!(fields.updated_at, ts("2024-03-01T10:00:00Z"));
+
+        let traces = mapping.explain(&item);
+        assert_eq!(traces["dataset_id"].source.as_deref(), Some("/meta/ids/0"));
+        assert_eq!(traces["study_type"].source.as_deref(), Some("default"));
+        assert_eq!(traces["updated_at"].source.as_deref(), Some("/dates/ms"));
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        let rule = |name: &str, body: &str| {
+            let mut fields: BTreeMap<String, Value> = serde_json::from_str(
+                r#"{
+                "dataset_id": {"paths": ["/id"]},
+                "title": {"paths": ["/title"]},
+                "organism": {"paths": ["/organism"]},
+                "study_type": {"paths": ["/type"]},
+                "status": {"paths": ["/status"]},
+                "updated_at": {"paths": ["/updated"], "date_formats": ["rfc3339"]}
+            }"#,
+            )
+            .unwrap();
+            if body.is_empty() {
+                fields.remove(name);
+            } else {
+                fields.insert(name.to_string(), serde_json::from_str(body).unwrap());
+            }
+            json!({ "fields": fields }).to_string()
+        };
+
+        assert!(OsdrMapping::from_json(&rule("title", r#"{"paths": ["/title"]}"#)).is_ok());
+
+        // (правило, ожидаемый фрагмент ошибки)
+        let cases = [
+            ("not json".to_string(), "Invalid OSDR mapping"),
+            (r#"{"fields": []}"#.to_string(), "Invalid OSDR mapping"),
+            (rule("title", ""), "title: rule is missing"),
+            (rule("owner", r#"{"paths": ["/owner"]}"#), "owner: unknown field"),
+            (rule("title", r#"{"paths": []}"#), "title: at least one path is required"),
+            (rule("title", r#"{"paths": ["title"]}"#), "\"title\" is not a JSON pointer"),
+            (rule("title", r#"{"paths": ["/t"], "transforms": ["reverse"]}"#), "unknown transform \"reverse\""),
+            (rule("title", r#"{"paths": ["/t"], "transforms": [{"split": ""}]}"#), "split separator is empty"),
+            (rule("title", r#"{"paths": ["/t"], "date_formats": ["unix"]}"#), "date_formats apply only to updated_at"),
+            (rule("updated_at", r#"{"paths": ["/u"]}"#), "at least one date format is required"),
+            (rule("updated_at", r#"{"paths": ["/u"], "date_formats": ["%Q"]}"#), "invalid date format \"%Q\""),
+            (
+                rule("updated_at", r#"{"paths": ["/u"], "date_formats": ["unix"], "default": "soon"}"#),
+                "default \"soon\" matches no date format",
+            ),
+        ];
+        for (text, expected) in cases {
+            let err = OsdrMapping::from_json(&text).unwrap_err();
+            assert_eq!(err.code, "VALIDATION_ERROR");
+            assert!(err.message.contains(expected), "{:?} not in {:?}", expected, err.message);
+        }
+    }
+
+    #[test]
+    fn problems_are_collected_together() {
+        let err = OsdrMapping::from_json(r#"{"fields": {"title": {"paths": []}}}"#).unwrap_err();
+        for expected in ["dataset_id: rule is missing", "title: at least one path", "updated_at: rule is missing"] {
+            assert!(err.message.contains(expected), "{:?} not in {:?}", expected, err.message);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Курсорная пагинация по ключу (время, id): глубокие страницы не дороже первых,
+//! а записи не съезжают между страницами, пока в таблицу пишут.
+//! Для клиента курсор непрозрачен — это base64url от направления, порядка и ключа строки.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::errors::ApiError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorDirection {
+    /// Строки после курсора
+    Next,
+    /// Строки перед курсором
+    Prev,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub direction: CursorDirection,
+    /// Порядок списка, для которого выдан курсор
+    pub descending: bool,
+    /// Ключ граничной строки страницы; None — время у строки не задано
+    pub at: Option<DateTime<Utc>>,
+    pub id: i64,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let raw = format!(
+            "{}|{}|{}|{}",
+            if self.direction == CursorDirection::Next { 'n' } else { 'p' },
+            if self.descending { 'd' } else { 'a' },
+            self.at.map(|t| t.timestamp_micros().to_string()).unwrap_or_default(),
+            self.id
+        );
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    /// Разбирает курсор и проверяет, что он выдан для списка с тем же порядком
+    pub fn decode(s: &str, descending: bool) -> Result<Self, ApiError> {
+        let invalid = || ApiError::validation("Invalid cursor");
+        let raw = URL_SAFE_NO_PAD.decode(s.trim()).map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+
+        let parts: Vec<&str> = raw.split('|').collect();
+        let [direction, order, at, id] = parts.as_slice() else {
+            return Err(invalid());
+        };
+        let direction = match *direction {
+            "n" => CursorDirection::Next,
+            "p" => CursorDirection::Prev,
+            _ => return Err(invalid()),
+        };
+        let cursor_descending = match *order {
+            "d" => true,
+            "a" => false,
+            _ => return Err(invalid()),
+        };
+        let at = match *at {
+            "" => None,
+            micros => Some(
+                micros
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(DateTime::from_timestamp_micros)
+                    .ok_or_else(invalid)?,
+            ),
+        };
+        let id = id.parse::<i64>().map_err(|_| invalid())?;
+
+        if cursor_descending != descending {
+            return Err(ApiError::validation("Cursor was issued for a different sort order"));
+        }
+        Ok(Self {
+            direction,
+            descending,
+            at,
+            id,
+        })
+    }
+
+    /// Порядок чтения из базы: к предыдущей странице идём в обратную сторону
+    pub fn scan_descending(&self) -> bool {
+        self.descending != (self.direction == CursorDirection::Prev)
+    }
+}
+
+/// Страница списка. `total` считается только по запросу
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+}
+
+impl<T> Page<T> {
+    /// Собирает страницу из `rows` — до `limit + 1` строк в порядке чтения
+    /// (лишняя строка говорит, что дальше есть ещё). `has_before` — есть ли строки
+    /// перед первой без курсора (например, при offset > 0); `key` — (время, id) строки.
+    pub fn from_rows(
+        mut rows: Vec<T>,
+        limit: usize,
+        cursor: Option<&Cursor>,
+        descending: bool,
+        has_before: bool,
+        key: impl Fn(&T) -> (Option<DateTime<Utc>>, i64),
+    ) -> Self {
+        let more = rows.len() > limit;
+        rows.truncate(limit);
+
+        let backward = cursor.is_some_and(|c| c.direction == CursorDirection::Prev);
+        if backward {
+            rows.reverse();
+        }
+        // Назад шли от существующей страницы, значит после этой что-то есть
+        let (has_prev, has_next) = if backward {
+            (more, true)
+        } else {
+            (cursor.is_some() || has_before, more)
+        };
+
+        let make = |row: Option<&T>, direction| {
+            row.map(|row| {
+                let (at, id) = key(row);
+                Cursor {
+                    direction,
+                    descending,
+                    at,
+                    id,
+                }
+                .encode()
+            })
+        };
+        let next_cursor = if has_next { make(rows.last(), CursorDirection::Next) } else { None };
+        let prev_cursor = if has_prev { make(rows.first(), CursorDirection::Prev) } else { None };
+
+        Self {
+            items: rows,
+            next_cursor,
+            prev_cursor,
+            total: None,
+        }
+    }
+}
+
+/// Параметры страницы из запроса
+#[derive(Debug, Clone)]
+pub struct PageRequest {
+    pub limit: i64,
+    pub cursor: Option<Cursor>,
+    /// Считать ли `total` — это отдельный COUNT по всему фильтру
+    pub include_total: bool,
+}
+
+/// Выражение ключа в SQL; `null_as` — чем заменить NULL во времени
+pub fn key_expr(column: &str, null_as: Option<&str>) -> String {
+    match null_as {
+        Some(fill) => format!("COALESCE({}, {})", column, fill),
+        None => column.to_string(),
+    }
+}
+
+/// ORDER BY по ключу (время, id)
+pub fn key_order(column: &str, null_as: Option<&str>, descending: bool) -> String {
+    let direction = if descending { "DESC" } else { "ASC" };
+    format!("{} {}, id {}", key_expr(column, null_as), direction, direction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(minute: u32) -> Option<DateTime<Utc>> {
+        Some(Utc.with_ymd_and_hms(2024, 5, 1, 12, minute, 0).unwrap())
+    }
+
+    fn cursor(direction: CursorDirection, at: Option<DateTime<Utc>>, id: i64) -> Cursor {
+        Cursor {
+            direction,
+            descending: true,
+            at,
+            id,
+        }
+    }
+
+    /// Строки — (минута, id); ключ страницы берётся из них
+    fn page(rows: Vec<(u32, i64)>, limit: usize, cursor: Option<&Cursor>, has_before: bool) -> Page<(u32, i64)> {
+        Page::from_rows(rows, limit, cursor, true, has_before, |&(minute, id)| (at(minute), id))
+    }
+
+    fn decode(raw: &Option<String>) -> Cursor {
+        Cursor::decode(raw.as_deref().unwrap(), true).unwrap()
+    }
+
+    #[test]
+    fn cursor_round_trip() {
+        let original = Cursor {
+            direction: CursorDirection::Prev,
+            descending: false,
+            at: Some(Utc.timestamp_micros(1_714_564_800_123_456).unwrap()),
+            id: 42,
+        };
+        let encoded = original.encode();
+        assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(Cursor::decode(&encoded, false).unwrap(), original);
+    }
+
+    #[test]
+    fn cursor_without_time_round_trips() {
+        let original = cursor(CursorDirection::Next, None, 7);
+        assert_eq!(Cursor::decode(&original.encode(), true).unwrap(), original);
+    }
+
+    #[test]
+    fn cursor_rejects_other_order_and_garbage() {
+        let encoded = cursor(CursorDirection::Next, at(0), 1).encode();
+        let err = Cursor::decode(&encoded, false).unwrap_err();
+        assert_eq!(err.code, "VALIDATION_ERROR");
+        assert!(err.message.contains("sort order"));
+
+        for raw in ["", "!!!", "bm90IGEgY3Vyc29y", &URL_SAFE_NO_PAD.encode("n|d|x|1"), &URL_SAFE_NO_PAD.encode("x|d||1")] {
+            let err = Cursor::decode(raw, true).unwrap_err();
+            assert_eq!(err.message, "Invalid cursor", "input {:?}", raw);
+        }
+    }
+
+    #[test]
+    fn scan_order_flips_for_prev() {
+        assert!(cursor(CursorDirection::Next, None, 1).scan_descending());
+        assert!(!cursor(CursorDirection::Prev, None, 1).scan_descending());
+    }
+
+    #[test]
+    fn first_page_has_only_next() {
+        let page = page(vec![(5, 5), (4, 4), (3, 3)], 2, None, false);
+        assert_eq!(page.items, vec![(5, 5), (4, 4)]);
+        assert!(page.prev_cursor.is_none());
+        let next = decode(&page.next_cursor);
+        assert_eq!((next.direction, next.at, next.id), (CursorDirection::Next, at(4), 4));
+    }
+
+    #[test]
+    fn last_page_has_only_prev() {
+        let from = cursor(CursorDirection::Next, at(2), 2);
+        let page = page(vec![(1, 1)], 2, Some(&from), false);
+        assert!(page.next_cursor.is_none());
+        let prev = decode(&page.prev_cursor);
+        assert_eq!((prev.direction, prev.id), (CursorDirection::Prev, 1));
+    }
+
+    #[test]
+    fn offset_page_gets_prev() {
+        let page = page(vec![(3, 3), (2, 2)], 2, None, true);
+        assert!(page.next_cursor.is_none());
+        assert_eq!(decode(&page.prev_cursor).id, 3);
+    }
+
+    #[test]
+    fn prev_page_is_reversed() {
+        // Назад от id 3 строки читаются по возрастанию: 4, 5, 6 (лишняя)
+        let from = cursor(CursorDirection::Prev, at(3), 3);
+        let page = page(vec![(4, 4), (5, 5), (6, 6)], 2, Some(&from), false);
+        assert_eq!(page.items, vec![(5, 5), (4, 4)]);
+        let next = decode(&page.next_cursor);
+        assert_eq!((next.direction, next.id), (CursorDirection::Next, 4));
+        let prev = decode(&page.prev_cursor);
+        assert_eq!((prev.direction, prev.id), (CursorDirection::Prev, 5));
+    }
+
+    #[test]
+    fn prev_page_at_start_has_no_prev() {
+        let from = cursor(CursorDirection::Prev, at(3), 3);
+        let page = page(vec![(4, 4)], 2, Some(&from), false);
+        assert_eq!(page.items, vec![(4, 4)]);
+        assert!(page.prev_cursor.is_none());
+        assert!(page.next_cursor.is_some());
+    }
+
+    #[test]
+    fn null_time_is_kept_in_cursor() {
+        let rows = vec![(None, 9_i64), (None, 8), (None, 7)];
+        let page = Page::from_rows(rows, 2, None, true, false, |&(at, id)| (at, id));
+        let next = decode(&page.next_cursor);
+        assert_eq!((next.at, next.id), (None, 8));
+    }
+
+    #[test]
+    fn key_order_fills_nulls() {
+        assert_eq!(key_order("fetched_at", None, true), "fetched_at DESC, id DESC");
+        assert_eq!(
+            key_order("updated_at", Some("'infinity'::timestamptz"), false),
+            "COALESCE(updated_at, 'infinity'::timestamptz) ASC, id ASC"
+        );
+    }
+}
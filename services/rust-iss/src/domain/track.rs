@@ -0,0 +1,223 @@
+//! Выгрузка трека в GeoJSON, KML, GPX и CSV построчно, без сборки всего файла в памяти
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+use crate::domain::{DataGap, IssFetchLog};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackFormat {
+    GeoJson,
+    Kml,
+    Gpx,
+    Csv,
+}
+
+impl TrackFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "geojson" | "json" => Some(Self::GeoJson),
+            "kml" => Some(Self::Kml),
+            "gpx" => Some(Self::Gpx),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::GeoJson => "application/geo+json",
+            Self::Kml => "application/vnd.google-earth.kml+xml",
+            Self::Gpx => "application/gpx+xml",
+            Self::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::GeoJson => "geojson",
+            Self::Kml => "kml",
+            Self::Gpx => "gpx",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+/// Пишет заголовок, строки и окончание файла; помнит, была ли уже строка.
+/// Точка после пропуска помечается `gap_before`, в GPX и KML с неё начинается новый отрезок
+pub struct TrackWriter {
+    format: TrackFormat,
+    name: String,
+    rows: u64,
+    /// Концы закрытых пропусков по возрастанию и индекс следующего непройденного
+    gap_ends: Vec<DateTime<Utc>>,
+    next_gap: usize,
+    /// Пропуск пройден, но точка после него ещё не записана
+    pending_gap: bool,
+    /// Номер текущего отрезка KML; 0 — отрезок не открыт
+    segment: u64,
+}
+
+impl TrackWriter {
+    pub fn new(format: TrackFormat, name: impl Into<String>, gaps: &[DataGap]) -> Self {
+        let mut gap_ends: Vec<DateTime<Utc>> = gaps.iter().filter(|g| !g.open).map(|g| g.end).collect();
+        gap_ends.sort();
+        Self {
+            format,
+            name: name.into(),
+            rows: 0,
+            gap_ends,
+            next_gap: 0,
+            pending_gap: false,
+            segment: 0,
+        }
+    }
+
+    pub fn header(&self) -> String {
+        let name = xml_escape(&self.name);
+        match self.format {
+            TrackFormat::GeoJson => format!(
+                "{{\"type\":\"FeatureCollection\",\"name\":{},\"features\":[\n",
+                json!(self.name)
+            ),
+            TrackFormat::Kml => format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n<name>{}</name>\n",
+                name
+            ),
+            TrackFormat::Gpx => format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <gpx version=\"1.1\" creator=\"rust_iss\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n\
+                 <trk>\n<name>{}</name>\n<trkseg>\n",
+                name
+            ),
+            TrackFormat::Csv => {
+                "id,norad_id,timestamp,latitude,longitude,altitude_km,velocity_kmh,visibility,location,gap_before\n"
+                    .to_string()
+            }
+        }
+    }
+
+    /// Строка для одной позиции; точки без координат в гео-форматах пропускаются
+    /// (пропуск перед такой точкой переходит на следующую записанную)
+    pub fn row(&mut self, log: &IssFetchLog) -> Option<String> {
+        while self.gap_ends.get(self.next_gap).is_some_and(|end| *end <= log.fetched_at) {
+            self.pending_gap = true;
+            self.next_gap += 1;
+        }
+        let gap_before = self.pending_gap;
+        let time = log.position_at().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let location = log.location.as_ref().map(|l| l.name.as_str());
+
+        let row = match self.format {
+            TrackFormat::Csv => {
+                let cells = [
+                    log.id.to_string(),
+                    log.norad_id.to_string(),
+                    time,
+                    opt_number(log.lat()),
+                    opt_number(log.lon()),
+                    opt_number(log.altitude()),
+                    opt_number(log.velocity()),
+                    log.visibility().as_deref().map(csv_escape).unwrap_or_default(),
+                    location.map(csv_escape).unwrap_or_default(),
+                    gap_before.to_string(),
+                ];
+                format!("{}\n", cells.join(","))
+            }
+            TrackFormat::GeoJson => {
+                let (lat, lon, ele) = point(log)?;
+                let mut coordinates = vec![json!(lon), json!(lat)];
+                if let Some(ele) = ele {
+                    coordinates.push(json!(ele));
+                }
+                let feature = json!({
+                    "type": "Feature",
+                    "geometry": {"type": "Point", "coordinates": coordinates},
+                    "properties": {
+                        "id": log.id,
+                        "norad_id": log.norad_id,
+                        "time": time,
+                        "altitude_km": log.altitude(),
+                        "velocity_kmh": log.velocity(),
+                        "visibility": log.visibility(),
+                        "location": location,
+                        "gap_before": gap_before,
+                    },
+                });
+                let sep = if self.rows == 0 { "" } else { "," };
+                format!("{}{}\n", sep, feature)
+            }
+            TrackFormat::Kml => {
+                let (lat, lon, ele) = point(log)?;
+                let mut out = String::new();
+                if self.segment == 0 || gap_before {
+                    if self.segment > 0 {
+                        out.push_str(KML_SEGMENT_END);
+                    }
+                    self.segment += 1;
+                    out.push_str(&format!(
+                        "<Placemark><name>Segment {} from {}</name><TimeStamp><when>{}</when></TimeStamp>\
+                         <LineString><altitudeMode>absolute</altitudeMode><coordinates>\n",
+                        self.segment, time, time
+                    ));
+                }
+                out.push_str(&format!("{},{},{}\n", lon, lat, ele.unwrap_or(0.0)));
+                out
+            }
+            TrackFormat::Gpx => {
+                let (lat, lon, ele) = point(log)?;
+                let split = if gap_before && self.rows > 0 { "</trkseg>\n<trkseg>\n" } else { "" };
+                format!(
+                    "{}<trkpt lat=\"{}\" lon=\"{}\">{}<time>{}</time></trkpt>\n",
+                    split,
+                    lat,
+                    lon,
+                    ele.map(|e| format!("<ele>{}</ele>", e)).unwrap_or_default(),
+                    time
+                )
+            }
+        };
+
+        self.pending_gap = false;
+        self.rows += 1;
+        Some(row)
+    }
+
+    pub fn footer(&self) -> String {
+        match self.format {
+            TrackFormat::GeoJson => "]}\n".to_string(),
+            TrackFormat::Kml if self.segment > 0 => format!("{}</Document>\n</kml>\n", KML_SEGMENT_END),
+            TrackFormat::Kml => "</Document>\n</kml>\n".to_string(),
+            TrackFormat::Gpx => "</trkseg>\n</trk>\n</gpx>\n".to_string(),
+            TrackFormat::Csv => String::new(),
+        }
+    }
+}
+
+/// Закрывает отрезок трека KML: одна линия на участок без пропусков
+const KML_SEGMENT_END: &str = "</coordinates></LineString></Placemark>\n";
+
+/// Широта, долгота и высота в метрах (в источнике высота в км)
+fn point(log: &IssFetchLog) -> Option<(f64, f64, Option<f64>)> {
+    Some((log.lat()?, log.lon()?, log.altitude().map(|km| km * 1000.0)))
+}
+
+fn opt_number(v: Option<f64>) -> String {
+    v.map(|x| x.to_string()).unwrap_or_default()
+}
+
+fn csv_escape(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,476 @@
+//! Потоковая запись XLSX: один лист, строки сжимаются и отдаются по мере записи.
+//! ZIP собирается вручную (deflate + data descriptor), поэтому размер файла
+//! не ограничен памятью. ZIP64 не поддерживается: на лимите строк Excel или 4 ГБ
+//! запись прекращается с ошибкой EXPORT_TOO_LARGE.
+
+use std::io::Write;
+
+use chrono::{Datelike, Timelike, Utc};
+use crc32fast::Hasher;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+use crate::errors::ApiError;
+
+const CONTENT_TYPES: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+    r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+    r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+    r#"<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#,
+    r#"<Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#,
+    r#"</Types>"#,
+);
+
+const ROOT_RELS: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/>"#,
+    r#"</Relationships>"#,
+);
+
+const WORKBOOK_RELS: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/>"#,
+    r#"</Relationships>"#,
+);
+
+const SHEET_PATH: &str = "xl/worksheets/sheet1.xml";
+const SHEET_START: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#,
+);
+const SHEET_END: &str = "</sheetData></worksheet>";
+
+/// Предел длины текста в ячейке Excel
+const MAX_CELL_CHARS: usize = 32_767;
+/// Предел строк на листе Excel, включая заголовок
+pub const MAX_ROWS: u64 = 1_048_576;
+
+/// Значение ячейки
+pub enum Cell<'a> {
+    Text(&'a str),
+    Number(f64),
+    Empty,
+}
+
+/// Запись в центральном каталоге ZIP
+struct Entry {
+    name: &'static str,
+    crc: u32,
+    compressed: u32,
+    size: u32,
+    offset: u32,
+    flags: u16,
+}
+
+pub struct XlsxStream {
+    entries: Vec<Entry>,
+    /// Сколько байт уже отдано
+    written: u32,
+    dos_time: u16,
+    dos_date: u16,
+    sheet: Option<SheetPart>,
+    rows: u64,
+}
+
+/// Лист пишется последним и единственный идёт потоком
+struct SheetPart {
+    offset: u32,
+    encoder: DeflateEncoder<Vec<u8>>,
+    hasher: Hasher,
+    size: u32,
+    compressed: u32,
+}
+
+impl XlsxStream {
+    pub fn new() -> Self {
+        let now = Utc::now();
+        Self {
+            entries: Vec::new(),
+            written: 0,
+            dos_time: ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16,
+            dos_date: (((now.year() - 1980).max(0) as u32) << 9 | (now.month() << 5) | now.day()) as u16,
+            sheet: None,
+            rows: 0,
+        }
+    }
+
+    /// Служебные части книги и начало листа
+    pub fn start(&mut self, sheet_name: &str) -> Result<Vec<u8>, ApiError> {
+        let workbook = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
+                r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">"#,
+                r#"<sheets><sheet name="{}" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+            ),
+            xml_text(sheet_name)
+        );
+
+        let mut out = Vec::new();
+        self.whole_part(&mut out, "[Content_Types].xml", CONTENT_TYPES.as_bytes())?;
+        self.whole_part(&mut out, "_rels/.rels", ROOT_RELS.as_bytes())?;
+        self.whole_part(&mut out, "xl/workbook.xml", workbook.as_bytes())?;
+        self.whole_part(&mut out, "xl/_rels/workbook.xml.rels", WORKBOOK_RELS.as_bytes())?;
+
+        // Размеры и CRC листа неизвестны заранее — они придут в data descriptor
+        let offset = self.written;
+        self.local_header(&mut out, SHEET_PATH, 0x0008, 0, 0, 0)?;
+        self.sheet = Some(SheetPart {
+            offset,
+            encoder: DeflateEncoder::new(Vec::new(), Compression::default()),
+            hasher: Hasher::new(),
+            size: 0,
+            compressed: 0,
+        });
+        out.extend(self.sheet_bytes(SHEET_START.as_bytes())?);
+        Ok(out)
+    }
+
+    /// Очередная строка; возвращает готовые к отправке байты (могут быть пустыми,
+    /// пока deflate копит вход)
+    pub fn row(&mut self, cells: &[Cell<'_>]) -> Result<Vec<u8>, ApiError> {
+        if self.rows >= MAX_ROWS {
+            return Err(too_large(format!("XLSX sheet is limited to {} rows", MAX_ROWS)));
+        }
+        self.rows += 1;
+        let mut xml = format!("<row r=\"{}\">", self.rows);
+        for cell in cells {
+            match cell {
+                Cell::Text(s) => {
+                    let s: String = s.chars().take(MAX_CELL_CHARS).collect();
+                    xml.push_str("<c t=\"inlineStr\"><is><t xml:space=\"preserve\">");
+                    xml.push_str(&xml_text(&s));
+                    xml.push_str("</t></is></c>");
+                }
+                Cell::Number(n) if n.is_finite() => xml.push_str(&format!("<c><v>{}</v></c>", n)),
+                Cell::Number(_) | Cell::Empty => xml.push_str("<c/>"),
+            }
+        }
+        xml.push_str("</row>");
+        self.sheet_bytes(xml.as_bytes())
+    }
+
+    /// Конец листа, data descriptor, центральный каталог
+    pub fn finish(&mut self) -> Result<Vec<u8>, ApiError> {
+        let mut out = self.sheet_bytes(SHEET_END.as_bytes())?;
+        let Some(sheet) = self.sheet.take() else {
+            return Ok(out);
+        };
+
+        let tail = sheet.encoder.finish().unwrap_or_default();
+        let mut compressed = sheet.compressed;
+        grow(&mut compressed, tail.len())?;
+        let crc = sheet.hasher.finalize();
+        self.emit(&mut out, &tail)?;
+
+        let mut descriptor = Vec::with_capacity(16);
+        push_u32(&mut descriptor, 0x0807_4b50);
+        push_u32(&mut descriptor, crc);
+        push_u32(&mut descriptor, compressed);
+        push_u32(&mut descriptor, sheet.size);
+        self.emit(&mut out, &descriptor)?;
+        self.entries.push(Entry {
+            name: SHEET_PATH,
+            crc,
+            compressed,
+            size: sheet.size,
+            offset: sheet.offset,
+            flags: 0x0008,
+        });
+
+        let directory_offset = self.written;
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            push_u32(&mut directory, 0x0201_4b50);
+            push_u16(&mut directory, 20);
+            push_u16(&mut directory, 20);
+            push_u16(&mut directory, entry.flags);
+            push_u16(&mut directory, 8);
+            push_u16(&mut directory, self.dos_time);
+            push_u16(&mut directory, self.dos_date);
+            push_u32(&mut directory, entry.crc);
+            push_u32(&mut directory, entry.compressed);
+            push_u32(&mut directory, entry.size);
+            push_u16(&mut directory, entry.name.len() as u16);
+            // extra, comment, disk, internal и external attributes
+            directory.extend_from_slice(&[0; 12]);
+            push_u32(&mut directory, entry.offset);
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = directory.len() as u32;
+        self.emit(&mut out, &directory)?;
+
+        let mut end = Vec::with_capacity(22);
+        push_u32(&mut end, 0x0605_4b50);
+        push_u32(&mut end, 0);
+        push_u16(&mut end, self.entries.len() as u16);
+        push_u16(&mut end, self.entries.len() as u16);
+        push_u32(&mut end, directory_size);
+        push_u32(&mut end, directory_offset);
+        push_u16(&mut end, 0);
+        self.emit(&mut out, &end)?;
+        Ok(out)
+    }
+
+    fn whole_part(&mut self, out: &mut Vec<u8>, name: &'static str, data: &[u8]) -> Result<(), ApiError> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        let _ = encoder.write_all(data);
+        let compressed = encoder.finish().unwrap_or_default();
+        let crc = crc32fast::hash(data);
+
+        let offset = self.written;
+        self.local_header(out, name, 0, crc, compressed.len() as u32, data.len() as u32)?;
+        self.emit(out, &compressed)?;
+        self.entries.push(Entry {
+            name,
+            crc,
+            compressed: compressed.len() as u32,
+            size: data.len() as u32,
+            offset,
+            flags: 0,
+        });
+        Ok(())
+    }
+
+    fn local_header(
+        &mut self,
+        out: &mut Vec<u8>,
+        name: &str,
+        flags: u16,
+        crc: u32,
+        compressed: u32,
+        size: u32,
+    ) -> Result<(), ApiError> {
+        let mut header = Vec::with_capacity(30 + name.len());
+        push_u32(&mut header, 0x0403_4b50);
+        push_u16(&mut header, 20);
+        push_u16(&mut header, flags);
+        push_u16(&mut header, 8);
+        push_u16(&mut header, self.dos_time);
+        push_u16(&mut header, self.dos_date);
+        push_u32(&mut header, crc);
+        push_u32(&mut header, compressed);
+        push_u32(&mut header, size);
+        push_u16(&mut header, name.len() as u16);
+        push_u16(&mut header, 0);
+        header.extend_from_slice(name.as_bytes());
+        self.emit(out, &header)
+    }
+
+    /// Пропускает данные листа через deflate и забирает то, что уже сжато
+    fn sheet_bytes(&mut self, data: &[u8]) -> Result<Vec<u8>, ApiError> {
+        let Some(sheet) = self.sheet.as_mut() else {
+            return Ok(Vec::new());
+        };
+        grow(&mut sheet.size, data.len())?;
+        sheet.hasher.update(data);
+        let _ = sheet.encoder.write_all(data);
+        let ready = std::mem::take(sheet.encoder.get_mut());
+        grow(&mut sheet.compressed, ready.len())?;
+        grow(&mut self.written, ready.len())?;
+        Ok(ready)
+    }
+
+    fn emit(&mut self, out: &mut Vec<u8>, bytes: &[u8]) -> Result<(), ApiError> {
+        grow(&mut self.written, bytes.len())?;
+        out.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+impl Default for XlsxStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Размеры и смещения ZIP без ZIP64 — 32 бита
+fn grow(total: &mut u32, len: usize) -> Result<(), ApiError> {
+    *total = u32::try_from(len)
+        .ok()
+        .and_then(|len| total.checked_add(len))
+        .ok_or_else(|| too_large("XLSX export exceeds the 4 GiB ZIP limit".to_string()))?;
+    Ok(())
+}
+
+fn too_large(message: String) -> ApiError {
+    ApiError::new("EXPORT_TOO_LARGE", format!("{}; narrow the filter or use csv/ndjson", message))
+}
+
+fn push_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn push_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+/// Экранирование для XML; управляющие символы, недопустимые в XML 1.0, выбрасываются
+fn xml_text(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if (c as u32) < 0x20 || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::BTreeMap;
+    use std::io::Read;
+
+    use flate2::read::DeflateDecoder;
+
+    use super::*;
+
+    fn u16_at(data: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([data[at], data[at + 1]])
+    }
+
+    fn u32_at(data: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+    }
+
+    /// Разбирает ZIP по центральному каталогу и проверяет каждую часть:
+    /// сигнатуры, смещения, размеры и CRC. Возвращает имя → содержимое
+    pub(crate) fn unzip(data: &[u8]) -> BTreeMap<String, String> {
+        let end = data.len() - 22;
+        assert_eq!(u32_at(data, end), 0x0605_4b50, "end of central directory");
+        let count = u16_at(data, end + 10) as usize;
+        let directory_size = u32_at(data, end + 12) as usize;
+        let mut at = u32_at(data, end + 16) as usize;
+        assert_eq!(at + directory_size, end, "directory ends where the end record starts");
+
+        let mut parts = BTreeMap::new();
+        for _ in 0..count {
+            assert_eq!(u32_at(data, at), 0x0201_4b50, "central directory header");
+            let flags = u16_at(data, at + 8);
+            assert_eq!(u16_at(data, at + 10), 8, "deflate");
+            let crc = u32_at(data, at + 16);
+            let compressed = u32_at(data, at + 20) as usize;
+            let size = u32_at(data, at + 24) as usize;
+            let name_len = u16_at(data, at + 28) as usize;
+            let offset = u32_at(data, at + 42) as usize;
+            let name = String::from_utf8(data[at + 46..at + 46 + name_len].to_vec()).unwrap();
+            at += 46 + name_len;
+
+            assert_eq!(u32_at(data, offset), 0x0403_4b50, "local header of {}", name);
+            assert_eq!(u16_at(data, offset + 6), flags, "flags of {}", name);
+            let local_name_len = u16_at(data, offset + 26) as usize;
+            let start = offset + 30 + local_name_len + u16_at(data, offset + 28) as usize;
+            assert_eq!(&data[offset + 30..offset + 30 + local_name_len], name.as_bytes());
+            if flags & 0x0008 != 0 {
+                let descriptor = start + compressed;
+                assert_eq!(u32_at(data, descriptor), 0x0807_4b50, "data descriptor of {}", name);
+                assert_eq!(u32_at(data, descriptor + 4), crc);
+                assert_eq!(u32_at(data, descriptor + 8) as usize, compressed);
+                assert_eq!(u32_at(data, descriptor + 12) as usize, size);
+            } else {
+                assert_eq!(u32_at(data, offset + 14), crc, "local crc of {}", name);
+            }
+
+            let mut content = Vec::new();
+            DeflateDecoder::new(&data[start..start + compressed]).read_to_end(&mut content).unwrap();
+            assert_eq!(content.len(), size, "size of {}", name);
+            assert_eq!(crc32fast::hash(&content), crc, "crc of {}", name);
+            parts.insert(name, String::from_utf8(content).unwrap());
+        }
+        parts
+    }
+
+    #[test]
+    fn workbook_is_valid_zip() {
+        let mut xlsx = XlsxStream::new();
+        let mut out = xlsx.start("Data").unwrap();
+        for i in 0..2000 {
+            out.extend(xlsx.row(&[Cell::Number(i as f64), Cell::Text("row"), Cell::Empty]).unwrap());
+        }
+        out.extend(xlsx.finish().unwrap());
+
+        let parts = unzip(&out);
+        assert_eq!(
+            parts.keys().map(String::as_str).collect::<Vec<_>>(),
+            [
+                "[Content_Types].xml",
+                "_rels/.rels",
+                "xl/_rels/workbook.xml.rels",
+                "xl/workbook.xml",
+                "xl/worksheets/sheet1.xml"
+            ]
+        );
+        let sheet = &parts[SHEET_PATH];
+        assert!(sheet.starts_with(SHEET_START) && sheet.ends_with(SHEET_END));
+        assert_eq!(sheet.matches("<row ").count(), 2000);
+        assert!(sheet.contains(r#"<row r="2000"><c><v>1999</v></c><c t="inlineStr"><is><t xml:space="preserve">row</t></is></c><c/></row>"#));
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        let mut xlsx = XlsxStream::new();
+        let mut out = xlsx.start("A&B <\"1\">").unwrap();
+        out.extend(xlsx.row(&[
+            Cell::Text("<b>Tom & \"Jerry\"</b>"),
+            Cell::Text("line\nbreak\u{1}\u{FFFF}"),
+            Cell::Number(f64::NAN),
+            Cell::Number(2.5),
+        ])
+        .unwrap());
+        out.extend(xlsx.finish().unwrap());
+
+        let parts = unzip(&out);
+        assert!(parts["xl/workbook.xml"].contains(r#"<sheet name="A&amp;B &lt;&quot;1&quot;&gt;""#));
+        let sheet = &parts[SHEET_PATH];
+        assert!(sheet.contains("&lt;b&gt;Tom &amp; &quot;Jerry&quot;&lt;/b&gt;"));
+        assert!(sheet.contains(">line\nbreak</t>"));
+        assert!(sheet.contains("<c/><c><v>2.5</v></c>"));
+        assert!(!sheet.contains("<b>") && !sheet.contains("Tom & "));
+    }
+
+    #[test]
+    fn long_text_is_truncated() {
+        let mut xlsx = XlsxStream::new();
+        let mut out = xlsx.start("Data").unwrap();
+        out.extend(xlsx.row(&[Cell::Text(&"я".repeat(MAX_CELL_CHARS + 10))]).unwrap());
+        out.extend(xlsx.finish().unwrap());
+
+        let sheet = &unzip(&out)[SHEET_PATH];
+        assert_eq!(sheet.matches('я').count(), MAX_CELL_CHARS);
+    }
+
+    #[test]
+    fn row_limit_stops_export() {
+        let mut xlsx = XlsxStream::new();
+        xlsx.start("Data").unwrap();
+        xlsx.rows = MAX_ROWS - 1;
+        xlsx.row(&[Cell::Number(1.0)]).unwrap();
+        let err = xlsx.row(&[Cell::Number(2.0)]).unwrap_err();
+        assert_eq!(err.code, "EXPORT_TOO_LARGE");
+        assert_eq!(xlsx.rows, MAX_ROWS);
+    }
+
+    #[test]
+    fn zip_size_limit_stops_export() {
+        let mut xlsx = XlsxStream::new();
+        xlsx.start("Data").unwrap();
+        xlsx.written = u32::MAX - 16;
+        let err = xlsx.finish().unwrap_err();
+        assert_eq!(err.code, "EXPORT_TOO_LARGE");
+
+        let mut xlsx = XlsxStream::new();
+        xlsx.start("Data").unwrap();
+        xlsx.sheet.as_mut().unwrap().size = u32::MAX - 16;
+        let err = xlsx.row(&[Cell::Text("row")]).unwrap_err();
+        assert_eq!(err.code, "EXPORT_TOO_LARGE");
+    }
+}
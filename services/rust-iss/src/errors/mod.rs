@@ -90,30 +90,3 @@ impl From<anyhow::Error> for ApiError {
         Self::internal(err.to_string())
     }
 }
-
-#[derive(Debug, Serialize)]
-pub struct ApiResponse<T> {
-    pub ok: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<T>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub error: Option<ApiError>,
-}
-
-impl<T> ApiResponse<T> {
-    pub fn success(data: T) -> Self {
-        Self {
-            ok: true,
-            data: Some(data),
-            error: None,
-        }
-    }
-
-    pub fn error(error: ApiError) -> ApiResponse<()> {
-        ApiResponse {
-            ok: false,
-            data: None,
-            error: Some(error),
-        }
-    }
-}
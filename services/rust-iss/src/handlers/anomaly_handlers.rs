@@ -0,0 +1,67 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::handlers::{IssResponse, SatelliteId};
+use crate::repo::{AnomalyRepository, IssRepository};
+use crate::services::AnomalyService;
+
+pub type AnomalyServiceState<A, I> = Arc<AnomalyService<A, I>>;
+
+#[derive(Debug, Deserialize)]
+pub struct AnomalyQuery {
+    /// position_jump, velocity_outlier, reboost, rapid_decay
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+}
+
+fn default_limit() -> i64 {
+    100
+}
+
+pub async fn get_anomalies<A: AnomalyRepository, I: IssRepository>(
+    State(svc): State<AnomalyServiceState<A, I>>,
+    SatelliteId(norad_id): SatelliteId,
+    Query(query): Query<AnomalyQuery>,
+) -> Json<IssResponse> {
+    let limit = query.limit.clamp(1, 1000);
+    let result = match svc.list(norad_id, query.kind, query.from, query.to, limit).await {
+        Ok(anomalies) => svc
+            .decay_rate(norad_id)
+            .await
+            .map(|decay| (anomalies, decay)),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok((anomalies, decay)) => Json(IssResponse {
+            ok: true,
+            data: Some(json!({
+                "norad_id": norad_id,
+                "decay_km_per_day": decay,
+                "anomalies": anomalies,
+            })),
+            error: None,
+        }),
+        Err(e) => Json(IssResponse {
+            ok: false,
+            data: None,
+            error: Some(json!({
+                "code": e.code,
+                "message": e.message,
+                "trace_id": e.trace_id,
+            })),
+        }),
+    }
+}
@@ -0,0 +1,118 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::domain::geofence::GeofenceShape;
+use crate::errors::ApiError;
+use crate::handlers::IssResponse;
+use crate::repo::GeofenceRepository;
+use crate::services::GeofenceService;
+
+pub type GeofenceServiceState<G> = Arc<GeofenceService<G>>;
+
+#[derive(Debug, Deserialize)]
+pub struct GeofenceBody {
+    pub name: String,
+    pub shape: GeofenceShape,
+    /// Пусто — зона для всех спутников
+    #[serde(default)]
+    pub norad_id: Option<i64>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GeofenceEventsQuery {
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+}
+
+fn default_limit() -> i64 {
+    100
+}
+
+fn respond<T: serde::Serialize>(result: Result<T, ApiError>) -> Json<IssResponse> {
+    match result {
+        Ok(data) => Json(IssResponse {
+            ok: true,
+            data: Some(json!(data)),
+            error: None,
+        }),
+        Err(e) => Json(IssResponse {
+            ok: false,
+            data: None,
+            error: Some(json!({
+                "code": e.code,
+                "message": e.message,
+                "trace_id": e.trace_id,
+            })),
+        }),
+    }
+}
+
+pub async fn list_geofences<G: GeofenceRepository>(
+    State(svc): State<GeofenceServiceState<G>>,
+) -> Json<IssResponse> {
+    respond(svc.list().await)
+}
+
+pub async fn get_geofence<G: GeofenceRepository>(
+    State(svc): State<GeofenceServiceState<G>>,
+    Path(id): Path<i64>,
+) -> Json<IssResponse> {
+    respond(svc.get(id).await)
+}
+
+pub async fn create_geofence<G: GeofenceRepository>(
+    State(svc): State<GeofenceServiceState<G>>,
+    Json(body): Json<GeofenceBody>,
+) -> Json<IssResponse> {
+    respond(
+        svc.create(body.name.trim(), &body.shape, body.norad_id, body.enabled)
+            .await,
+    )
+}
+
+pub async fn update_geofence<G: GeofenceRepository>(
+    State(svc): State<GeofenceServiceState<G>>,
+    Path(id): Path<i64>,
+    Json(body): Json<GeofenceBody>,
+) -> Json<IssResponse> {
+    respond(
+        svc.update(id, body.name.trim(), &body.shape, body.norad_id, body.enabled)
+            .await,
+    )
+}
+
+pub async fn delete_geofence<G: GeofenceRepository>(
+    State(svc): State<GeofenceServiceState<G>>,
+    Path(id): Path<i64>,
+) -> Json<IssResponse> {
+    respond(
+        svc.delete(id)
+            .await
+            .map(|()| json!({"status": "deleted", "id": id})),
+    )
+}
+
+pub async fn get_geofence_events<G: GeofenceRepository>(
+    State(svc): State<GeofenceServiceState<G>>,
+    Path(id): Path<i64>,
+    Query(query): Query<GeofenceEventsQuery>,
+) -> Json<IssResponse> {
+    let limit = query.limit.clamp(1, 1000);
+    respond(svc.list_events(id, query.from, query.to, limit).await)
+}
@@ -1,20 +1,169 @@
 use std::sync::Arc;
 use axum::{
+    body::Body,
     extract::{Query, State},
-    response::Json,
+    http::header,
+    response::{IntoResponse, Json, Response},
 };
+use chrono::{DateTime, Duration, Utc};
+use futures_util::{future, stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
 
+use crate::domain::pagination::{Cursor, PageRequest};
+use crate::domain::track::{TrackFormat, TrackWriter};
+use crate::domain::{IssFetchLog, TrendBucket, TrendMetric};
+use crate::errors::ApiError;
+use crate::handlers::SatelliteId;
 use crate::repo::IssRepository;
+use crate::services::iss_service::GAP_COVERAGE_THRESHOLD;
 use crate::services::IssService;
 
 pub type IssServiceState<R> = Arc<IssService<R>>;
 
 #[derive(Debug, Deserialize)]
 pub struct TrendQuery {
+    /// Окно до `to` (или до текущего момента), если `from` не задан
     #[serde(default = "default_hours")]
     pub hours: i64,
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+    /// minute, 5min, hour, day
+    #[serde(default)]
+    pub bucket: Option<String>,
+    /// Показатели через запятую: latitude, longitude, altitude, velocity
+    #[serde(default)]
+    pub metrics: Option<String>,
+}
+
+impl TrendQuery {
+    fn bucket(&self) -> Result<TrendBucket, ApiError> {
+        parse_bucket(self.bucket.as_deref())
+    }
+
+    fn metrics(&self) -> Result<Vec<TrendMetric>, ApiError> {
+        let Some(raw) = self.metrics.as_deref() else {
+            return Ok(TrendMetric::ALL.to_vec());
+        };
+        raw.split(',')
+            .filter(|m| !m.trim().is_empty())
+            .map(|m| {
+                TrendMetric::parse(m)
+                    .ok_or_else(|| ApiError::validation(format!("Unknown metric: {}", m.trim())))
+            })
+            .collect()
+    }
+
+    fn window(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        resolve_window(self.hours, self.from, self.to)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoverageQuery {
+    #[serde(default = "default_hours")]
+    pub hours: i64,
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DwellQuery {
+    #[serde(default = "default_hours")]
+    pub hours: i64,
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// geojson, kml, gpx, csv
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default = "default_hours")]
+    pub hours: i64,
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RebuildQuery {
+    #[serde(default = "default_hours")]
+    pub hours: i64,
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Страница истории: от новых записей к старым
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    #[serde(default = "default_history_limit")]
+    pub limit: i64,
+    /// next_cursor или prev_cursor из предыдущего ответа
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub include_total: bool,
+}
+
+impl HistoryQuery {
+    pub fn page(&self) -> Result<PageRequest, ApiError> {
+        let cursor = match self.cursor.as_deref().filter(|c| !c.trim().is_empty()) {
+            Some(raw) => Some(Cursor::decode(raw, true)?),
+            None => None,
+        };
+        Ok(PageRequest {
+            limit: self.limit.clamp(1, 500),
+            cursor,
+            include_total: self.include_total,
+        })
+    }
+}
+
+fn default_history_limit() -> i64 {
+    50
+}
+
+fn parse_bucket(raw: Option<&str>) -> Result<TrendBucket, ApiError> {
+    match raw {
+        None => Ok(TrendBucket::Hour),
+        Some(raw) => TrendBucket::parse(raw)
+            .ok_or_else(|| ApiError::validation(format!("Unknown bucket: {}", raw))),
+    }
+}
+
+fn resolve_window(
+    hours: i64,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> (DateTime<Utc>, DateTime<Utc>) {
+    let to = to.unwrap_or_else(Utc::now);
+    let from = from.unwrap_or_else(|| to - Duration::hours(hours.clamp(1, 24 * 366)));
+    (from, to)
+}
+
+fn failure(e: ApiError) -> Json<IssResponse> {
+    Json(IssResponse {
+        ok: false,
+        data: None,
+        error: Some(json!({
+            "code": e.code,
+            "message": e.message,
+            "trace_id": e.trace_id,
+        })),
+    })
 }
 
 fn default_hours() -> i64 {
@@ -32,19 +181,12 @@ pub struct IssResponse {
 
 pub async fn get_latest<R: IssRepository>(
     State(svc): State<IssServiceState<R>>,
+    SatelliteId(norad_id): SatelliteId,
 ) -> Json<IssResponse> {
-    match svc.get_latest().await {
+    match svc.get_latest(norad_id).await {
         Ok(Some(log)) => Json(IssResponse {
             ok: true,
-            data: Some(json!({
-                "latitude": log.lat(),
-                "longitude": log.lon(),
-                "altitude": log.altitude(),
-                "velocity": log.velocity(),
-                "visibility": log.visibility(),
-                "timestamp": log.fetched_at.to_string(),
-                "payload": log.payload,
-            })),
+            data: Some(position_json(log)),
             error: None,
         }),
         Ok(None) => Json(IssResponse {
@@ -66,50 +208,204 @@ pub async fn get_latest<R: IssRepository>(
     }
 }
 
+pub async fn get_position_history<R: IssRepository>(
+    State(svc): State<IssServiceState<R>>,
+    SatelliteId(norad_id): SatelliteId,
+    Query(query): Query<HistoryQuery>,
+) -> Json<IssResponse> {
+    let page = match query.page() {
+        Ok(page) => page,
+        Err(e) => return failure(e),
+    };
+
+    match svc.history(norad_id, &page).await {
+        Ok((history, gaps)) => Json(IssResponse {
+            ok: true,
+            data: Some(json!({
+                "norad_id": norad_id,
+                "items": history.items.into_iter().map(position_json).collect::<Vec<_>>(),
+                "total": history.total,
+                "limit": page.limit,
+                "next_cursor": history.next_cursor,
+                "prev_cursor": history.prev_cursor,
+                "gaps": gaps,
+            })),
+            error: None,
+        }),
+        Err(e) => failure(e),
+    }
+}
+
+fn position_json(log: IssFetchLog) -> Value {
+    json!({
+        "id": log.id,
+        "norad_id": log.norad_id,
+        "latitude": log.lat(),
+        "longitude": log.lon(),
+        "altitude": log.altitude(),
+        "velocity": log.velocity(),
+        "visibility": log.visibility(),
+        "provider": log.provider,
+        "location": log.location,
+        "timestamp": log.fetched_at.to_string(),
+        "payload": log.payload,
+    })
+}
+
 pub async fn get_trend<R: IssRepository>(
     State(svc): State<IssServiceState<R>>,
+    SatelliteId(norad_id): SatelliteId,
     Query(query): Query<TrendQuery>,
 ) -> Json<IssResponse> {
-    let hours = query.hours.clamp(1, 168); // Max 7 days
+    let (bucket, metrics) = match (query.bucket(), query.metrics()) {
+        (Ok(bucket), Ok(metrics)) => (bucket, metrics),
+        (Err(e), _) | (_, Err(e)) => return failure(e),
+    };
+    let (from, to) = query.window();
 
-    match svc.get_trend(hours).await {
+    match svc.get_trend(norad_id, from, to, bucket).await {
         Ok(trends) => {
             let data: Vec<Value> = trends
                 .into_iter()
-                .map(|t| json!({
-                    "hour": t.hour,
-                    "avg_lat": t.avg_lat,
-                    "avg_lon": t.avg_lon,
-                    "avg_altitude": t.avg_altitude,
-                    "avg_velocity": t.avg_velocity,
-                    "cnt": t.cnt,
-                }))
+                .map(|t| {
+                    let mut point = Map::new();
+                    point.insert("hour".into(), json!(t.bucket_start.format("%Y-%m-%d %H:%M").to_string()));
+                    point.insert("bucket_start".into(), json!(t.bucket_start));
+                    point.insert("cnt".into(), json!(t.cnt));
+                    if let Some(coverage) = t.coverage {
+                        point.insert("coverage".into(), json!(coverage));
+                        point.insert("gap".into(), json!(coverage < GAP_COVERAGE_THRESHOLD));
+                    }
+                    for &metric in &metrics {
+                        let stats = t.metric(metric);
+                        point.insert(metric.legacy_avg_field().into(), json!(stats.avg));
+                        point.insert(metric.name().into(), json!(stats));
+                    }
+                    Value::Object(point)
+                })
                 .collect();
-            
+
             Json(IssResponse {
                 ok: true,
                 data: Some(json!(data)),
                 error: None,
             })
         }
-        Err(e) => {
-            Json(IssResponse {
-                ok: false,
-                data: None,
-                error: Some(json!({
-                    "code": e.code,
-                    "message": e.message,
-                    "trace_id": e.trace_id,
-                })),
-            })
+        Err(e) => failure(e),
+    }
+}
+
+pub async fn get_coverage<R: IssRepository>(
+    State(svc): State<IssServiceState<R>>,
+    SatelliteId(norad_id): SatelliteId,
+    Query(query): Query<CoverageQuery>,
+) -> Json<IssResponse> {
+    let bucket = match parse_bucket(query.bucket.as_deref()) {
+        Ok(bucket) => bucket,
+        Err(e) => return failure(e),
+    };
+    let (from, to) = resolve_window(query.hours, query.from, query.to);
+
+    match svc.get_coverage(norad_id, from, to, bucket).await {
+        Ok(report) => Json(IssResponse {
+            ok: true,
+            data: Some(json!(report)),
+            error: None,
+        }),
+        Err(e) => failure(e),
+    }
+}
+
+pub async fn get_dwell<R: IssRepository>(
+    State(svc): State<IssServiceState<R>>,
+    SatelliteId(norad_id): SatelliteId,
+    Query(query): Query<DwellQuery>,
+) -> Json<IssResponse> {
+    let (from, to) = resolve_window(query.hours, query.from, query.to);
+
+    match svc.get_dwell(norad_id, from, to).await {
+        Ok(report) => Json(IssResponse {
+            ok: true,
+            data: Some(json!(report)),
+            error: None,
+        }),
+        Err(e) => failure(e),
+    }
+}
+
+pub async fn export_track<R: IssRepository>(
+    State(svc): State<IssServiceState<R>>,
+    SatelliteId(norad_id): SatelliteId,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let raw = query.format.as_deref().unwrap_or("geojson");
+    let Some(format) = TrackFormat::parse(raw) else {
+        return failure(ApiError::validation(format!("Unknown format: {}", raw))).into_response();
+    };
+    let (from, to) = resolve_window(query.hours, query.from, query.to);
+    let (logs, gaps) = match svc.export_track(norad_id, from, to).await {
+        Ok(track) => track,
+        Err(e) => return failure(e).into_response(),
+    };
+
+    let writer = TrackWriter::new(format, format!("NORAD {} track", norad_id), &gaps);
+    let header = writer.header();
+    // Окончание KML зависит от открытого отрезка, поэтому writer идёт вместе с потоком
+    let rows = stream::unfold(Some((logs, writer)), |state| async move {
+        let (mut logs, mut writer) = state?;
+        loop {
+            match logs.next().await {
+                Some(Ok(log)) => {
+                    if let Some(row) = writer.row(&log) {
+                        return Some((Ok(row), Some((logs, writer))));
+                    }
+                }
+                Some(Err(e)) => return Some((Err(e), None)),
+                None => return Some((Ok(writer.footer()), None)),
+            }
         }
+    });
+    let body = stream::once(future::ready(Ok::<_, ApiError>(header))).chain(rows);
+
+    let filename = format!(
+        "track_{}_{}_{}.{}",
+        norad_id,
+        from.format("%Y%m%dT%H%M%S"),
+        to.format("%Y%m%dT%H%M%S"),
+        format.extension()
+    );
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+pub async fn rebuild_rollups<R: IssRepository>(
+    State(svc): State<IssServiceState<R>>,
+    SatelliteId(norad_id): SatelliteId,
+    Query(query): Query<RebuildQuery>,
+) -> Json<IssResponse> {
+    let (from, to) = resolve_window(query.hours, query.from, query.to);
+
+    match svc.rebuild_rollups(Some(norad_id), from, to).await {
+        Ok(report) => Json(IssResponse {
+            ok: true,
+            data: Some(json!({"norad_id": norad_id, "rebuilt": report})),
+            error: None,
+        }),
+        Err(e) => failure(e),
     }
 }
 
 pub async fn refresh_iss<R: IssRepository>(
     State(svc): State<IssServiceState<R>>,
+    SatelliteId(norad_id): SatelliteId,
 ) -> Json<IssResponse> {
-    match svc.fetch_and_store().await {
+    match svc.fetch_and_store(norad_id).await {
         Ok(_) => Json(IssResponse {
             ok: true,
             data: Some(json!({"status": "refreshed"})),
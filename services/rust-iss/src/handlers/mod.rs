@@ -1,7 +1,17 @@
+pub mod anomaly_handlers;
+pub mod geofence_handlers;
 pub mod iss_handlers;
+pub mod orbit_handlers;
 pub mod osdr_handlers;
+pub mod satellite_handlers;
 pub mod space_handlers;
+pub mod stream_handlers;
 
+pub use anomaly_handlers::*;
+pub use geofence_handlers::*;
 pub use iss_handlers::*;
+pub use orbit_handlers::*;
 pub use osdr_handlers::*;
+pub use satellite_handlers::*;
 pub use space_handlers::*;
+pub use stream_handlers::*;
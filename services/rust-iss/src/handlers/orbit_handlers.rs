@@ -0,0 +1,190 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::domain::Observer;
+use crate::errors::ApiError;
+use crate::handlers::SatelliteId;
+use crate::repo::{IssRepository, TleRepository};
+use crate::services::OrbitService;
+
+pub type OrbitServiceState<T, I> = Arc<OrbitService<T, I>>;
+
+#[derive(Debug, Deserialize)]
+pub struct PredictQuery {
+    #[serde(default)]
+    pub at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default = "default_step")]
+    pub step: i64,
+}
+
+fn default_step() -> i64 {
+    60
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PassesQuery {
+    pub lat: f64,
+    pub lon: f64,
+    /// Высота наблюдателя, м
+    #[serde(default)]
+    pub alt: f64,
+    #[serde(default = "default_days")]
+    pub days: i64,
+    #[serde(default)]
+    pub min_elevation: f64,
+    #[serde(default)]
+    pub visible_only: bool,
+}
+
+fn default_days() -> i64 {
+    3
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrbitResponse {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+impl OrbitResponse {
+    fn failure(e: ApiError) -> Json<Self> {
+        Json(Self {
+            ok: false,
+            data: None,
+            error: Some(json!({
+                "code": e.code,
+                "message": e.message,
+                "trace_id": e.trace_id,
+            })),
+        })
+    }
+}
+
+pub async fn predict_iss<T: TleRepository, I: IssRepository>(
+    State(svc): State<OrbitServiceState<T, I>>,
+    SatelliteId(norad_id): SatelliteId,
+    Query(query): Query<PredictQuery>,
+) -> Json<OrbitResponse> {
+    let result = match (query.from, query.to) {
+        (Some(from), Some(to)) => svc
+            .predict_range(norad_id, from, to, query.step)
+            .await
+            .map(|points| json!(points)),
+        (None, None) => svc
+            .predict_at(norad_id, query.at.unwrap_or_else(Utc::now))
+            .await
+            .map(|point| json!(point)),
+        _ => Err(ApiError::validation("Both from and to are required for a range")),
+    };
+
+    match result {
+        Ok(data) => Json(OrbitResponse {
+            ok: true,
+            data: Some(data),
+            error: None,
+        }),
+        Err(e) => OrbitResponse::failure(e),
+    }
+}
+
+pub async fn get_passes<T: TleRepository, I: IssRepository>(
+    State(svc): State<OrbitServiceState<T, I>>,
+    SatelliteId(norad_id): SatelliteId,
+    Query(query): Query<PassesQuery>,
+) -> Json<OrbitResponse> {
+    let days = query.days.clamp(1, 10);
+    let observer = Observer {
+        lat: query.lat,
+        lon: query.lon,
+        alt_m: query.alt,
+    };
+
+    match svc.passes(norad_id, observer, days, query.min_elevation).await {
+        Ok(passes) => {
+            let passes: Vec<_> = passes
+                .into_iter()
+                .filter(|p| !query.visible_only || p.visible)
+                .collect();
+
+            Json(OrbitResponse {
+                ok: true,
+                data: Some(json!({
+                    "observer": observer,
+                    "days": days,
+                    "passes": passes,
+                })),
+                error: None,
+            })
+        }
+        Err(e) => OrbitResponse::failure(e),
+    }
+}
+
+pub async fn get_tle_status<T: TleRepository, I: IssRepository>(
+    State(svc): State<OrbitServiceState<T, I>>,
+    SatelliteId(norad_id): SatelliteId,
+) -> Json<OrbitResponse> {
+    let tle = match svc.get_tle(norad_id).await {
+        Ok(tle) => tle,
+        Err(e) => return OrbitResponse::failure(e),
+    };
+    let check = match svc.get_last_check(norad_id).await {
+        Ok(check) => check,
+        Err(e) => return OrbitResponse::failure(e),
+    };
+
+    Json(OrbitResponse {
+        ok: true,
+        data: Some(json!({
+            "tle": tle.as_ref().map(|t| json!({
+                "norad_id": t.norad_id,
+                "name": t.object_name,
+                "line1": t.line1,
+                "line2": t.line2,
+                "epoch": t.epoch.to_string(),
+                "age_hours": (Utc::now() - t.epoch).num_minutes() as f64 / 60.0,
+                "source": t.source,
+                "fetched_at": t.fetched_at.to_string(),
+            })),
+            "last_check": check,
+            "stale": check.as_ref().map(|c| c.stale),
+        })),
+        error: None,
+    })
+}
+
+pub async fn refresh_tle<T: TleRepository, I: IssRepository>(
+    State(svc): State<OrbitServiceState<T, I>>,
+    SatelliteId(norad_id): SatelliteId,
+) -> Json<OrbitResponse> {
+    let stored = match svc.refresh_tle().await {
+        Ok(n) => n,
+        Err(e) => return OrbitResponse::failure(e),
+    };
+
+    match svc.check_accuracy(norad_id).await {
+        Ok(check) => Json(OrbitResponse {
+            ok: true,
+            data: Some(json!({
+                "status": "refreshed",
+                "count": stored,
+                "check": check,
+            })),
+            error: None,
+        }),
+        Err(e) => OrbitResponse::failure(e),
+    }
+}
@@ -1,24 +1,142 @@
 use std::sync::Arc;
 use axum::{
-    extract::{Query, State},
-    response::Json,
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Json, Response},
 };
+use chrono::{DateTime, Utc};
+use futures_util::{future, stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+use crate::domain::osdr::{OsdrFilter, OsdrSort};
+use crate::domain::osdr_export::{OsdrExportFormat, OsdrExportWriter};
+use crate::domain::osdr_mapping::OsdrMapping;
+use crate::domain::pagination::{Cursor, PageRequest};
+use crate::domain::OsdrSyncStatus;
+use crate::errors::ApiError;
 use crate::repo::OsdrRepository;
 use crate::services::OsdrService;
 
 pub type OsdrServiceState<R> = Arc<OsdrService<R>>;
 
+/// Фильтры списка; organism, study_type и status — через запятую
 #[derive(Debug, Deserialize)]
 pub struct ListQuery {
     #[serde(default = "default_limit")]
     pub limit: i32,
     #[serde(default)]
     pub offset: i32,
+    /// next_cursor или prev_cursor из предыдущего ответа; с offset не сочетается
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// Считать total — отдельный COUNT по всему фильтру
+    #[serde(default)]
+    pub include_total: bool,
+    /// Полнотекстовый поиск: `"фраза"`, `префикс*`, `-исключить`, `or`
     #[serde(default)]
     pub search: Option<String>,
+    #[serde(default)]
+    pub organism: Option<String>,
+    #[serde(default)]
+    pub study_type: Option<String>,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub updated_from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub updated_to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub inserted_from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub inserted_to: Option<DateTime<Utc>>,
+    /// Показывать и наборы, пропавшие из выдачи OSDR
+    #[serde(default)]
+    pub include_withdrawn: bool,
+    /// relevance, updated_at, inserted_at, title, dataset_id; `-` впереди — по убыванию.
+    /// С `search` по умолчанию relevance
+    #[serde(default)]
+    pub sort: Option<String>,
+    /// asc или desc
+    #[serde(default)]
+    pub order: Option<String>,
+}
+
+impl ListQuery {
+    fn filter(&self) -> OsdrFilter {
+        OsdrFilter {
+            search: self.search.clone(),
+            organisms: split_values(&self.organism),
+            study_types: split_values(&self.study_type),
+            statuses: split_values(&self.status),
+            updated_from: self.updated_from,
+            updated_to: self.updated_to,
+            inserted_from: self.inserted_from,
+            inserted_to: self.inserted_to,
+            include_withdrawn: self.include_withdrawn,
+        }
+    }
+
+    fn sort(&self) -> Result<OsdrSort, ApiError> {
+        let searching = self.search.as_deref().is_some_and(|s| !s.trim().is_empty());
+        match (&self.sort, &self.order) {
+            (None, None) if searching => Ok(OsdrSort::relevance()),
+            (None, None) => Ok(OsdrSort::default()),
+            (sort, order) => OsdrSort::parse(sort.as_deref().unwrap_or("updated_at"), order.as_deref())
+                .ok_or_else(|| ApiError::validation("Unknown sort; use relevance, updated_at, inserted_at, title or dataset_id with order asc|desc")),
+        }
+    }
+    fn page(&self, sort: OsdrSort) -> Result<PageRequest, ApiError> {
+        let cursor = match self.cursor.as_deref().filter(|c| !c.trim().is_empty()) {
+            Some(_) if self.offset > 0 => {
+                return Err(ApiError::validation("Use either cursor or offset, not both"))
+            }
+            Some(raw) => Some(Cursor::decode(raw, sort.descending)?),
+            None => None,
+        };
+        Ok(PageRequest {
+            limit: i64::from(self.limit.clamp(1, 100)),
+            cursor,
+            include_total: self.include_total,
+        })
+    }
+}
+
+fn split_values(raw: &Option<String>) -> Vec<String> {
+    raw.as_deref()
+        .map(|s| {
+            s.split(',')
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Формат выгрузки; фильтры и сортировка — те же, что у списка
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// csv, xlsx или ndjson
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SyncQuery {
+    /// Игнорировать водяной знак и пройти весь каталог
+    #[serde(default)]
+    pub full: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportsQuery {
+    #[serde(default = "default_reports_limit")]
+    pub limit: i64,
+}
+
+fn default_reports_limit() -> i64 {
+    20
 }
 
 fn default_limit() -> i32 {
@@ -38,14 +156,23 @@ pub async fn list_datasets<R: OsdrRepository>(
     State(svc): State<OsdrServiceState<R>>,
     Query(query): Query<ListQuery>,
 ) -> Json<OsdrResponse> {
-    let limit = query.limit.clamp(1, 100);
     let offset = query.offset.max(0);
+    let filter = query.filter();
+    let (sort, page) = match query.sort().and_then(|sort| Ok((sort, query.page(sort)?))) {
+        Ok(parsed) => parsed,
+        Err(e) => return failure(e),
+    };
+
+    let result = async {
+        let list = svc.list(&filter, sort, &page, i64::from(offset)).await?;
+        let facets = svc.facets(&filter).await?;
+        Ok::<_, ApiError>((list, facets))
+    };
 
-    match svc.list(limit, offset, query.search.clone()).await {
-        Ok(items) => {
-            let total = svc.count().await.unwrap_or(0);
-            
-            let data: Vec<Value> = items
+    match result.await {
+        Ok((list, facets)) => {
+            let data: Vec<Value> = list
+                .items
                 .into_iter()
                 .map(|item| json!({
                     "id": item.id,
@@ -53,7 +180,10 @@ pub async fn list_datasets<R: OsdrRepository>(
                     "title": item.title,
                     "organism": item.organism,
                     "study_type": item.study_type,
+                    "status": item.status,
                     "updated_at": item.updated_at.map(|t| t.to_string()),
+                    "withdrawn_at": item.withdrawn_at,
+                    "search": item.search,
                 }))
                 .collect();
 
@@ -61,13 +191,90 @@ pub async fn list_datasets<R: OsdrRepository>(
                 ok: true,
                 data: Some(json!({
                     "items": data,
-                    "total": total,
-                    "limit": limit,
+                    "total": list.total,
+                    "limit": page.limit,
                     "offset": offset,
+                    "next_cursor": list.next_cursor,
+                    "prev_cursor": list.prev_cursor,
+                    "facets": facets,
                 })),
                 error: None,
             })
         }
+        Err(e) => failure(e),
+    }
+}
+
+pub async fn export_datasets<R: OsdrRepository>(
+    State(svc): State<OsdrServiceState<R>>,
+    Query(list): Query<ListQuery>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let raw = query.format.as_deref().unwrap_or("csv");
+    let Some(format) = OsdrExportFormat::parse(raw) else {
+        return failure(ApiError::validation(format!("Unknown format: {}", raw))).into_response();
+    };
+    let sort = match list.sort() {
+        Ok(sort) => sort,
+        Err(e) => return failure(e).into_response(),
+    };
+    let items = svc.export(&list.filter(), sort);
+
+    let mut writer = OsdrExportWriter::new(format);
+    let header = match writer.header() {
+        Ok(header) => header,
+        Err(e) => return failure(e).into_response(),
+    };
+    // Окончание XLSX зависит от записанных строк, поэтому writer идёт вместе с потоком;
+    // после ошибки (в том числе лимита XLSX) поток обрывается
+    let rows = stream::unfold(Some((items, writer)), |state| async move {
+        let (mut items, mut writer) = state?;
+        match items.next().await {
+            Some(Ok(item)) => match writer.row(&item) {
+                Ok(bytes) => Some((Ok(bytes), Some((items, writer)))),
+                Err(e) => Some((Err(e), None)),
+            },
+            Some(Err(e)) => Some((Err(e), None)),
+            None => Some((writer.footer(), None)),
+        }
+    });
+    let body = stream::once(future::ready(Ok::<_, ApiError>(header))).chain(rows);
+
+    let filename = format!("osdr_export_{}.{}", Utc::now().format("%Y%m%d_%H%M%S"), format.extension());
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+pub async fn sync_osdr<R: OsdrRepository>(
+    State(svc): State<OsdrServiceState<R>>,
+    Query(query): Query<SyncQuery>,
+) -> Json<OsdrResponse> {
+    match svc.sync_datasets(query.full).await {
+        // Прерванный прогон — ошибка для вызывающего, но отчёт отдаём и в этом случае
+        Ok(report) => {
+            let failed = report.status == OsdrSyncStatus::Failed;
+            let error = failed.then(|| {
+                json!({
+                    "code": "OSDR_SYNC_FAILED",
+                    "message": report.error.clone().unwrap_or_default(),
+                })
+            });
+            Json(OsdrResponse {
+                ok: !failed,
+                data: Some(json!({
+                    "status": report.status.as_str(),
+                    "count": report.written(),
+                    "report": report,
+                })),
+                error,
+            })
+        }
         Err(e) => {
             Json(OsdrResponse {
                 ok: false,
@@ -82,28 +289,165 @@ pub async fn list_datasets<R: OsdrRepository>(
     }
 }
 
-pub async fn sync_osdr<R: OsdrRepository>(
+pub async fn list_sync_reports<R: OsdrRepository>(
     State(svc): State<OsdrServiceState<R>>,
+    Query(query): Query<ReportsQuery>,
 ) -> Json<OsdrResponse> {
-    match svc.sync_datasets().await {
-        Ok(count) => Json(OsdrResponse {
+    match svc.sync_reports(query.limit.clamp(1, 200)).await {
+        Ok(reports) => Json(OsdrResponse {
             ok: true,
-            data: Some(json!({
-                "status": "synced",
-                "count": count,
-            })),
+            data: Some(json!({ "reports": reports })),
             error: None,
         }),
-        Err(e) => {
+        Err(e) => failure(e),
+    }
+}
+
+pub async fn get_mapping<R: OsdrRepository>(
+    State(svc): State<OsdrServiceState<R>>,
+) -> Json<OsdrResponse> {
+    Json(OsdrResponse {
+        ok: true,
+        data: Some(json!(svc.mapping())),
+        error: None,
+    })
+}
+
+/// Тело — образец записи или страницы upstream, либо
+/// {"payload": образец, "mapping": правила} для проверки новых правил
+pub async fn dry_run_mapping<R: OsdrRepository>(
+    State(svc): State<OsdrServiceState<R>>,
+    Json(body): Json<Value>,
+) -> Json<OsdrResponse> {
+    let (sample, mapping) = match body.get("payload") {
+        Some(payload) => {
+            let mapping = match body.get("mapping") {
+                Some(rules) => match OsdrMapping::from_json(&rules.to_string()) {
+                    Ok(mapping) => Some(mapping),
+                    Err(e) => return failure(e),
+                },
+                None => None,
+            };
+            (payload, mapping)
+        }
+        None => (&body, None),
+    };
+
+    Json(OsdrResponse {
+        ok: true,
+        data: Some(json!({ "items": svc.dry_run(sample, mapping.as_ref()) })),
+        error: None,
+    })
+}
+
+pub async fn get_dataset<R: OsdrRepository>(
+    State(svc): State<OsdrServiceState<R>>,
+    Path(dataset_id): Path<String>,
+) -> Json<OsdrResponse> {
+    match svc.get(&dataset_id).await {
+        Ok((item, related)) => {
+            let related: Vec<Value> = related
+                .into_iter()
+                .map(|r| json!({
+                    "dataset_id": r.dataset_id,
+                    "title": r.title,
+                    "relation": r.relation,
+                    "href": format!("/api/osdr/{}", r.dataset_id),
+                }))
+                .collect();
+
             Json(OsdrResponse {
-                ok: false,
-                data: None,
-                error: Some(json!({
-                    "code": e.code,
-                    "message": e.message,
-                    "trace_id": e.trace_id,
+                ok: true,
+                data: Some(json!({
+                    "id": item.id,
+                    "dataset_id": item.dataset_id,
+                    "title": item.title,
+                    "organism": item.organism,
+                    "study_type": item.study_type,
+                    "status": item.status,
+                    "updated_at": item.updated_at,
+                    "inserted_at": item.inserted_at,
+                    "last_seen_at": item.last_seen_at,
+                    "withdrawn_at": item.withdrawn_at,
+                    "withdrawn_reason": item.withdrawn_reason,
+                    "raw": item.raw,
+                    "links": {
+                        "self": format!("/api/osdr/{}", dataset_id),
+                        "history": format!("/api/osdr/{}/history", dataset_id),
+                        "assays": format!("/api/osdr/{}/assays", dataset_id),
+                        "files": format!("/api/osdr/{}/files", dataset_id),
+                        "related": related,
+                    },
                 })),
+                error: None,
             })
         }
+        Err(e) => failure(e),
     }
 }
+
+pub async fn get_history<R: OsdrRepository>(
+    State(svc): State<OsdrServiceState<R>>,
+    Path(dataset_id): Path<String>,
+) -> Json<OsdrResponse> {
+    match svc.history(&dataset_id).await {
+        Ok(versions) => Json(OsdrResponse {
+            ok: true,
+            data: Some(json!({
+                "dataset_id": dataset_id,
+                "versions": versions,
+            })),
+            error: None,
+        }),
+        Err(e) => failure(e),
+    }
+}
+
+pub async fn get_assays<R: OsdrRepository>(
+    State(svc): State<OsdrServiceState<R>>,
+    Path(dataset_id): Path<String>,
+) -> Json<OsdrResponse> {
+    match svc.assays(&dataset_id).await {
+        Ok(assays) => Json(OsdrResponse {
+            ok: true,
+            data: Some(json!({
+                "dataset_id": dataset_id,
+                "total": assays.len(),
+                "assays": assays,
+            })),
+            error: None,
+        }),
+        Err(e) => failure(e),
+    }
+}
+
+pub async fn get_files<R: OsdrRepository>(
+    State(svc): State<OsdrServiceState<R>>,
+    Path(dataset_id): Path<String>,
+) -> Json<OsdrResponse> {
+    match svc.files(&dataset_id).await {
+        Ok(files) => Json(OsdrResponse {
+            ok: true,
+            data: Some(json!({
+                "dataset_id": dataset_id,
+                "total": files.len(),
+                "total_bytes": files.iter().filter_map(|f| f.size_bytes).sum::<i64>(),
+                "files": files,
+            })),
+            error: None,
+        }),
+        Err(e) => failure(e),
+    }
+}
+
+fn failure(e: ApiError) -> Json<OsdrResponse> {
+    Json(OsdrResponse {
+        ok: false,
+        data: None,
+        error: Some(json!({
+            "code": e.code,
+            "message": e.message,
+            "trace_id": e.trace_id,
+        })),
+    })
+}
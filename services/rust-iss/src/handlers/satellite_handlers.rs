@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, State},
+    http::request::Parts,
+    response::Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::domain::ISS_NORAD_ID;
+use crate::errors::ApiError;
+use crate::handlers::{IssResponse, IssServiceState};
+use crate::repo::IssRepository;
+
+/// NORAD ID из пути `/api/satellites/:norad_id/...`; для `/api/iss/...` — МКС
+#[derive(Debug, Clone, Copy)]
+pub struct SatelliteId(pub i64);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for SatelliteId {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let params = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+            .await
+            .map(|Path(p)| p)
+            .unwrap_or_default();
+
+        match params.get("norad_id") {
+            Some(raw) => raw
+                .parse()
+                .map(SatelliteId)
+                .map_err(|_| ApiError::validation(format!("Invalid NORAD id: {}", raw))),
+            None => Ok(SatelliteId(ISS_NORAD_ID)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SatelliteBody {
+    pub norad_id: i64,
+    pub name: String,
+    pub source_url: String,
+    #[serde(default = "default_interval")]
+    pub interval_seconds: i32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_interval() -> i32 {
+    120
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn failure(e: ApiError) -> Json<IssResponse> {
+    Json(IssResponse {
+        ok: false,
+        data: None,
+        error: Some(json!({
+            "code": e.code,
+            "message": e.message,
+            "trace_id": e.trace_id,
+        })),
+    })
+}
+
+pub async fn list_satellites<R: IssRepository>(
+    State(svc): State<IssServiceState<R>>,
+) -> Json<IssResponse> {
+    match svc.list_satellites().await {
+        Ok(satellites) => Json(IssResponse {
+            ok: true,
+            data: Some(json!(satellites)),
+            error: None,
+        }),
+        Err(e) => failure(e),
+    }
+}
+
+pub async fn get_satellite<R: IssRepository>(
+    State(svc): State<IssServiceState<R>>,
+    SatelliteId(norad_id): SatelliteId,
+) -> Json<IssResponse> {
+    match svc.get_satellite(norad_id).await {
+        Ok(sat) => Json(IssResponse {
+            ok: true,
+            data: Some(json!(sat)),
+            error: None,
+        }),
+        Err(e) => failure(e),
+    }
+}
+
+pub async fn register_satellite<R: IssRepository>(
+    State(svc): State<IssServiceState<R>>,
+    Json(body): Json<SatelliteBody>,
+) -> Json<IssResponse> {
+    match svc
+        .register_satellite(
+            body.norad_id,
+            body.name.trim(),
+            body.source_url.trim(),
+            body.interval_seconds,
+            body.enabled,
+        )
+        .await
+    {
+        Ok(sat) => Json(IssResponse {
+            ok: true,
+            data: Some(json!(sat)),
+            error: None,
+        }),
+        Err(e) => failure(e),
+    }
+}
+
+pub async fn delete_satellite<R: IssRepository>(
+    State(svc): State<IssServiceState<R>>,
+    SatelliteId(norad_id): SatelliteId,
+) -> Json<IssResponse> {
+    match svc.delete_satellite(norad_id).await {
+        Ok(()) => Json(IssResponse {
+            ok: true,
+            data: Some(json!({"status": "deleted", "norad_id": norad_id})),
+            error: None,
+        }),
+        Err(e) => failure(e),
+    }
+}
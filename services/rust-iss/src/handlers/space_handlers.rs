@@ -6,6 +6,8 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+use crate::errors::ApiError;
+use crate::handlers::HistoryQuery;
 use crate::repo::CacheRepository;
 use crate::services::SpaceService;
 
@@ -59,6 +61,54 @@ pub async fn get_cache<C: CacheRepository>(
     }
 }
 
+pub async fn get_cache_history<C: CacheRepository>(
+    State(svc): State<SpaceServiceState<C>>,
+    Path(source): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Json<SpaceResponse> {
+    let result = async {
+        let page = query.page()?;
+        let history = svc.history(&source, &page).await?;
+        Ok::<_, ApiError>((page, history))
+    };
+
+    match result.await {
+        Ok((page, history)) => {
+            let items: Vec<Value> = history
+                .items
+                .into_iter()
+                .map(|cache| json!({
+                    "id": cache.id,
+                    "fetched_at": cache.fetched_at.to_string(),
+                    "payload": cache.payload,
+                }))
+                .collect();
+
+            Json(SpaceResponse {
+                ok: true,
+                data: Some(json!({
+                    "source": source,
+                    "items": items,
+                    "total": history.total,
+                    "limit": page.limit,
+                    "next_cursor": history.next_cursor,
+                    "prev_cursor": history.prev_cursor,
+                })),
+                error: None,
+            })
+        }
+        Err(e) => Json(SpaceResponse {
+            ok: false,
+            data: None,
+            error: Some(json!({
+                "code": e.code,
+                "message": e.message,
+                "trace_id": e.trace_id,
+            })),
+        }),
+    }
+}
+
 pub async fn refresh_space<C: CacheRepository>(
     State(svc): State<SpaceServiceState<C>>,
     Query(query): Query<RefreshQuery>,
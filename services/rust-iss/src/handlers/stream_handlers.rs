@@ -0,0 +1,211 @@
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::HeaderMap,
+    response::{
+        sse::{self, KeepAlive, Sse},
+        Response,
+    },
+};
+use futures_util::{stream::{self, SplitSink}, SinkExt, Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, warn};
+
+use crate::domain::IssFetchLog;
+use crate::handlers::{IssServiceState, SatelliteId};
+use crate::repo::IssRepository;
+use crate::services::{Event, EventBus};
+
+pub type EventBusState = Arc<EventBus>;
+
+/// Интервал ping-сообщений клиенту
+const WS_PING_INTERVAL: Duration = Duration::from_secs(20);
+/// Клиент, не ответивший pong за это время, считается отвалившимся
+const WS_PONG_TIMEOUT: Duration = Duration::from_secs(60);
+/// Медленный клиент, не принявший сообщение за это время, отключается
+const WS_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+const WS_MAX_REPLAY: i64 = 500;
+/// Интервал комментариев-keepalive в SSE-потоке
+const SSE_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Deserialize)]
+pub struct WsQuery {
+    /// Сколько последних позиций отправить сразу после подключения
+    #[serde(default)]
+    pub replay: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventsQuery {
+    /// Список источников через запятую: iss, apod, neo, donki, spacex, osdr
+    #[serde(default)]
+    pub sources: Option<String>,
+    /// Альтернатива заголовку Last-Event-ID для клиентов, не умеющих его слать
+    #[serde(default)]
+    pub last_event_id: Option<u64>,
+}
+
+pub async fn iss_ws<R: IssRepository + 'static>(
+    ws: WebSocketUpgrade,
+    State(svc): State<IssServiceState<R>>,
+    SatelliteId(norad_id): SatelliteId,
+    Query(query): Query<WsQuery>,
+) -> Response {
+    let replay = query.replay.clamp(0, WS_MAX_REPLAY);
+    ws.on_upgrade(move |socket| stream_positions(socket, svc, norad_id, replay))
+}
+
+async fn stream_positions<R: IssRepository>(
+    socket: WebSocket,
+    svc: IssServiceState<R>,
+    norad_id: i64,
+    replay: i64,
+) {
+    // Подписываемся до чтения истории, чтобы не потерять позиции между ними
+    let mut rx = svc.subscribe();
+    let (mut sender, mut receiver) = socket.split();
+
+    let mut last_sent_id = 0;
+    if replay > 0 {
+        match svc.get_recent(norad_id, replay).await {
+            Ok(logs) => {
+                for log in &logs {
+                    if send_json(&mut sender, position_message("replay", log)).await.is_err() {
+                        return;
+                    }
+                    last_sent_id = last_sent_id.max(log.id);
+                }
+            }
+            Err(e) => warn!("WS replay for {} failed: {}", norad_id, e),
+        }
+    }
+
+    let mut heartbeat = tokio::time::interval(WS_PING_INTERVAL);
+    let mut last_pong = Instant::now();
+
+    loop {
+        tokio::select! {
+            msg = rx.recv() => match msg {
+                Ok(log) if log.norad_id == norad_id && log.id > last_sent_id => {
+                    if send_json(&mut sender, position_message("position", &log)).await.is_err() {
+                        break;
+                    }
+                    last_sent_id = log.id;
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    // Клиент не успевает — старые позиции вытеснены из канала
+                    let notice = json!({"type": "lagged", "skipped": skipped});
+                    if send_json(&mut sender, notice).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = heartbeat.tick() => {
+                if last_pong.elapsed() > WS_PONG_TIMEOUT {
+                    debug!("WS client for {} timed out", norad_id);
+                    break;
+                }
+                if send(&mut sender, Message::Ping(Vec::new())).await.is_err() {
+                    break;
+                }
+            },
+            incoming = receiver.next() => match incoming {
+                Some(Ok(Message::Pong(_))) => last_pong = Instant::now(),
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+fn position_message(kind: &str, log: &IssFetchLog) -> Value {
+    json!({"type": kind, "data": log.position_json()})
+}
+
+async fn send_json(sender: &mut SplitSink<WebSocket, Message>, value: Value) -> Result<(), ()> {
+    send(sender, Message::Text(value.to_string())).await
+}
+
+async fn send(sender: &mut SplitSink<WebSocket, Message>, msg: Message) -> Result<(), ()> {
+    match tokio::time::timeout(WS_SEND_TIMEOUT, sender.send(msg)).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(_)) => Err(()),
+        Err(_) => {
+            warn!("WS client too slow, disconnecting");
+            Err(())
+        }
+    }
+}
+
+pub async fn events_stream(
+    State(bus): State<EventBusState>,
+    headers: HeaderMap,
+    Query(query): Query<EventsQuery>,
+) -> Sse<impl Stream<Item = Result<sse::Event, Infallible>>> {
+    let sources: Option<HashSet<String>> = query.sources.as_deref().map(|raw| {
+        raw.split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect()
+    });
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+        .or(query.last_event_id);
+
+    let (missed, rx) = bus.subscribe_since(last_id);
+    let last_sent = missed.last().map(|e| e.id).or(last_id).unwrap_or(0);
+
+    let live = stream::unfold(rx, |mut rx| async move {
+        match rx.recv().await {
+            Ok(event) => Some((Ok(event), rx)),
+            // Клиент отстал: сообщаем, сколько событий потеряно, и продолжаем
+            Err(RecvError::Lagged(skipped)) => Some((Err(skipped), rx)),
+            Err(RecvError::Closed) => None,
+        }
+    })
+    // События, уже отданные из истории, в живом потоке пропускаем
+    .filter(move |item| {
+        let fresh = !matches!(item, Ok(event) if event.id <= last_sent);
+        async move { fresh }
+    });
+
+    let stream = stream::iter(missed.into_iter().map(Ok))
+        .chain(live)
+        .filter_map(move |item| {
+            let out = match item {
+                Ok(event) if !wants(&sources, &event) => None,
+                Ok(event) => Some(sse_event(&event)),
+                Err(skipped) => Some(
+                    sse::Event::default()
+                        .event("stream.lagged")
+                        .data(json!({"skipped": skipped}).to_string()),
+                ),
+            };
+            async move { out.map(Ok) }
+        });
+
+    Sse::new(stream).keep_alive(KeepAlive::new().interval(SSE_KEEPALIVE_INTERVAL))
+}
+
+fn wants(sources: &Option<HashSet<String>>, event: &Event) -> bool {
+    sources.as_ref().is_none_or(|s| s.contains(event.source()))
+}
+
+fn sse_event(event: &Event) -> sse::Event {
+    sse::Event::default()
+        .id(event.id.to_string())
+        .event(&event.kind)
+        .data(json!({"at": event.at.to_rfc3339(), "data": event.data}).to_string())
+}
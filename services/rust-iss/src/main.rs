@@ -22,14 +22,27 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use sqlx::postgres::PgPoolOptions;
-use tracing::{error, info};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{error, info, warn};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
+use crate::clients::build_providers;
 use crate::config::AppConfig;
-use crate::repo::{PgIssRepo, PgOsdrRepo, PgCacheRepo};
-use crate::services::{IssService, OsdrService, SpaceService};
+use crate::domain::anomaly::AnomalyThresholds;
+use crate::domain::geocode::ReverseGeocoder;
+use crate::domain::osdr_enrichment::OsdrEnrichConfig;
+use crate::domain::osdr_export::OsdrExportFormat;
+use crate::domain::osdr_mapping::{OsdrMapping, DEFAULT_MAPPING};
+use crate::domain::{OsdrSyncStatus, RetentionPolicy, ISS_NORAD_ID};
+use crate::repo::{PgAnomalyRepo, PgGeofenceRepo, PgIssRepo, PgOsdrRepo, PgCacheRepo, PgTleRepo};
+use crate::services::{AnomalyService, EventBus, GeofenceService, IssService, OrbitService, OsdrService, SpaceService};
 use crate::routes::create_router;
 
+/// Как часто проверять, не пора ли опросить очередной спутник
+const SATELLITE_POLL_TICK_SECONDS: u64 = 10;
+/// Размер пачки при геокодировании ранее сохранённых позиций
+const GEOCODE_BACKFILL_BATCH: i64 = 1000;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Инициализация логгера
@@ -57,34 +70,130 @@ async fn main() -> anyhow::Result<()> {
     let iss_repo = Arc::new(PgIssRepo::new(pool.clone()));
     let osdr_repo = Arc::new(PgOsdrRepo::new(pool.clone()));
     let cache_repo = Arc::new(PgCacheRepo::new(pool.clone()));
+    let tle_repo = Arc::new(PgTleRepo::new(pool.clone()));
+    let anomaly_repo = Arc::new(PgAnomalyRepo::new(pool.clone()));
+    let geofence_repo = Arc::new(PgGeofenceRepo::new(pool.clone()));
 
     // Инициализация сервисов
+    let event_bus = Arc::new(EventBus::new());
+    let geocoder = Arc::new(ReverseGeocoder::embedded()?);
+    let providers = build_providers(
+        &config.iss_providers,
+        &config.open_notify_url,
+        tle_repo.clone(),
+    )?;
+    info!(
+        "Position providers: {}",
+        providers.iter().map(|p| p.name()).collect::<Vec<_>>().join(" -> ")
+    );
     let iss_service = Arc::new(IssService::new(
         iss_repo.clone(),
-        config.where_iss_url.clone(),
+        providers,
+        config.iss_stale_seconds,
+        RetentionPolicy {
+            raw_days: config.raw_retention_days,
+            minute_days: config.rollup_minute_retention_days,
+            hour_days: config.rollup_hour_retention_days,
+            day_days: config.rollup_day_retention_days,
+        },
+        geocoder,
+        event_bus.clone(),
+    ));
+    iss_service
+        .ensure_satellite(
+            ISS_NORAD_ID,
+            "ISS (ZARYA)",
+            &config.where_iss_url,
+            config.iss_every_seconds,
+        )
+        .await?;
+    let orbit_service = Arc::new(OrbitService::new(
+        tle_repo.clone(),
+        iss_repo.clone(),
+        config.tle_url.clone(),
+        config.tle_file.clone(),
+        config.tle_stale_km,
     ));
+    let anomaly_service = Arc::new(AnomalyService::new(
+        anomaly_repo.clone(),
+        iss_repo.clone(),
+        AnomalyThresholds {
+            jump_factor: config.anomaly_jump_factor,
+            velocity_z: config.anomaly_velocity_z,
+            reboost_km: config.anomaly_reboost_km,
+            decay_km_per_day: config.anomaly_decay_km_per_day,
+        },
+        event_bus.clone(),
+    ));
+    let geofence_service = Arc::new(GeofenceService::new(
+        geofence_repo.clone(),
+        event_bus.clone(),
+    ));
+    let osdr_mapping = match &config.osdr_mapping_file {
+        Some(path) => {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Cannot read OSDR mapping {}: {}", path, e))?;
+            info!("OSDR mapping loaded from {}", path);
+            OsdrMapping::from_json(&text)?
+        }
+        None => OsdrMapping::from_json(DEFAULT_MAPPING)?,
+    };
     let osdr_service = Arc::new(OsdrService::new(
         osdr_repo.clone(),
         config.nasa_api_url.clone(),
+        config.osdr_since_param.clone(),
+        config.osdr_max_pages,
+        config.osdr_withdraw_after,
+        config.osdr_full_sync_every_seconds,
+        Arc::new(osdr_mapping),
+        config.osdr_enrich.then(|| OsdrEnrichConfig {
+            detail_url: config.osdr_detail_url.clone(),
+            assays_url: config.osdr_assays_url.clone(),
+            files_url: config.osdr_files_url.clone(),
+            concurrency: config.osdr_enrich_concurrency,
+            batch: config.osdr_enrich_batch,
+        }),
+        event_bus.clone(),
     ));
     let space_service = Arc::new(SpaceService::new(
         cache_repo.clone(),
         config.nasa_api_key.clone(),
+        event_bus.clone(),
     ));
 
+    // Форматы плановой выгрузки OSDR проверяются при старте, как и правила маппинга
+    let osdr_export_formats = config
+        .osdr_export_formats
+        .iter()
+        .map(|f| OsdrExportFormat::parse(f).ok_or_else(|| anyhow::anyhow!("Unknown OSDR export format: {}", f)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // Разовые команды: `rust_iss osdr-dedup [--apply]`
+    if let Some(command) = std::env::args().nth(1) {
+        return run_command(&command, osdr_service).await;
+    }
+
     // Фоновые задачи
     spawn_background_tasks(
         iss_service.clone(),
+        orbit_service.clone(),
+        anomaly_service.clone(),
+        geofence_service.clone(),
         osdr_service.clone(),
         space_service.clone(),
         &config,
+        osdr_export_formats,
     );
 
     // Создание роутера
     let app = create_router(
         iss_service,
+        orbit_service,
+        anomaly_service,
+        geofence_service,
         osdr_service,
         space_service,
+        event_bus,
     );
 
     // Запуск сервера
@@ -97,38 +206,185 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn spawn_background_tasks<I, O, C>(
+async fn run_command<O>(command: &str, osdr_service: Arc<OsdrService<O>>) -> anyhow::Result<()>
+where
+    O: crate::repo::OsdrRepository + 'static,
+{
+    match command {
+        "osdr-dedup" => {
+            let apply = std::env::args().any(|a| a == "--apply");
+            let report = osdr_service.dedup(apply).await?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            if !apply {
+                info!("Dry run; pass --apply to merge {} groups", report.groups.len());
+            }
+            Ok(())
+        }
+        other => anyhow::bail!("Unknown command: {}", other),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_background_tasks<I, O, C, T, A, G>(
     iss_service: Arc<IssService<I>>,
+    orbit_service: Arc<OrbitService<T, I>>,
+    anomaly_service: Arc<AnomalyService<A, I>>,
+    geofence_service: Arc<GeofenceService<G>>,
     osdr_service: Arc<OsdrService<O>>,
     space_service: Arc<SpaceService<C>>,
     config: &AppConfig,
+    osdr_export_formats: Vec<OsdrExportFormat>,
 ) where
     I: crate::repo::IssRepository + 'static,
     O: crate::repo::OsdrRepository + 'static,
     C: crate::repo::CacheRepository + 'static,
+    T: crate::repo::TleRepository + 'static,
+    A: crate::repo::AnomalyRepository + 'static,
+    G: crate::repo::GeofenceRepository + 'static,
 {
-    // ISS fetch task
+    // Satellite positions task: интервалы берутся из реестра спутников
     {
         let svc = iss_service.clone();
-        let interval = config.iss_every_seconds;
         tokio::spawn(async move {
             loop {
-                if let Err(e) = svc.fetch_and_store().await {
-                    error!("ISS fetch error: {:?}", e);
+                if let Err(e) = svc.poll_due().await {
+                    error!("Satellite poll error: {:?}", e);
+                }
+                tokio::time::sleep(Duration::from_secs(SATELLITE_POLL_TICK_SECONDS)).await;
+            }
+        });
+    }
+
+    // Геокодирование старых позиций: однократно при старте
+    {
+        let svc = iss_service.clone();
+        tokio::spawn(async move {
+            match svc.backfill_locations(GEOCODE_BACKFILL_BATCH).await {
+                Ok(0) => {}
+                Ok(n) => info!("Annotated {} stored positions with country/ocean", n),
+                Err(e) => error!("Location backfill error: {:?}", e),
+            }
+        });
+    }
+
+    // Агрегаты: первичное заполнение, затем удаление истории по срокам хранения
+    {
+        let svc = iss_service.clone();
+        let interval = config.retention_every_seconds;
+        tokio::spawn(async move {
+            if let Err(e) = svc.ensure_rollups().await {
+                error!("Rollup initialization error: {:?}", e);
+            }
+            loop {
+                if let Err(e) = svc.apply_retention().await {
+                    error!("Retention error: {:?}", e);
+                }
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        });
+    }
+
+    // TLE refresh task
+    {
+        let svc = orbit_service.clone();
+        let interval = config.tle_every_seconds;
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = svc.refresh_tle().await {
+                    error!("TLE refresh error: {:?}", e);
                 }
                 tokio::time::sleep(Duration::from_secs(interval)).await;
             }
         });
     }
 
+    // TLE accuracy check task
+    {
+        let svc = orbit_service.clone();
+        let interval = config.tle_check_every_seconds;
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+                if let Err(e) = svc.check_all().await {
+                    error!("TLE check error: {:?}", e);
+                }
+            }
+        });
+    }
+
+    // Anomaly detection task
+    {
+        let svc = anomaly_service.clone();
+        let interval = config.anomaly_every_seconds;
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = svc.run().await {
+                    error!("Anomaly detection error: {:?}", e);
+                }
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        });
+    }
+
+    // Geofence task: каждая новая позиция проверяется сразу после сохранения
+    {
+        let svc = geofence_service.clone();
+        let iss = iss_service.clone();
+        let mut positions = iss_service.subscribe();
+        tokio::spawn(async move {
+            loop {
+                let logs = match positions.recv().await {
+                    Ok(log) => vec![log],
+                    // Пропущенные позиции не восстановить, но последняя позиция
+                    // каждого спутника из лога даёт актуальное состояние зон
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Geofence evaluation skipped {} positions, re-checking latest ones", skipped);
+                        match iss.latest_positions().await {
+                            Ok(logs) => logs,
+                            Err(e) => {
+                                error!("Cannot read latest positions for geofences: {:?}", e);
+                                continue;
+                            }
+                        }
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                for log in &logs {
+                    if let Err(e) = svc.evaluate(log).await {
+                        error!("Geofence evaluation error: {:?}", e);
+                    }
+                }
+            }
+        });
+    }
+
     // OSDR sync task
     {
         let svc = osdr_service.clone();
         let interval = config.osdr_every_seconds;
         tokio::spawn(async move {
             loop {
-                if let Err(e) = svc.sync_datasets().await {
-                    error!("OSDR sync error: {:?}", e);
+                match svc.sync_datasets(false).await {
+                    Ok(report) if report.status == OsdrSyncStatus::Failed => {
+                        error!("OSDR sync failed: {}", report.error.unwrap_or_default());
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("OSDR sync error: {:?}", e),
+                }
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        });
+    }
+
+    // Плановая выгрузка OSDR в файлы
+    if let Some(dir) = config.osdr_export_dir.clone() {
+        let svc = osdr_service.clone();
+        let interval = config.osdr_export_every_seconds;
+        tokio::spawn(async move {
+            let dir = std::path::PathBuf::from(dir);
+            loop {
+                if let Err(e) = svc.export_to_dir(&dir, &osdr_export_formats).await {
+                    error!("OSDR export error: {:?}", e);
                 }
                 tokio::time::sleep(Duration::from_secs(interval)).await;
             }
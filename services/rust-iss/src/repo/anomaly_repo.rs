@@ -0,0 +1,293 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, PgPool, Row};
+
+use crate::domain::anomaly::{DetectedAnomaly, PositionSample};
+use crate::domain::IssAnomaly;
+use crate::errors::ApiError;
+
+/// Новые точки лога для детектора и граница, до которой они прочитаны
+pub struct SampleBatch {
+    pub samples: Vec<PositionSample>,
+    /// Наибольший id среди новых строк (включая строки без координат)
+    pub last_fetch_id: Option<i64>,
+}
+
+#[async_trait]
+pub trait AnomalyRepository: Send + Sync {
+    async fn get_watermark(&self, norad_id: i64) -> Result<i64, ApiError>;
+    async fn set_watermark(&self, norad_id: i64, last_fetch_id: i64) -> Result<(), ApiError>;
+    /// До `limit` строк после `after_id` плюс `context_seconds` предшествующей истории
+    async fn load_new_samples(
+        &self,
+        norad_id: i64,
+        after_id: i64,
+        context_seconds: i64,
+        limit: i64,
+    ) -> Result<SampleBatch, ApiError>;
+    async fn load_samples(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<PositionSample>, ApiError>;
+    /// Сохраняет аномалии; уже записанные (та же точка и вид) пропускаются
+    async fn insert(&self, norad_id: i64, anomalies: &[DetectedAnomaly]) -> Result<Vec<IssAnomaly>, ApiError>;
+    async fn last_of_kind(&self, norad_id: i64, kind: &str) -> Result<Option<IssAnomaly>, ApiError>;
+    async fn list(
+        &self,
+        norad_id: i64,
+        kind: Option<String>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<IssAnomaly>, ApiError>;
+}
+
+pub struct PgAnomalyRepo {
+    pool: PgPool,
+}
+
+impl PgAnomalyRepo {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Время фиксации из payload (если число) и координаты, пригодные для расчётов
+const SAMPLE_COLUMNS: &str = r#"
+    id,
+    COALESCE(
+        CASE WHEN jsonb_typeof(payload->'timestamp') = 'number'
+             THEN to_timestamp((payload->>'timestamp')::float8) END,
+        fetched_at
+    ) AS at,
+    (payload->>'latitude')::float8 AS lat,
+    (payload->>'longitude')::float8 AS lon,
+    CASE WHEN jsonb_typeof(payload->'altitude') = 'number'
+         THEN (payload->>'altitude')::float8 END AS alt,
+    CASE WHEN jsonb_typeof(payload->'velocity') = 'number'
+         THEN (payload->>'velocity')::float8 END AS velocity
+"#;
+
+const SAMPLE_FILTER: &str = r#"
+    jsonb_typeof(payload->'latitude') = 'number'
+    AND jsonb_typeof(payload->'longitude') = 'number'
+"#;
+
+#[async_trait]
+impl AnomalyRepository for PgAnomalyRepo {
+    async fn get_watermark(&self, norad_id: i64) -> Result<i64, ApiError> {
+        let row = sqlx::query(r#"SELECT last_fetch_id FROM iss_anomaly_state WHERE norad_id = $1"#)
+            .bind(norad_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|r| r.get("last_fetch_id")).unwrap_or(0))
+    }
+
+    async fn set_watermark(&self, norad_id: i64, last_fetch_id: i64) -> Result<(), ApiError> {
+        sqlx::query(
+            r#"
+            INSERT INTO iss_anomaly_state (norad_id, last_fetch_id, processed_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (norad_id) DO UPDATE
+            SET last_fetch_id = GREATEST(iss_anomaly_state.last_fetch_id, EXCLUDED.last_fetch_id),
+                processed_at = NOW()
+            "#
+        )
+        .bind(norad_id)
+        .bind(last_fetch_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn load_new_samples(
+        &self,
+        norad_id: i64,
+        after_id: i64,
+        context_seconds: i64,
+        limit: i64,
+    ) -> Result<SampleBatch, ApiError> {
+        let bounds = sqlx::query(
+            r#"
+            SELECT MIN(fetched_at) AS first_at, MAX(id) AS last_id
+            FROM (
+                SELECT id, fetched_at
+                FROM iss_fetch_log
+                WHERE norad_id = $1 AND id > $2
+                ORDER BY id
+                LIMIT $3
+            ) fresh
+            "#
+        )
+        .bind(norad_id)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let first_at: Option<DateTime<Utc>> = bounds.get("first_at");
+        let last_id: Option<i64> = bounds.get("last_id");
+        let (Some(first_at), Some(last_id)) = (first_at, last_id) else {
+            return Ok(SampleBatch { samples: Vec::new(), last_fetch_id: None });
+        };
+
+        let sql = format!(
+            r#"
+            SELECT {SAMPLE_COLUMNS}
+            FROM iss_fetch_log
+            WHERE norad_id = $1
+              AND fetched_at >= $2 - make_interval(secs => $3)
+              AND id <= $4
+              AND {SAMPLE_FILTER}
+            ORDER BY at, id
+            "#
+        );
+        let rows = sqlx::query(&sql)
+            .bind(norad_id)
+            .bind(first_at)
+            .bind(context_seconds as f64)
+            .bind(last_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(SampleBatch {
+            samples: rows.iter().map(map_sample).collect(),
+            last_fetch_id: Some(last_id),
+        })
+    }
+
+    async fn load_samples(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<PositionSample>, ApiError> {
+        let sql = format!(
+            r#"
+            SELECT {SAMPLE_COLUMNS}
+            FROM iss_fetch_log
+            WHERE norad_id = $1
+              AND fetched_at >= $2
+              AND fetched_at < $3
+              AND {SAMPLE_FILTER}
+            ORDER BY at, id
+            "#
+        );
+        let rows = sqlx::query(&sql)
+            .bind(norad_id)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(map_sample).collect())
+    }
+
+    async fn insert(&self, norad_id: i64, anomalies: &[DetectedAnomaly]) -> Result<Vec<IssAnomaly>, ApiError> {
+        let mut stored = Vec::new();
+
+        for a in anomalies {
+            let row = sqlx::query(
+                r#"
+                INSERT INTO iss_anomalies (norad_id, kind, fetch_id, observed_at, value, threshold, details)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (norad_id, fetch_id, kind) DO NOTHING
+                RETURNING id, norad_id, kind, fetch_id, observed_at, detected_at, value, threshold, details
+                "#
+            )
+            .bind(norad_id)
+            .bind(a.kind.as_str())
+            .bind(a.fetch_id)
+            .bind(a.observed_at)
+            .bind(a.value)
+            .bind(a.threshold)
+            .bind(&a.details)
+            .fetch_optional(&self.pool)
+            .await?;
+
+            if let Some(row) = row {
+                stored.push(map_anomaly(&row));
+            }
+        }
+
+        Ok(stored)
+    }
+
+    async fn last_of_kind(&self, norad_id: i64, kind: &str) -> Result<Option<IssAnomaly>, ApiError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, norad_id, kind, fetch_id, observed_at, detected_at, value, threshold, details
+            FROM iss_anomalies
+            WHERE norad_id = $1 AND kind = $2
+            ORDER BY observed_at DESC
+            LIMIT 1
+            "#
+        )
+        .bind(norad_id)
+        .bind(kind)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(map_anomaly))
+    }
+
+    async fn list(
+        &self,
+        norad_id: i64,
+        kind: Option<String>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<IssAnomaly>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, norad_id, kind, fetch_id, observed_at, detected_at, value, threshold, details
+            FROM iss_anomalies
+            WHERE norad_id = $1
+              AND ($2::text IS NULL OR kind = $2)
+              AND ($3::timestamptz IS NULL OR observed_at >= $3)
+              AND ($4::timestamptz IS NULL OR observed_at < $4)
+            ORDER BY observed_at DESC
+            LIMIT $5
+            "#
+        )
+        .bind(norad_id)
+        .bind(kind)
+        .bind(from)
+        .bind(to)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(map_anomaly).collect())
+    }
+}
+
+fn map_sample(r: &PgRow) -> PositionSample {
+    PositionSample {
+        fetch_id: r.get("id"),
+        at: r.get("at"),
+        lat: r.get("lat"),
+        lon: r.get("lon"),
+        alt: r.get("alt"),
+        velocity: r.get("velocity"),
+    }
+}
+
+fn map_anomaly(r: &PgRow) -> IssAnomaly {
+    IssAnomaly {
+        id: r.get("id"),
+        norad_id: r.get("norad_id"),
+        kind: r.get("kind"),
+        fetch_id: r.get("fetch_id"),
+        observed_at: r.get("observed_at"),
+        detected_at: r.get("detected_at"),
+        value: r.get("value"),
+        threshold: r.get("threshold"),
+        details: r.get("details"),
+    }
+}
@@ -1,15 +1,20 @@
 use async_trait::async_trait;
 use serde_json::Value;
-use sqlx::{PgPool, Row};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
 
+use crate::domain::pagination::{key_order, Cursor, Page};
 use crate::domain::SpaceCache;
 use crate::errors::ApiError;
+use crate::repo::keyset::push_after;
 
 #[async_trait]
 pub trait CacheRepository: Send + Sync {
     async fn insert(&self, source: &str, payload: Value) -> Result<i64, ApiError>;
     async fn get_latest(&self, source: &str) -> Result<Option<SpaceCache>, ApiError>;
-    async fn cleanup_old(&self, source: &str, keep_days: i32) -> Result<u64, ApiError>;
+    /// История источника от новых к старым, страница после `cursor`; `total` не заполняется
+    async fn history(&self, source: &str, cursor: Option<&Cursor>, limit: i64) -> Result<Page<SpaceCache>, ApiError>;
+    async fn count(&self, source: &str) -> Result<i64, ApiError>;
 }
 
 pub struct PgCacheRepo {
@@ -54,26 +59,45 @@ impl CacheRepository for PgCacheRepo {
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(row.map(|r| SpaceCache {
-            id: r.get("id"),
-            source: r.get("source"),
-            fetched_at: r.get("fetched_at"),
-            payload: r.get("payload"),
+        Ok(row.as_ref().map(map_cache))
+    }
+
+    async fn history(&self, source: &str, cursor: Option<&Cursor>, limit: i64) -> Result<Page<SpaceCache>, ApiError> {
+        let mut qb = QueryBuilder::<Postgres>::new(
+            "SELECT id, source, fetched_at, payload FROM space_cache WHERE source = ",
+        );
+        qb.push_bind(source);
+        if let Some(cursor) = cursor {
+            push_after(&mut qb, "fetched_at", None, cursor);
+        }
+        let descending = cursor.is_none_or(Cursor::scan_descending);
+        qb.push(" ORDER BY ")
+            .push(key_order("fetched_at", None, descending))
+            .push(" LIMIT ")
+            .push_bind(limit + 1);
+
+        let rows = qb.build().fetch_all(&self.pool).await?;
+        let entries = rows.iter().map(map_cache).collect();
+        Ok(Page::from_rows(entries, limit as usize, cursor, true, false, |c| {
+            (Some(c.fetched_at), c.id)
         }))
     }
 
-    async fn cleanup_old(&self, source: &str, keep_days: i32) -> Result<u64, ApiError> {
-        let result = sqlx::query(
-            r#"
-            DELETE FROM space_cache
-            WHERE source = $1 AND fetched_at < NOW() - INTERVAL '1 day' * $2
-            "#
-        )
-        .bind(source)
-        .bind(keep_days)
-        .execute(&self.pool)
-        .await?;
+    async fn count(&self, source: &str) -> Result<i64, ApiError> {
+        let row = sqlx::query("SELECT COUNT(*) AS count FROM space_cache WHERE source = $1")
+            .bind(source)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get("count"))
+    }
+}
 
-        Ok(result.rows_affected())
+fn map_cache(r: &PgRow) -> SpaceCache {
+    SpaceCache {
+        id: r.get("id"),
+        source: r.get("source"),
+        fetched_at: r.get("fetched_at"),
+        payload: r.get("payload"),
     }
 }
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use sqlx::{postgres::PgRow, PgPool, Row};
+
+use crate::domain::geofence::GeofenceShape;
+use crate::domain::{Geofence, GeofenceCrossing, GeofenceEvent};
+use crate::errors::ApiError;
+
+#[async_trait]
+pub trait GeofenceRepository: Send + Sync {
+    async fn list(&self) -> Result<Vec<Geofence>, ApiError>;
+    async fn get(&self, id: i64) -> Result<Option<Geofence>, ApiError>;
+    async fn create(
+        &self,
+        name: &str,
+        shape: &GeofenceShape,
+        norad_id: Option<i64>,
+        enabled: bool,
+    ) -> Result<Geofence, ApiError>;
+    /// При смене формы состояние зоны сбрасывается: старое «внутри/снаружи»
+    /// относилось к другой форме и дало бы ложные входы и выходы
+    async fn update(
+        &self,
+        id: i64,
+        name: &str,
+        shape: &GeofenceShape,
+        norad_id: Option<i64>,
+        enabled: bool,
+    ) -> Result<Option<Geofence>, ApiError>;
+    async fn delete(&self, id: i64) -> Result<bool, ApiError>;
+
+    /// Включённые зоны, относящиеся к спутнику
+    async fn list_for_satellite(&self, norad_id: i64) -> Result<Vec<Geofence>, ApiError>;
+    /// Находится ли спутник внутри каждой зоны по последней проверке
+    async fn get_states(&self, norad_id: i64) -> Result<HashMap<i64, bool>, ApiError>;
+    /// Новое состояние и событие пересечения пишутся одной транзакцией,
+    /// чтобы событие не потерялось при сохранённом состоянии
+    async fn record_state(
+        &self,
+        geofence_id: i64,
+        norad_id: i64,
+        inside: bool,
+        crossing: Option<&GeofenceCrossing>,
+    ) -> Result<Option<GeofenceEvent>, ApiError>;
+    async fn list_events(
+        &self,
+        geofence_id: i64,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<GeofenceEvent>, ApiError>;
+}
+
+pub struct PgGeofenceRepo {
+    pool: PgPool,
+}
+
+impl PgGeofenceRepo {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl GeofenceRepository for PgGeofenceRepo {
+    async fn list(&self) -> Result<Vec<Geofence>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, name, shape, norad_id, enabled, created_at, updated_at
+            FROM geofences
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(map_geofence).collect()
+    }
+
+    async fn get(&self, id: i64) -> Result<Option<Geofence>, ApiError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, name, shape, norad_id, enabled, created_at, updated_at
+            FROM geofences
+            WHERE id = $1
+            "#
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(map_geofence).transpose()
+    }
+
+    async fn create(
+        &self,
+        name: &str,
+        shape: &GeofenceShape,
+        norad_id: Option<i64>,
+        enabled: bool,
+    ) -> Result<Geofence, ApiError> {
+        let row = sqlx::query(
+            r#"
+            INSERT INTO geofences (name, shape, norad_id, enabled)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, name, shape, norad_id, enabled, created_at, updated_at
+            "#
+        )
+        .bind(name)
+        .bind(json!(shape))
+        .bind(norad_id)
+        .bind(enabled)
+        .fetch_one(&self.pool)
+        .await?;
+
+        map_geofence(&row)
+    }
+
+    async fn update(
+        &self,
+        id: i64,
+        name: &str,
+        shape: &GeofenceShape,
+        norad_id: Option<i64>,
+        enabled: bool,
+    ) -> Result<Option<Geofence>, ApiError> {
+        let shape = json!(shape);
+        let mut tx = self.pool.begin().await?;
+        let Some(old_shape) = sqlx::query_scalar::<_, Value>(r#"SELECT shape FROM geofences WHERE id = $1 FOR UPDATE"#)
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let row = sqlx::query(
+            r#"
+            UPDATE geofences
+            SET name = $2, shape = $3, norad_id = $4, enabled = $5
+            WHERE id = $1
+            RETURNING id, name, shape, norad_id, enabled, created_at, updated_at
+            "#
+        )
+        .bind(id)
+        .bind(name)
+        .bind(&shape)
+        .bind(norad_id)
+        .bind(enabled)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if old_shape != shape {
+            sqlx::query(r#"DELETE FROM geofence_state WHERE geofence_id = $1"#)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        map_geofence(&row).map(Some)
+    }
+
+    async fn delete(&self, id: i64) -> Result<bool, ApiError> {
+        let result = sqlx::query(r#"DELETE FROM geofences WHERE id = $1"#)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_for_satellite(&self, norad_id: i64) -> Result<Vec<Geofence>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, name, shape, norad_id, enabled, created_at, updated_at
+            FROM geofences
+            WHERE enabled AND (norad_id IS NULL OR norad_id = $1)
+            ORDER BY id
+            "#
+        )
+        .bind(norad_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(map_geofence).collect()
+    }
+
+    async fn get_states(&self, norad_id: i64) -> Result<HashMap<i64, bool>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT geofence_id, inside
+            FROM geofence_state
+            WHERE norad_id = $1
+            "#
+        )
+        .bind(norad_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get("geofence_id"), r.get("inside"))).collect())
+    }
+
+    async fn record_state(
+        &self,
+        geofence_id: i64,
+        norad_id: i64,
+        inside: bool,
+        crossing: Option<&GeofenceCrossing>,
+    ) -> Result<Option<GeofenceEvent>, ApiError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO geofence_state (geofence_id, norad_id, inside, updated_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (geofence_id, norad_id) DO UPDATE
+            SET inside = EXCLUDED.inside, updated_at = NOW()
+            "#
+        )
+        .bind(geofence_id)
+        .bind(norad_id)
+        .bind(inside)
+        .execute(&mut *tx)
+        .await?;
+
+        let event = match crossing {
+            Some(crossing) => {
+                let row = sqlx::query(
+                    r#"
+                    INSERT INTO geofence_events (geofence_id, norad_id, event, fetch_id, occurred_at, latitude, longitude)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    RETURNING id, geofence_id, norad_id, event, fetch_id, occurred_at, latitude, longitude
+                    "#
+                )
+                .bind(geofence_id)
+                .bind(norad_id)
+                .bind(crossing.event)
+                .bind(crossing.fetch_id)
+                .bind(crossing.occurred_at)
+                .bind(crossing.latitude)
+                .bind(crossing.longitude)
+                .fetch_one(&mut *tx)
+                .await?;
+                Some(map_event(&row))
+            }
+            None => None,
+        };
+
+        tx.commit().await?;
+        Ok(event)
+    }
+
+    async fn list_events(
+        &self,
+        geofence_id: i64,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<GeofenceEvent>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, geofence_id, norad_id, event, fetch_id, occurred_at, latitude, longitude
+            FROM geofence_events
+            WHERE geofence_id = $1
+              AND ($2::timestamptz IS NULL OR occurred_at >= $2)
+              AND ($3::timestamptz IS NULL OR occurred_at < $3)
+            ORDER BY occurred_at DESC
+            LIMIT $4
+            "#
+        )
+        .bind(geofence_id)
+        .bind(from)
+        .bind(to)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(map_event).collect())
+    }
+}
+
+fn map_geofence(r: &PgRow) -> Result<Geofence, ApiError> {
+    let shape = serde_json::from_value(r.get("shape"))
+        .map_err(|e| ApiError::internal(format!("Invalid geofence shape: {}", e)))?;
+
+    Ok(Geofence {
+        id: r.get("id"),
+        name: r.get("name"),
+        shape,
+        norad_id: r.get("norad_id"),
+        enabled: r.get("enabled"),
+        created_at: r.get("created_at"),
+        updated_at: r.get("updated_at"),
+    })
+}
+
+fn map_event(r: &PgRow) -> GeofenceEvent {
+    GeofenceEvent {
+        id: r.get("id"),
+        geofence_id: r.get("geofence_id"),
+        norad_id: r.get("norad_id"),
+        event: r.get("event"),
+        fetch_id: r.get("fetch_id"),
+        occurred_at: r.get("occurred_at"),
+        latitude: r.get("latitude"),
+        longitude: r.get("longitude"),
+    }
+}
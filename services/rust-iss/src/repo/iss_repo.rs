@@ -1,17 +1,144 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use futures_util::stream::{self, BoxStream, StreamExt};
 use serde_json::Value;
-use sqlx::{PgPool, Row};
+use sqlx::{postgres::PgRow, PgPool, Postgres, QueryBuilder, Row};
 
-use crate::domain::{IssFetchLog, IssTrend};
+use crate::domain::geocode::{GeoKind, GeoLocation};
+use crate::domain::pagination::{key_order, Cursor, Page};
+use crate::domain::{
+    DwellTime, FetchError, IssFetchLog, IssTrend, MetricStats, RollupResolution, Satellite,
+};
 use crate::errors::ApiError;
+use crate::repo::keyset::push_after;
+
+/// Размер страницы при потоковом чтении лога позиций
+const STREAM_PAGE_SIZE: i64 = 1000;
 
 #[async_trait]
 pub trait IssRepository: Send + Sync {
-    async fn insert(&self, source_url: &str, payload: Value) -> Result<i64, ApiError>;
-    async fn get_last(&self) -> Result<Option<IssFetchLog>, ApiError>;
-    async fn get_last_n(&self, n: i64) -> Result<Vec<IssFetchLog>, ApiError>;
-    async fn get_trend(&self, hours: i64) -> Result<Vec<IssTrend>, ApiError>;
+    /// Возвращает id и fetched_at новой строки
+    async fn insert(
+        &self,
+        norad_id: i64,
+        source_url: &str,
+        payload: Value,
+        provider: &str,
+        location: Option<&GeoLocation>,
+    ) -> Result<(i64, DateTime<Utc>), ApiError>;
+    async fn get_last(&self, norad_id: i64) -> Result<Option<IssFetchLog>, ApiError>;
+    async fn get_last_n(&self, norad_id: i64, n: i64) -> Result<Vec<IssFetchLog>, ApiError>;
+    /// Лог позиций от новых к старым, страница после `cursor`; `total` не заполняется
+    async fn history(&self, norad_id: i64, cursor: Option<&Cursor>, limit: i64) -> Result<Page<IssFetchLog>, ApiError>;
+    async fn count(&self, norad_id: i64) -> Result<i64, ApiError>;
+    /// Все позиции окна [from, to) по возрастанию времени; читаются страницами
+    fn stream_range(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxStream<'static, Result<IssFetchLog, ApiError>>;
+    /// Агрегаты по интервалам длиной `bucket_seconds` в окне [from, to), от новых к старым;
+    /// при заданном `rollup` считаются по таблице агрегатов вместо сырого лога
+    async fn get_trend(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket_seconds: i64,
+        rollup: Option<RollupResolution>,
+    ) -> Result<Vec<IssTrend>, ApiError>;
+
+    // Полнота лога
+    async fn insert_fetch_error(&self, norad_id: i64, code: &str, message: &str) -> Result<(), ApiError>;
+    async fn get_fetch_errors(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<FetchError>, ApiError>;
+    /// Первая и последняя точка в окне
+    async fn get_sample_bounds(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Option<(DateTime<Utc>, DateTime<Utc>)>, ApiError>;
+    /// Пары соседних точек, между которыми больше `min_gap_seconds`
+    async fn get_gaps(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        min_gap_seconds: f64,
+    ) -> Result<Vec<(DateTime<Utc>, DateTime<Utc>)>, ApiError>;
+    async fn get_sample_counts(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket_seconds: i64,
+        rollup: Option<RollupResolution>,
+    ) -> Result<Vec<(DateTime<Utc>, i64)>, ApiError>;
+
+    // Агрегаты и хранение
+    /// Самая ранняя сырая точка по всем спутникам
+    async fn get_raw_start(&self) -> Result<Option<DateTime<Utc>>, ApiError>;
+    async fn has_rollups(&self) -> Result<bool, ApiError>;
+    /// Пересчитывает агрегаты в [from, to) из сырого лога; границы выровнены по разрешению
+    async fn rebuild_rollup(
+        &self,
+        resolution: RollupResolution,
+        norad_id: Option<i64>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<u64, ApiError>;
+    async fn delete_raw_before(&self, cutoff: DateTime<Utc>) -> Result<u64, ApiError>;
+    async fn delete_rollups_before(
+        &self,
+        resolution: RollupResolution,
+        cutoff: DateTime<Utc>,
+    ) -> Result<u64, ApiError>;
+
+    // Геокодирование
+    /// Записи без аннотации страны/океана с id больше `after_id`, по возрастанию id
+    async fn get_unlocated(&self, after_id: i64, limit: i64) -> Result<Vec<IssFetchLog>, ApiError>;
+    async fn set_location(
+        &self,
+        id: i64,
+        fetched_at: DateTime<Utc>,
+        location: &GeoLocation,
+    ) -> Result<(), ApiError>;
+    /// Время над каждой страной/океаном: каждая точка длится до следующей,
+    /// но не дольше `max_step_seconds`
+    async fn get_dwell(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        max_step_seconds: f64,
+    ) -> Result<Vec<DwellTime>, ApiError>;
+
+    // Реестр спутников
+    async fn list_satellites(&self) -> Result<Vec<Satellite>, ApiError>;
+    async fn get_satellite(&self, norad_id: i64) -> Result<Option<Satellite>, ApiError>;
+    async fn upsert_satellite(
+        &self,
+        norad_id: i64,
+        name: &str,
+        source_url: &str,
+        interval_seconds: i32,
+        enabled: bool,
+    ) -> Result<Satellite, ApiError>;
+    /// Добавляет спутник, только если его ещё нет в реестре
+    async fn ensure_satellite(
+        &self,
+        norad_id: i64,
+        name: &str,
+        source_url: &str,
+        interval_seconds: i32,
+    ) -> Result<(), ApiError>;
+    async fn delete_satellite(&self, norad_id: i64) -> Result<bool, ApiError>;
 }
 
 pub struct PgIssRepo {
@@ -22,97 +149,843 @@ impl PgIssRepo {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
+
+    /// Тренд по агрегатам: суммы и суммы квадратов складываются, затем из них
+    /// восстанавливаются среднее и выборочное отклонение
+    async fn get_trend_rollup(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket_seconds: i64,
+        resolution: RollupResolution,
+    ) -> Result<Vec<IssTrend>, ApiError> {
+        let sql = format!(
+            r#"
+            WITH agg AS (
+                SELECT
+                    to_timestamp(floor(extract(epoch FROM bucket_start)::float8 / $4) * $4) AS bucket,
+                    SUM(cnt)::bigint AS cnt,
+                    SUM(lat_cnt)::float8 AS lat_n, SUM(lat_sum) AS lat_s, SUM(lat_sumsq) AS lat_q,
+                    MIN(lat_min) AS lat_min, MAX(lat_max) AS lat_max,
+                    SUM(lon_cnt)::float8 AS lon_n, SUM(lon_sin) AS lon_sin, SUM(lon_cos) AS lon_cos,
+                    MIN(lon_min) AS lon_min, MAX(lon_max) AS lon_max,
+                    SUM(alt_cnt)::float8 AS alt_n, SUM(alt_sum) AS alt_s, SUM(alt_sumsq) AS alt_q,
+                    MIN(alt_min) AS alt_min, MAX(alt_max) AS alt_max,
+                    SUM(vel_cnt)::float8 AS vel_n, SUM(vel_sum) AS vel_s, SUM(vel_sumsq) AS vel_q,
+                    MIN(vel_min) AS vel_min, MAX(vel_max) AS vel_max
+                FROM {table}
+                WHERE norad_id = $1 AND bucket_start >= $2 AND bucket_start < $3
+                GROUP BY bucket
+            )
+            SELECT
+                bucket, cnt,
+                {lat},
+                CASE WHEN lon_n > 0 THEN DEGREES(ATAN2(lon_sin / lon_n, lon_cos / lon_n)) END AS lon_avg,
+                lon_min, lon_max,
+                CASE WHEN lon_n > 1 THEN DEGREES(SQRT(-2 * LN(GREATEST(LEAST(
+                    SQRT(lon_sin * lon_sin + lon_cos * lon_cos) / lon_n, 1.0), 1e-12)))) END AS lon_std,
+                {alt},
+                {vel}
+            FROM agg
+            ORDER BY bucket DESC
+            "#,
+            table = resolution.table(),
+            lat = rollup_stats("lat"),
+            alt = rollup_stats("alt"),
+            vel = rollup_stats("vel"),
+        );
+        let rows = sqlx::query(&sql)
+            .bind(norad_id)
+            .bind(from)
+            .bind(to)
+            .bind(bucket_seconds)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(map_trend).collect())
+    }
+}
+
+const ROLLUP_COLUMNS: &str = "norad_id, bucket_start, cnt, \
+    lat_cnt, lat_sum, lat_sumsq, lat_min, lat_max, \
+    lon_cnt, lon_sin, lon_cos, lon_min, lon_max, \
+    alt_cnt, alt_sum, alt_sumsq, alt_min, alt_max, \
+    vel_cnt, vel_sum, vel_sumsq, vel_min, vel_max";
+
+/// Слияние новой точки с существующим агрегатом (LEAST/GREATEST пропускают NULL)
+const ROLLUP_MERGE: &str = "cnt = r.cnt + EXCLUDED.cnt, \
+    lat_cnt = r.lat_cnt + EXCLUDED.lat_cnt, lat_sum = r.lat_sum + EXCLUDED.lat_sum, \
+    lat_sumsq = r.lat_sumsq + EXCLUDED.lat_sumsq, \
+    lat_min = LEAST(r.lat_min, EXCLUDED.lat_min), lat_max = GREATEST(r.lat_max, EXCLUDED.lat_max), \
+    lon_cnt = r.lon_cnt + EXCLUDED.lon_cnt, lon_sin = r.lon_sin + EXCLUDED.lon_sin, \
+    lon_cos = r.lon_cos + EXCLUDED.lon_cos, \
+    lon_min = LEAST(r.lon_min, EXCLUDED.lon_min), lon_max = GREATEST(r.lon_max, EXCLUDED.lon_max), \
+    alt_cnt = r.alt_cnt + EXCLUDED.alt_cnt, alt_sum = r.alt_sum + EXCLUDED.alt_sum, \
+    alt_sumsq = r.alt_sumsq + EXCLUDED.alt_sumsq, \
+    alt_min = LEAST(r.alt_min, EXCLUDED.alt_min), alt_max = GREATEST(r.alt_max, EXCLUDED.alt_max), \
+    vel_cnt = r.vel_cnt + EXCLUDED.vel_cnt, vel_sum = r.vel_sum + EXCLUDED.vel_sum, \
+    vel_sumsq = r.vel_sumsq + EXCLUDED.vel_sumsq, \
+    vel_min = LEAST(r.vel_min, EXCLUDED.vel_min), vel_max = GREATEST(r.vel_max, EXCLUDED.vel_max), \
+    updated_at = NOW()";
+
+const ROLLUP_REPLACE: &str = "cnt = EXCLUDED.cnt, \
+    lat_cnt = EXCLUDED.lat_cnt, lat_sum = EXCLUDED.lat_sum, lat_sumsq = EXCLUDED.lat_sumsq, \
+    lat_min = EXCLUDED.lat_min, lat_max = EXCLUDED.lat_max, \
+    lon_cnt = EXCLUDED.lon_cnt, lon_sin = EXCLUDED.lon_sin, lon_cos = EXCLUDED.lon_cos, \
+    lon_min = EXCLUDED.lon_min, lon_max = EXCLUDED.lon_max, \
+    alt_cnt = EXCLUDED.alt_cnt, alt_sum = EXCLUDED.alt_sum, alt_sumsq = EXCLUDED.alt_sumsq, \
+    alt_min = EXCLUDED.alt_min, alt_max = EXCLUDED.alt_max, \
+    vel_cnt = EXCLUDED.vel_cnt, vel_sum = EXCLUDED.vel_sum, vel_sumsq = EXCLUDED.vel_sumsq, \
+    vel_min = EXCLUDED.vel_min, vel_max = EXCLUDED.vel_max, \
+    updated_at = NOW()";
+
+/// Агрегаты сырых точек под условием `filter` в колонках ROLLUP_COLUMNS
+fn rollup_select(resolution: RollupResolution, filter: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            norad_id,
+            to_timestamp(floor(extract(epoch FROM fetched_at)::float8 / {seconds}) * {seconds}) AS bucket_start,
+            COUNT(*),
+            COUNT(lat), COALESCE(SUM(lat), 0), COALESCE(SUM(lat * lat), 0), MIN(lat), MAX(lat),
+            COUNT(lon), COALESCE(SUM(SIN(RADIANS(lon))), 0), COALESCE(SUM(COS(RADIANS(lon))), 0),
+            MIN(lon), MAX(lon),
+            COUNT(alt), COALESCE(SUM(alt), 0), COALESCE(SUM(alt * alt), 0), MIN(alt), MAX(alt),
+            COUNT(vel), COALESCE(SUM(vel), 0), COALESCE(SUM(vel * vel), 0), MIN(vel), MAX(vel)
+        FROM (
+            SELECT
+                norad_id, fetched_at,
+                (payload->>'latitude')::float AS lat,
+                (payload->>'longitude')::float AS lon,
+                (payload->>'altitude')::float AS alt,
+                (payload->>'velocity')::float AS vel
+            FROM iss_fetch_log
+            WHERE {filter}
+        ) s
+        GROUP BY norad_id, bucket_start
+        "#,
+        seconds = resolution.seconds(),
+        filter = filter,
+    )
+}
+
+/// avg/min/max/std показателя из сумм агрегатов
+fn rollup_stats(prefix: &str) -> String {
+    format!(
+        "{p}_s / NULLIF({p}_n, 0) AS {p}_avg, {p}_min, {p}_max, \
+         CASE WHEN {p}_n > 1 THEN SQRT(GREATEST(({p}_q - {p}_s * {p}_s / {p}_n) / ({p}_n - 1), 0)) END AS {p}_std",
+        p = prefix
+    )
 }
 
 #[async_trait]
 impl IssRepository for PgIssRepo {
-    async fn insert(&self, source_url: &str, payload: Value) -> Result<i64, ApiError> {
+    async fn insert(
+        &self,
+        norad_id: i64,
+        source_url: &str,
+        payload: Value,
+        provider: &str,
+        location: Option<&GeoLocation>,
+    ) -> Result<(i64, DateTime<Utc>), ApiError> {
+        // Точка и агрегаты пишутся одной транзакцией
+        let mut tx = self.pool.begin().await?;
         let row = sqlx::query(
             r#"
-            INSERT INTO iss_fetch_log (source_url, payload)
-            VALUES ($1, $2)
-            RETURNING id
+            INSERT INTO iss_fetch_log (norad_id, source_url, payload, provider, geo_kind, geo_code, geo_name)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, fetched_at
             "#
         )
+        .bind(norad_id)
         .bind(source_url)
         .bind(&payload)
-        .fetch_one(&self.pool)
+        .bind(provider)
+        .bind(location.map(|l| l.kind.as_str()))
+        .bind(location.and_then(|l| l.code.as_deref()))
+        .bind(location.map(|l| l.name.as_str()))
+        .fetch_one(&mut *tx)
         .await?;
+        let id: i64 = row.get("id");
+        let fetched_at: DateTime<Utc> = row.get("fetched_at");
+
+        for resolution in RollupResolution::ALL {
+            let sql = format!(
+                r#"
+                INSERT INTO {table} AS r ({columns})
+                {select}
+                ON CONFLICT (norad_id, bucket_start) DO UPDATE SET {merge}
+                "#,
+                table = resolution.table(),
+                columns = ROLLUP_COLUMNS,
+                select = rollup_select(resolution, "id = $1 AND fetched_at = $2"),
+                merge = ROLLUP_MERGE,
+            );
+            sqlx::query(&sql)
+                .bind(id)
+                .bind(fetched_at)
+                .execute(&mut *tx)
+                .await?;
+        }
 
-        Ok(row.get("id"))
+        tx.commit().await?;
+        Ok((id, fetched_at))
     }
 
-    async fn get_last(&self) -> Result<Option<IssFetchLog>, ApiError> {
+    async fn get_last(&self, norad_id: i64) -> Result<Option<IssFetchLog>, ApiError> {
         let row = sqlx::query(
             r#"
-            SELECT id, fetched_at, source_url, payload
+            SELECT id, norad_id, fetched_at, source_url, payload, provider, geo_kind, geo_code, geo_name
             FROM iss_fetch_log
+            WHERE norad_id = $1
             ORDER BY fetched_at DESC
             LIMIT 1
             "#
         )
+        .bind(norad_id)
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(row.map(|r| IssFetchLog {
-            id: r.get("id"),
-            fetched_at: r.get("fetched_at"),
-            source_url: r.get("source_url"),
-            payload: r.get("payload"),
-        }))
+        Ok(row.as_ref().map(map_log))
     }
 
-    async fn get_last_n(&self, n: i64) -> Result<Vec<IssFetchLog>, ApiError> {
+    async fn get_last_n(&self, norad_id: i64, n: i64) -> Result<Vec<IssFetchLog>, ApiError> {
         let rows = sqlx::query(
             r#"
-            SELECT id, fetched_at, source_url, payload
+            SELECT id, norad_id, fetched_at, source_url, payload, provider, geo_kind, geo_code, geo_name
             FROM iss_fetch_log
-            ORDER BY fetched_at DESC
-            LIMIT $1
+            WHERE norad_id = $1
+            ORDER BY fetched_at DESC, id DESC
+            LIMIT $2
             "#
         )
+        .bind(norad_id)
         .bind(n)
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(rows.into_iter().map(|r| IssFetchLog {
-            id: r.get("id"),
-            fetched_at: r.get("fetched_at"),
-            source_url: r.get("source_url"),
-            payload: r.get("payload"),
+        Ok(rows.iter().map(map_log).collect())
+    }
+
+    async fn history(&self, norad_id: i64, cursor: Option<&Cursor>, limit: i64) -> Result<Page<IssFetchLog>, ApiError> {
+        let mut qb = QueryBuilder::<Postgres>::new(
+            "SELECT id, norad_id, fetched_at, source_url, payload, provider, geo_kind, geo_code, geo_name \
+             FROM iss_fetch_log WHERE norad_id = ",
+        );
+        qb.push_bind(norad_id);
+        if let Some(cursor) = cursor {
+            push_after(&mut qb, "fetched_at", None, cursor);
+        }
+        let descending = cursor.is_none_or(Cursor::scan_descending);
+        qb.push(" ORDER BY ")
+            .push(key_order("fetched_at", None, descending))
+            .push(" LIMIT ")
+            .push_bind(limit + 1);
+
+        let rows = qb.build().fetch_all(&self.pool).await?;
+        let logs = rows.iter().map(map_log).collect();
+        Ok(Page::from_rows(logs, limit as usize, cursor, true, false, |l| {
+            (Some(l.fetched_at), l.id)
+        }))
+    }
+
+    async fn count(&self, norad_id: i64) -> Result<i64, ApiError> {
+        let row = sqlx::query("SELECT COUNT(*) AS count FROM iss_fetch_log WHERE norad_id = $1")
+            .bind(norad_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get("count"))
+    }
+
+    fn stream_range(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> BoxStream<'static, Result<IssFetchLog, ApiError>> {
+        let pool = self.pool.clone();
+        // Keyset по (fetched_at, id): None — поток исчерпан, Some(None) — первая страница
+        stream::unfold(Some(None), move |cursor: Option<Option<(DateTime<Utc>, i64)>>| {
+            let pool = pool.clone();
+            async move {
+                let after = cursor?;
+                let rows = sqlx::query(
+                    r#"
+                    SELECT id, norad_id, fetched_at, source_url, payload, provider, geo_kind, geo_code, geo_name
+                    FROM iss_fetch_log
+                    WHERE norad_id = $1 AND fetched_at >= $2 AND fetched_at < $3
+                      AND ($4::timestamptz IS NULL OR (fetched_at, id) > ($4, $5))
+                    ORDER BY fetched_at, id
+                    LIMIT $6
+                    "#
+                )
+                .bind(norad_id)
+                .bind(from)
+                .bind(to)
+                .bind(after.map(|(at, _)| at))
+                .bind(after.map(|(_, id)| id).unwrap_or(0))
+                .bind(STREAM_PAGE_SIZE)
+                .fetch_all(&pool)
+                .await;
+
+                match rows {
+                    Err(e) => Some((vec![Err(ApiError::from(e))], None)),
+                    Ok(rows) if rows.is_empty() => None,
+                    Ok(rows) => {
+                        let logs: Vec<IssFetchLog> = rows.iter().map(map_log).collect();
+                        let next = (logs.len() as i64 == STREAM_PAGE_SIZE)
+                            .then(|| logs.last().map(|l| (l.fetched_at, l.id)));
+                        Some((logs.into_iter().map(Ok).collect(), next))
+                    }
+                }
+            }
+        })
+        .flat_map(stream::iter)
+        .boxed()
+    }
+
+    async fn get_trend(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket_seconds: i64,
+        rollup: Option<RollupResolution>,
+    ) -> Result<Vec<IssTrend>, ApiError> {
+        if let Some(resolution) = rollup {
+            return self.get_trend_rollup(norad_id, from, to, bucket_seconds, resolution).await;
+        }
+
+        // Долгота усредняется по кругу, чтобы переход через ±180° не давал 0
+        let rows = sqlx::query(
+            r#"
+            WITH samples AS (
+                SELECT
+                    to_timestamp(floor(extract(epoch FROM fetched_at)::float8 / $4) * $4) AS bucket,
+                    (payload->>'latitude')::float AS lat,
+                    (payload->>'longitude')::float AS lon,
+                    (payload->>'altitude')::float AS alt,
+                    (payload->>'velocity')::float AS vel
+                FROM iss_fetch_log
+                WHERE norad_id = $1
+                  AND fetched_at >= $2
+                  AND fetched_at < $3
+            ),
+            agg AS (
+                SELECT
+                    bucket,
+                    COUNT(*) AS cnt,
+                    AVG(lat) AS lat_avg, MIN(lat) AS lat_min, MAX(lat) AS lat_max, STDDEV_SAMP(lat) AS lat_std,
+                    AVG(SIN(RADIANS(lon))) AS lon_sin, AVG(COS(RADIANS(lon))) AS lon_cos,
+                    MIN(lon) AS lon_min, MAX(lon) AS lon_max,
+                    AVG(alt) AS alt_avg, MIN(alt) AS alt_min, MAX(alt) AS alt_max, STDDEV_SAMP(alt) AS alt_std,
+                    AVG(vel) AS vel_avg, MIN(vel) AS vel_min, MAX(vel) AS vel_max, STDDEV_SAMP(vel) AS vel_std
+                FROM samples
+                GROUP BY bucket
+            )
+            SELECT
+                bucket, cnt,
+                lat_avg, lat_min, lat_max, lat_std,
+                DEGREES(ATAN2(lon_sin, lon_cos)) AS lon_avg,
+                lon_min, lon_max,
+                DEGREES(SQRT(-2 * LN(GREATEST(LEAST(SQRT(lon_sin * lon_sin + lon_cos * lon_cos), 1.0), 1e-12)))) AS lon_std,
+                alt_avg, alt_min, alt_max, alt_std,
+                vel_avg, vel_min, vel_max, vel_std
+            FROM agg
+            ORDER BY bucket DESC
+            "#
+        )
+        .bind(norad_id)
+        .bind(from)
+        .bind(to)
+        .bind(bucket_seconds)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(map_trend).collect())
+    }
+
+    async fn insert_fetch_error(&self, norad_id: i64, code: &str, message: &str) -> Result<(), ApiError> {
+        sqlx::query(
+            r#"
+            INSERT INTO iss_fetch_errors (norad_id, code, message)
+            VALUES ($1, $2, $3)
+            "#
+        )
+        .bind(norad_id)
+        .bind(code)
+        .bind(message)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_fetch_errors(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<FetchError>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT occurred_at, code, message
+            FROM iss_fetch_errors
+            WHERE norad_id = $1 AND occurred_at >= $2 AND occurred_at < $3
+            ORDER BY occurred_at
+            "#
+        )
+        .bind(norad_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| FetchError {
+            occurred_at: r.get("occurred_at"),
+            code: r.get("code"),
+            message: r.get("message"),
         }).collect())
     }
 
-    async fn get_trend(&self, hours: i64) -> Result<Vec<IssTrend>, ApiError> {
+    async fn get_sample_bounds(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Option<(DateTime<Utc>, DateTime<Utc>)>, ApiError> {
+        let row = sqlx::query(
+            r#"
+            SELECT MIN(fetched_at) AS first_at, MAX(fetched_at) AS last_at
+            FROM iss_fetch_log
+            WHERE norad_id = $1 AND fetched_at >= $2 AND fetched_at < $3
+            "#
+        )
+        .bind(norad_id)
+        .bind(from)
+        .bind(to)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let first: Option<DateTime<Utc>> = row.get("first_at");
+        let last: Option<DateTime<Utc>> = row.get("last_at");
+        Ok(first.zip(last))
+    }
+
+    async fn get_gaps(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        min_gap_seconds: f64,
+    ) -> Result<Vec<(DateTime<Utc>, DateTime<Utc>)>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT prev_at, fetched_at
+            FROM (
+                SELECT fetched_at, LAG(fetched_at) OVER (ORDER BY fetched_at) AS prev_at
+                FROM iss_fetch_log
+                WHERE norad_id = $1 AND fetched_at >= $2 AND fetched_at < $3
+            ) s
+            WHERE prev_at IS NOT NULL
+              AND fetched_at - prev_at > make_interval(secs => $4)
+            ORDER BY prev_at
+            "#
+        )
+        .bind(norad_id)
+        .bind(from)
+        .bind(to)
+        .bind(min_gap_seconds)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get("prev_at"), r.get("fetched_at"))).collect())
+    }
+
+    async fn get_sample_counts(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket_seconds: i64,
+        rollup: Option<RollupResolution>,
+    ) -> Result<Vec<(DateTime<Utc>, i64)>, ApiError> {
+        if let Some(resolution) = rollup {
+            let sql = format!(
+                r#"
+                SELECT
+                    to_timestamp(floor(extract(epoch FROM bucket_start)::float8 / $4) * $4) AS bucket,
+                    SUM(cnt)::bigint AS cnt
+                FROM {}
+                WHERE norad_id = $1 AND bucket_start >= $2 AND bucket_start < $3
+                GROUP BY bucket
+                ORDER BY bucket
+                "#,
+                resolution.table()
+            );
+            let rows = sqlx::query(&sql)
+                .bind(norad_id)
+                .bind(from)
+                .bind(to)
+                .bind(bucket_seconds)
+                .fetch_all(&self.pool)
+                .await?;
+            return Ok(rows.into_iter().map(|r| (r.get("bucket"), r.get("cnt"))).collect());
+        }
+
         let rows = sqlx::query(
             r#"
-            SELECT 
-                date_trunc('hour', fetched_at) as hour,
-                AVG((payload->>'latitude')::float) as avg_lat,
-                AVG((payload->>'longitude')::float) as avg_lon,
-                AVG((payload->>'altitude')::float) as avg_altitude,
-                AVG((payload->>'velocity')::float) as avg_velocity,
-                COUNT(*) as cnt
+            SELECT
+                to_timestamp(floor(extract(epoch FROM fetched_at)::float8 / $4) * $4) AS bucket,
+                COUNT(*) AS cnt
             FROM iss_fetch_log
-            WHERE fetched_at >= NOW() - ($1 || ' hours')::interval
-            GROUP BY date_trunc('hour', fetched_at)
-            ORDER BY hour DESC
+            WHERE norad_id = $1 AND fetched_at >= $2 AND fetched_at < $3
+            GROUP BY bucket
+            ORDER BY bucket
             "#
         )
-        .bind(hours)
+        .bind(norad_id)
+        .bind(from)
+        .bind(to)
+        .bind(bucket_seconds)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| (r.get("bucket"), r.get("cnt"))).collect())
+    }
+
+    async fn get_raw_start(&self) -> Result<Option<DateTime<Utc>>, ApiError> {
+        let row = sqlx::query(r#"SELECT MIN(fetched_at) AS first_at FROM iss_fetch_log"#)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get("first_at"))
+    }
+
+    async fn has_rollups(&self) -> Result<bool, ApiError> {
+        let row = sqlx::query(r#"SELECT EXISTS (SELECT 1 FROM iss_rollup_minute) AS present"#)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get("present"))
+    }
+
+    async fn rebuild_rollup(
+        &self,
+        resolution: RollupResolution,
+        norad_id: Option<i64>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<u64, ApiError> {
+        let mut tx = self.pool.begin().await?;
+
+        let delete = format!(
+            r#"
+            DELETE FROM {}
+            WHERE bucket_start >= $1 AND bucket_start < $2
+              AND ($3::bigint IS NULL OR norad_id = $3)
+            "#,
+            resolution.table()
+        );
+        sqlx::query(&delete)
+            .bind(from)
+            .bind(to)
+            .bind(norad_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // Параллельная запись новой точки могла успеть создать строку после DELETE;
+        // её вклад уже виден в снимке INSERT ... SELECT, поэтому строка перезаписывается
+        let insert = format!(
+            r#"
+            INSERT INTO {table} ({columns})
+            {select}
+            ON CONFLICT (norad_id, bucket_start) DO UPDATE SET {replace}
+            "#,
+            table = resolution.table(),
+            columns = ROLLUP_COLUMNS,
+            replace = ROLLUP_REPLACE,
+            select = rollup_select(
+                resolution,
+                "fetched_at >= $1 AND fetched_at < $2 AND ($3::bigint IS NULL OR norad_id = $3)",
+            ),
+        );
+        let inserted = sqlx::query(&insert)
+            .bind(from)
+            .bind(to)
+            .bind(norad_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+        Ok(inserted)
+    }
+
+    async fn delete_raw_before(&self, cutoff: DateTime<Utc>) -> Result<u64, ApiError> {
+        let result = sqlx::query(r#"DELETE FROM iss_fetch_log WHERE fetched_at < $1"#)
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_rollups_before(
+        &self,
+        resolution: RollupResolution,
+        cutoff: DateTime<Utc>,
+    ) -> Result<u64, ApiError> {
+        let sql = format!(r#"DELETE FROM {} WHERE bucket_start < $1"#, resolution.table());
+        let result = sqlx::query(&sql)
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn get_unlocated(&self, after_id: i64, limit: i64) -> Result<Vec<IssFetchLog>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, norad_id, fetched_at, source_url, payload, provider, geo_kind, geo_code, geo_name
+            FROM iss_fetch_log
+            WHERE geo_kind IS NULL AND id > $1
+            ORDER BY id
+            LIMIT $2
+            "#
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(map_log).collect())
+    }
+
+    async fn set_location(
+        &self,
+        id: i64,
+        fetched_at: DateTime<Utc>,
+        location: &GeoLocation,
+    ) -> Result<(), ApiError> {
+        // fetched_at нужен для отсечения партиций
+        sqlx::query(
+            r#"
+            UPDATE iss_fetch_log
+            SET geo_kind = $3, geo_code = $4, geo_name = $5
+            WHERE id = $1 AND fetched_at = $2
+            "#
+        )
+        .bind(id)
+        .bind(fetched_at)
+        .bind(location.kind.as_str())
+        .bind(location.code.as_deref())
+        .bind(&location.name)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_dwell(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        max_step_seconds: f64,
+    ) -> Result<Vec<DwellTime>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                geo_kind, geo_code, geo_name,
+                SUM(step)::float8 AS seconds,
+                COUNT(*) AS samples,
+                MIN(fetched_at) AS first_seen,
+                MAX(fetched_at) AS last_seen
+            FROM (
+                SELECT
+                    geo_kind, geo_code, geo_name, fetched_at,
+                    LEAST(
+                        extract(epoch FROM
+                            COALESCE(LEAD(fetched_at) OVER (ORDER BY fetched_at, id), LEAST($3, NOW()))
+                            - fetched_at
+                        )::float8,
+                        $4
+                    ) AS step
+                FROM iss_fetch_log
+                WHERE norad_id = $1 AND fetched_at >= $2 AND fetched_at < $3
+            ) s
+            GROUP BY geo_kind, geo_code, geo_name
+            ORDER BY seconds DESC
+            "#
+        )
+        .bind(norad_id)
+        .bind(from)
+        .bind(to)
+        .bind(max_step_seconds)
         .fetch_all(&self.pool)
         .await?;
 
         Ok(rows.into_iter().map(|r| {
-            let hour: DateTime<Utc> = r.get("hour");
-            IssTrend {
-                hour: hour.format("%Y-%m-%d %H:00").to_string(),
-                avg_lat: r.get::<Option<f64>, _>("avg_lat").unwrap_or(0.0),
-                avg_lon: r.get::<Option<f64>, _>("avg_lon").unwrap_or(0.0),
-                avg_altitude: r.get::<Option<f64>, _>("avg_altitude").unwrap_or(0.0),
-                avg_velocity: r.get::<Option<f64>, _>("avg_velocity").unwrap_or(0.0),
-                cnt: r.get("cnt"),
+            let kind: Option<String> = r.get("geo_kind");
+            DwellTime {
+                kind: kind.as_deref().and_then(GeoKind::parse),
+                code: r.get("geo_code"),
+                name: r.get("geo_name"),
+                seconds: r.get::<Option<f64>, _>("seconds").unwrap_or(0.0).max(0.0),
+                share: 0.0,
+                samples: r.get("samples"),
+                first_seen: r.get("first_seen"),
+                last_seen: r.get("last_seen"),
             }
         }).collect())
     }
+
+    async fn list_satellites(&self) -> Result<Vec<Satellite>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT norad_id, name, source_url, interval_seconds, enabled, created_at, updated_at
+            FROM satellites
+            ORDER BY norad_id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(map_satellite).collect())
+    }
+
+    async fn get_satellite(&self, norad_id: i64) -> Result<Option<Satellite>, ApiError> {
+        let row = sqlx::query(
+            r#"
+            SELECT norad_id, name, source_url, interval_seconds, enabled, created_at, updated_at
+            FROM satellites
+            WHERE norad_id = $1
+            "#
+        )
+        .bind(norad_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(map_satellite))
+    }
+
+    async fn upsert_satellite(
+        &self,
+        norad_id: i64,
+        name: &str,
+        source_url: &str,
+        interval_seconds: i32,
+        enabled: bool,
+    ) -> Result<Satellite, ApiError> {
+        let row = sqlx::query(
+            r#"
+            INSERT INTO satellites (norad_id, name, source_url, interval_seconds, enabled)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (norad_id) DO UPDATE
+            SET name = EXCLUDED.name,
+                source_url = EXCLUDED.source_url,
+                interval_seconds = EXCLUDED.interval_seconds,
+                enabled = EXCLUDED.enabled
+            RETURNING norad_id, name, source_url, interval_seconds, enabled, created_at, updated_at
+            "#
+        )
+        .bind(norad_id)
+        .bind(name)
+        .bind(source_url)
+        .bind(interval_seconds)
+        .bind(enabled)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(map_satellite(&row))
+    }
+
+    async fn ensure_satellite(
+        &self,
+        norad_id: i64,
+        name: &str,
+        source_url: &str,
+        interval_seconds: i32,
+    ) -> Result<(), ApiError> {
+        sqlx::query(
+            r#"
+            INSERT INTO satellites (norad_id, name, source_url, interval_seconds)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (norad_id) DO NOTHING
+            "#
+        )
+        .bind(norad_id)
+        .bind(name)
+        .bind(source_url)
+        .bind(interval_seconds)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_satellite(&self, norad_id: i64) -> Result<bool, ApiError> {
+        let result = sqlx::query(r#"DELETE FROM satellites WHERE norad_id = $1"#)
+            .bind(norad_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+fn map_log(r: &PgRow) -> IssFetchLog {
+    let kind: Option<String> = r.get("geo_kind");
+    let location = kind.as_deref().and_then(GeoKind::parse).map(|kind| GeoLocation {
+        kind,
+        code: r.get("geo_code"),
+        name: r.get::<Option<String>, _>("geo_name").unwrap_or_default(),
+    });
+
+    IssFetchLog {
+        id: r.get("id"),
+        norad_id: r.get("norad_id"),
+        fetched_at: r.get("fetched_at"),
+        source_url: r.get("source_url"),
+        payload: r.get("payload"),
+        provider: r.get("provider"),
+        location,
+    }
+}
+
+fn map_satellite(r: &PgRow) -> Satellite {
+    Satellite {
+        norad_id: r.get("norad_id"),
+        name: r.get("name"),
+        source_url: r.get("source_url"),
+        interval_seconds: r.get("interval_seconds"),
+        enabled: r.get("enabled"),
+        created_at: r.get("created_at"),
+        updated_at: r.get("updated_at"),
+    }
+}
+
+fn map_trend(r: &PgRow) -> IssTrend {
+    IssTrend {
+        bucket_start: r.get("bucket"),
+        cnt: r.get("cnt"),
+        latitude: map_stats(r, "lat"),
+        longitude: map_stats(r, "lon"),
+        altitude: map_stats(r, "alt"),
+        velocity: map_stats(r, "vel"),
+        coverage: None,
+    }
+}
+
+fn map_stats(r: &PgRow, prefix: &str) -> MetricStats {
+    MetricStats {
+        avg: r.get(format!("{}_avg", prefix).as_str()),
+        min: r.get(format!("{}_min", prefix).as_str()),
+        max: r.get(format!("{}_max", prefix).as_str()),
+        stddev: r.get(format!("{}_std", prefix).as_str()),
+    }
 }
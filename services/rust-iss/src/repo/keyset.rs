@@ -0,0 +1,24 @@
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::domain::pagination::{key_expr, Cursor};
+
+/// ` AND (ключ, id) < курсор` — или `>`, если читаем по возрастанию.
+/// `null_as` должен совпадать с тем, что использован в ORDER BY
+pub(crate) fn push_after(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    column: &str,
+    null_as: Option<&str>,
+    cursor: &Cursor,
+) {
+    let op = if cursor.scan_descending() { "<" } else { ">" };
+    qb.push(format!(" AND ({}, id) {} (", key_expr(column, null_as), op));
+    match null_as {
+        Some(fill) => {
+            qb.push("COALESCE(").push_bind(cursor.at).push(format!("::timestamptz, {})", fill));
+        }
+        None => {
+            qb.push_bind(cursor.at);
+        }
+    }
+    qb.push(", ").push_bind(cursor.id).push(")");
+}
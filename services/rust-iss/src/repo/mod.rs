@@ -1,7 +1,14 @@
+pub mod anomaly_repo;
+pub mod geofence_repo;
 pub mod iss_repo;
+mod keyset;
 pub mod osdr_repo;
 pub mod cache_repo;
+pub mod tle_repo;
 
+pub use anomaly_repo::{AnomalyRepository, PgAnomalyRepo};
+pub use geofence_repo::{GeofenceRepository, PgGeofenceRepo};
 pub use iss_repo::{IssRepository, PgIssRepo};
 pub use osdr_repo::{OsdrRepository, PgOsdrRepo};
 pub use cache_repo::{CacheRepository, PgCacheRepo};
+pub use tle_repo::{TleRepository, PgTleRepo};
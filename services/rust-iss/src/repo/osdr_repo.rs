@@ -1,26 +1,96 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use serde_json::Value;
-use sqlx::{PgPool, Row};
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde_json::{json, Map, Value};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row, Transaction};
+use tokio::sync::mpsc;
 
-use crate::domain::OsdrItem;
+use crate::domain::osdr::{
+    build_tsquery, OsdrFacet, OsdrFields, OsdrFilter, OsdrSort, OsdrSortField, SEARCH_CONFIG,
+};
+use crate::domain::osdr_enrichment::OsdrEnrichment;
+use crate::domain::pagination::{key_order, Cursor, Page};
+use crate::domain::{
+    FacetCount, OsdrAssay, OsdrFacets, OsdrFile, OsdrItem, OsdrItemVersion, OsdrRelated, OsdrSearchHit,
+    OsdrSyncMode, OsdrSyncReport, OsdrSyncState, OsdrSyncStatus, OsdrWriteOutcome,
+};
 use crate::errors::ApiError;
+use crate::repo::keyset::push_after;
 
 #[async_trait]
 pub trait OsdrRepository: Send + Sync {
-    async fn upsert(
+    /// Пишет страницу наборов в одной транзакции; новая версия — только если
+    /// нормализованные поля или raw изменились. Ошибка записи откатывает
+    /// лишь свою запись (savepoint) и возвращается на её месте.
+    async fn upsert_page(
         &self,
-        dataset_id: Option<String>,
-        title: Option<String>,
-        organism: Option<String>,
-        study_type: Option<String>,
-        status: Option<String>,
-        updated_at: Option<DateTime<Utc>>,
-        raw: Value,
-    ) -> Result<i64, ApiError>;
+        items: Vec<OsdrFields>,
+        seen_at: DateTime<Utc>,
+    ) -> Result<Vec<Result<OsdrWriteOutcome, String>>, ApiError>;
     
-    async fn list(&self, limit: i32, offset: i32, search: Option<String>) -> Result<Vec<OsdrItem>, ApiError>;
-    async fn count(&self) -> Result<i64, ApiError>;
+    /// Страница списка: после `cursor`, если он задан, иначе с `offset`.
+    /// Курсоры выдаются только при сортировке по updated_at; `total` не заполняется
+    async fn list(
+        &self,
+        filter: &OsdrFilter,
+        sort: OsdrSort,
+        cursor: Option<&Cursor>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Page<OsdrItem>, ApiError>;
+    async fn count(&self, filter: &OsdrFilter) -> Result<i64, ApiError>;
+    /// Все наборы по фильтру в порядке `sort`, одним запросом; строки читаются
+    /// по мере того, как потребитель забирает их из потока
+    fn stream(&self, filter: &OsdrFilter, sort: OsdrSort) -> BoxStream<'static, Result<OsdrItem, ApiError>>;
+    /// Фасеты по каждому измерению с учётом остальных фильтров, но не своего
+    async fn facets(&self, filter: &OsdrFilter, limit: i64) -> Result<OsdrFacets, ApiError>;
+    async fn get_by_dataset_id(&self, dataset_id: &str) -> Result<Option<OsdrItem>, ApiError>;
+    /// Наборы из `referenced`, затем до `limit` похожих по организму и типу исследования
+    async fn related(&self, item: &OsdrItem, referenced: &[String], limit: i64) -> Result<Vec<OsdrRelated>, ApiError>;
+
+    /// Водяной знак инкрементальной синхронизации и время последней полной для источника
+    async fn get_sync_state(&self, source: &str) -> Result<OsdrSyncState, ApiError>;
+    async fn save_watermark(
+        &self,
+        source: &str,
+        watermark: Option<DateTime<Utc>>,
+        full: bool,
+    ) -> Result<(), ApiError>;
+
+    /// Итог полной синхронизации, начатой в `started_at`: не увиденным в ней наборам
+    /// (кроме `unwritten` — были в выдаче, но не записались) добавляется пропуск,
+    /// набравшие `threshold` пропусков подряд помечаются отозванными.
+    /// Возвращает dataset_id отозванных.
+    async fn mark_unseen(
+        &self,
+        started_at: DateTime<Utc>,
+        unwritten: &[String],
+        threshold: u32,
+    ) -> Result<Vec<String>, ApiError>;
+
+    /// Наборы для обогащения: ещё не читались, изменились upstream или
+    /// прошлое чтение упало больше суток назад; давно читанные первыми
+    async fn list_enrichment_due(&self, limit: i64) -> Result<Vec<OsdrItem>, ApiError>;
+    /// Заменяет карточку, ассаи и файлы набора
+    async fn save_enrichment(&self, item: &OsdrItem, enrichment: &OsdrEnrichment) -> Result<(), ApiError>;
+    /// Запоминает неудачное чтение; прежние ассаи и файлы остаются
+    async fn save_enrichment_error(&self, item: &OsdrItem, error: &str) -> Result<(), ApiError>;
+    async fn list_assays(&self, item_id: i64) -> Result<Vec<OsdrAssay>, ApiError>;
+    async fn list_files(&self, item_id: i64) -> Result<Vec<OsdrFile>, ApiError>;
+
+    /// Записи, сохранённые до появления синтетических идентификаторов
+    async fn list_unidentified(&self) -> Result<Vec<OsdrItem>, ApiError>;
+    /// Сливает строки `ids` (и уже существующую строку с `dataset_id`, если есть)
+    /// в одну с этим dataset_id; возвращает id оставшейся строки
+    async fn merge_duplicates(&self, dataset_id: &str, ids: &[i64]) -> Result<i64, ApiError>;
+
+    async fn save_sync_report(&self, report: &OsdrSyncReport) -> Result<i64, ApiError>;
+    /// Последние отчёты синхронизации, новые первыми
+    async fn list_sync_reports(&self, limit: i64) -> Result<Vec<OsdrSyncReport>, ApiError>;
+
+    /// Версии набора, новые первыми
+    async fn history(&self, dataset_id: &str) -> Result<Vec<OsdrItemVersion>, ApiError>;
 }
 
 pub struct PgOsdrRepo {
@@ -35,93 +105,867 @@ impl PgOsdrRepo {
 
 #[async_trait]
 impl OsdrRepository for PgOsdrRepo {
-    async fn upsert(
+    async fn upsert_page(
+        &self,
+        items: Vec<OsdrFields>,
+        seen_at: DateTime<Utc>,
+    ) -> Result<Vec<Result<OsdrWriteOutcome, String>>, ApiError> {
+        let mut tx = self.pool.begin().await?;
+        let mut outcomes = Vec::with_capacity(items.len());
+
+        for fields in items {
+            sqlx::query("SAVEPOINT osdr_item").execute(&mut *tx).await?;
+            match upsert_item(&mut tx, &fields, seen_at).await {
+                Ok(outcome) => {
+                    sqlx::query("RELEASE SAVEPOINT osdr_item").execute(&mut *tx).await?;
+                    outcomes.push(Ok(outcome));
+                }
+                Err(e) => {
+                    sqlx::query("ROLLBACK TO SAVEPOINT osdr_item").execute(&mut *tx).await?;
+                    outcomes.push(Err(e.message));
+                }
+            }
+        }
+
+        tx.commit().await?;
+        Ok(outcomes)
+    }
+
+    fn stream(&self, filter: &OsdrFilter, sort: OsdrSort) -> BoxStream<'static, Result<OsdrItem, ApiError>> {
+        let pool = self.pool.clone();
+        let filter = filter.clone();
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+
+        tokio::spawn(async move {
+            let tsquery = filter.search.as_deref().and_then(build_tsquery);
+            let sort = effective_sort(sort, &tsquery);
+            let mut qb = QueryBuilder::<Postgres>::new(ITEM_COLUMNS);
+            if let Some(tsquery) = &tsquery {
+                qb.push(", ts_rank_cd(search_vector, to_tsquery(")
+                    .push_bind(SEARCH_CONFIG)
+                    .push("::regconfig, ")
+                    .push_bind(tsquery.clone())
+                    .push(")) AS rank");
+            }
+            qb.push(" FROM osdr_items");
+            push_filter(&mut qb, &filter, None);
+            qb.push(" ORDER BY ").push(sort.order_by());
+
+            let mut rows = qb.build().fetch(&pool);
+            while let Some(row) = rows.next().await {
+                let item = row.map(|r| map_item(&r)).map_err(ApiError::from);
+                let failed = item.is_err();
+                // Потребитель ушёл (клиент оборвал загрузку) или запрос упал
+                if tx.send(item).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|item| (item, rx)) }).boxed()
+    }
+
+    async fn list(
         &self,
-        dataset_id: Option<String>,
-        title: Option<String>,
-        organism: Option<String>,
-        study_type: Option<String>,
-        status: Option<String>,
-        updated_at: Option<DateTime<Utc>>,
-        raw: Value,
-    ) -> Result<i64, ApiError> {
+        filter: &OsdrFilter,
+        sort: OsdrSort,
+        cursor: Option<&Cursor>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Page<OsdrItem>, ApiError> {
+        let tsquery = filter.search.as_deref().and_then(build_tsquery);
+        let sort = effective_sort(sort, &tsquery);
+        let null_as = sort.keyset_null();
+        if cursor.is_some() && null_as.is_none() {
+            return Err(ApiError::validation("Cursor pagination requires sort by updated_at"));
+        }
+        // К предыдущей странице читаем в обратном порядке, Page развернёт строки
+        let order = match cursor {
+            Some(cursor) => key_order("updated_at", null_as, cursor.scan_descending()),
+            None => sort.order_by(),
+        };
+
+        let mut qb = QueryBuilder::<Postgres>::new("");
+        if let Some(tsquery) = &tsquery {
+            // Подсветка считается только для строк страницы, поэтому во внешнем запросе
+            qb.push("SELECT p.*, ts_headline(")
+                .push_bind(SEARCH_CONFIG)
+                .push("::regconfig, COALESCE(p.title, ''), to_tsquery(")
+                .push_bind(SEARCH_CONFIG)
+                .push("::regconfig, ")
+                .push_bind(tsquery.clone())
+                .push(format!("), '{HEADLINE_TITLE}') AS title_highlight, ts_headline("))
+                .push_bind(SEARCH_CONFIG)
+                .push("::regconfig, ")
+                .push(SNIPPET_SOURCE)
+                .push(", to_tsquery(")
+                .push_bind(SEARCH_CONFIG)
+                .push("::regconfig, ")
+                .push_bind(tsquery.clone())
+                .push(format!("), '{HEADLINE_SNIPPET}') AS snippet FROM ("))
+                .push(ITEM_COLUMNS)
+                .push(", ts_rank_cd(search_vector, to_tsquery(")
+                .push_bind(SEARCH_CONFIG)
+                .push("::regconfig, ")
+                .push_bind(tsquery.clone())
+                .push(")) AS rank FROM osdr_items");
+        } else {
+            qb.push(ITEM_COLUMNS).push(" FROM osdr_items");
+        }
+        push_filter(&mut qb, filter, None);
+        if let Some(cursor) = cursor {
+            push_after(&mut qb, "updated_at", null_as, cursor);
+        }
+        // Лишняя строка показывает, есть ли следующая страница
+        qb.push(" ORDER BY ")
+            .push(&order)
+            .push(" LIMIT ")
+            .push_bind(limit + 1);
+        if cursor.is_none() {
+            qb.push(" OFFSET ").push_bind(offset);
+        }
+        if tsquery.is_some() {
+            qb.push(") p ORDER BY ").push(&order);
+        }
+
+        let rows = qb.build().fetch_all(&self.pool).await?;
+        let mut items: Vec<OsdrItem> = rows
+            .iter()
+            .map(|r| {
+                let mut item = map_item(r);
+                if tsquery.is_some() {
+                    item.search = Some(OsdrSearchHit {
+                        rank: r.get("rank"),
+                        title: r.get::<Option<String>, _>("title_highlight").filter(|s| !s.is_empty()),
+                        snippet: r.get::<Option<String>, _>("snippet").filter(|s| !s.is_empty()),
+                    });
+                }
+                item
+            })
+            .collect();
+
+        if null_as.is_none() {
+            // Для остальных сортировок — только offset
+            items.truncate(limit as usize);
+            return Ok(Page {
+                items,
+                next_cursor: None,
+                prev_cursor: None,
+                total: None,
+            });
+        }
+        Ok(Page::from_rows(items, limit as usize, cursor, sort.descending, offset > 0, |i| {
+            (i.updated_at, i.id)
+        }))
+    }
+
+    async fn count(&self, filter: &OsdrFilter) -> Result<i64, ApiError> {
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT COUNT(*) AS count FROM osdr_items");
+        push_filter(&mut qb, filter, None);
+
+        let row = qb.build().fetch_one(&self.pool).await?;
+        Ok(row.get::<i64, _>("count"))
+    }
+
+    async fn facets(&self, filter: &OsdrFilter, limit: i64) -> Result<OsdrFacets, ApiError> {
+        let mut facets = OsdrFacets::default();
+        for facet in OsdrFacet::ALL {
+            let column = facet.column();
+            let mut qb = QueryBuilder::<Postgres>::new(format!(
+                "SELECT {column} AS value, COUNT(*) AS count FROM osdr_items"
+            ));
+            push_filter(&mut qb, filter, Some(facet));
+            qb.push(format!(" AND {column} IS NOT NULL GROUP BY {column} ORDER BY count DESC, value LIMIT "))
+                .push_bind(limit);
+
+            let counts = qb
+                .build()
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .map(|r| FacetCount {
+                    value: r.get("value"),
+                    count: r.get("count"),
+                })
+                .collect();
+            match facet {
+                OsdrFacet::Organism => facets.organism = counts,
+                OsdrFacet::StudyType => facets.study_type = counts,
+                OsdrFacet::Status => facets.status = counts,
+            }
+        }
+        Ok(facets)
+    }
+
+    async fn get_by_dataset_id(&self, dataset_id: &str) -> Result<Option<OsdrItem>, ApiError> {
         let row = sqlx::query(
             r#"
-            INSERT INTO osdr_items (dataset_id, title, organism, study_type, status, updated_at, raw)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
-            ON CONFLICT (dataset_id) DO UPDATE
-            SET title = EXCLUDED.title,
-                organism = EXCLUDED.organism,
-                study_type = EXCLUDED.study_type,
-                status = EXCLUDED.status,
-                updated_at = EXCLUDED.updated_at,
-                raw = EXCLUDED.raw
-            RETURNING id
+            SELECT id, dataset_id, title, organism, study_type, status, updated_at, inserted_at, raw,
+                   last_seen_at, withdrawn_at, withdrawn_reason
+            FROM osdr_items
+            WHERE dataset_id = $1
             "#
         )
-        .bind(&dataset_id)
-        .bind(&title)
-        .bind(&organism)
-        .bind(&study_type)
-        .bind(&status)
-        .bind(&updated_at)
-        .bind(&raw)
-        .fetch_one(&self.pool)
+        .bind(dataset_id)
+        .fetch_optional(&self.pool)
         .await?;
 
-        Ok(row.get("id"))
+        Ok(row.as_ref().map(map_item))
     }
 
-    async fn list(&self, limit: i32, offset: i32, search: Option<String>) -> Result<Vec<OsdrItem>, ApiError> {
-        let rows = if let Some(s) = search {
-            let pattern = format!("%{}%", s);
-            sqlx::query(
-                r#"
-                SELECT id, dataset_id, title, organism, study_type, status, updated_at, inserted_at, raw
+    async fn related(&self, item: &OsdrItem, referenced: &[String], limit: i64) -> Result<Vec<OsdrRelated>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT dataset_id, title, 'referenced' AS relation
+            FROM osdr_items
+            WHERE dataset_id = ANY($2) AND id <> $1
+            UNION ALL
+            (
+                SELECT dataset_id, title, 'similar' AS relation
                 FROM osdr_items
-                WHERE title ILIKE $1 OR organism ILIKE $1 OR dataset_id ILIKE $1
+                WHERE organism = $3 AND study_type = $4 AND id <> $1
+                  AND dataset_id IS NOT NULL AND NOT (dataset_id = ANY($2))
                 ORDER BY updated_at DESC NULLS LAST
-                LIMIT $2 OFFSET $3
-                "#
+                LIMIT $5
             )
-            .bind(&pattern)
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(&self.pool)
-            .await?
-        } else {
+            "#
+        )
+        .bind(item.id)
+        .bind(referenced)
+        .bind(&item.organism)
+        .bind(&item.study_type)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| OsdrRelated {
+                dataset_id: r.get("dataset_id"),
+                title: r.get("title"),
+                relation: r.get("relation"),
+            })
+            .collect())
+    }
+
+    async fn get_sync_state(&self, source: &str) -> Result<OsdrSyncState, ApiError> {
+        let row = sqlx::query(r#"SELECT watermark, last_full_sync_at FROM osdr_sync_state WHERE source = $1"#)
+            .bind(source)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row
+            .map(|r| OsdrSyncState {
+                watermark: r.get("watermark"),
+                last_full_sync_at: r.get("last_full_sync_at"),
+            })
+            .unwrap_or_default())
+    }
+
+    async fn save_watermark(
+        &self,
+        source: &str,
+        watermark: Option<DateTime<Utc>>,
+        full: bool,
+    ) -> Result<(), ApiError> {
+        sqlx::query(
+            r#"
+            INSERT INTO osdr_sync_state (source, watermark, last_full_sync_at, updated_at)
+            VALUES ($1, $2, CASE WHEN $3 THEN NOW() END, NOW())
+            ON CONFLICT (source) DO UPDATE
+            SET watermark = GREATEST(osdr_sync_state.watermark, EXCLUDED.watermark),
+                last_full_sync_at = COALESCE(EXCLUDED.last_full_sync_at, osdr_sync_state.last_full_sync_at),
+                updated_at = NOW()
+            "#
+        )
+        .bind(source)
+        .bind(watermark)
+        .bind(full)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn history(&self, dataset_id: &str) -> Result<Vec<OsdrItemVersion>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT v.version, v.changed_at, v.diff
+            FROM osdr_item_versions v
+            JOIN osdr_items i ON i.id = v.item_id
+            WHERE i.dataset_id = $1
+            ORDER BY v.version DESC
+            "#
+        )
+        .bind(dataset_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| OsdrItemVersion {
+                version: r.get("version"),
+                changed_at: r.get("changed_at"),
+                diff: r.get("diff"),
+            })
+            .collect())
+    }
+
+    async fn mark_unseen(
+        &self,
+        started_at: DateTime<Utc>,
+        unwritten: &[String],
+        threshold: u32,
+    ) -> Result<Vec<String>, ApiError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE osdr_items
+            SET missed_syncs = missed_syncs + 1
+            WHERE withdrawn_at IS NULL
+              AND (last_seen_at IS NULL OR last_seen_at < $1)
+              AND dataset_id <> ALL($2)
+            "#
+        )
+        .bind(started_at)
+        .bind(unwritten)
+        .execute(&mut *tx)
+        .await?;
+
+        let rows = sqlx::query(
+            r#"
+            WITH withdrawn AS (
+                UPDATE osdr_items
+                SET withdrawn_at = NOW(),
+                    withdrawn_reason = format(
+                        'Not seen upstream in %s consecutive full syncs (last seen %s)',
+                        missed_syncs,
+                        COALESCE(to_char(last_seen_at AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS"Z"'), 'never')
+                    )
+                WHERE withdrawn_at IS NULL AND missed_syncs >= $1
+                RETURNING id, dataset_id, withdrawn_at, withdrawn_reason
+            ),
+            versions AS (
+                INSERT INTO osdr_item_versions (item_id, version, diff)
+                SELECT w.id,
+                       COALESCE((SELECT MAX(v.version) FROM osdr_item_versions v WHERE v.item_id = w.id), 0) + 1,
+                       jsonb_build_object(
+                           'withdrawn_at', jsonb_build_object('old', NULL, 'new', w.withdrawn_at),
+                           'withdrawn_reason', jsonb_build_object('old', NULL, 'new', w.withdrawn_reason)
+                       )
+                FROM withdrawn w
+            )
+            SELECT dataset_id FROM withdrawn ORDER BY dataset_id
+            "#
+        )
+        .bind(threshold as i32)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(rows.iter().map(|r| r.get("dataset_id")).collect())
+    }
+
+    async fn list_enrichment_due(&self, limit: i64) -> Result<Vec<OsdrItem>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT i.id, i.dataset_id, i.title, i.organism, i.study_type, i.status, i.updated_at,
+                   i.inserted_at, i.raw, i.last_seen_at, i.withdrawn_at, i.withdrawn_reason
+            FROM osdr_items i
+            LEFT JOIN osdr_item_details d ON d.item_id = i.id
+            WHERE i.withdrawn_at IS NULL
+              AND i.dataset_id IS NOT NULL
+              AND (
+                  d.item_id IS NULL
+                  OR d.source_updated_at IS DISTINCT FROM i.updated_at
+                  OR (d.error IS NOT NULL AND d.fetched_at < NOW() - INTERVAL '1 day')
+              )
+            ORDER BY d.fetched_at NULLS FIRST, i.id
+            LIMIT $1
+            "#
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(map_item).collect())
+    }
+
+    async fn save_enrichment(&self, item: &OsdrItem, enrichment: &OsdrEnrichment) -> Result<(), ApiError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO osdr_item_details (item_id, source_updated_at, fetched_at, detail, error)
+            VALUES ($1, $2, NOW(), $3, NULL)
+            ON CONFLICT (item_id) DO UPDATE
+            SET source_updated_at = EXCLUDED.source_updated_at,
+                fetched_at = EXCLUDED.fetched_at,
+                detail = EXCLUDED.detail,
+                error = NULL
+            "#
+        )
+        .bind(item.id)
+        .bind(item.updated_at)
+        .bind(&enrichment.detail)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM osdr_assays WHERE item_id = $1")
+            .bind(item.id)
+            .execute(&mut *tx)
+            .await?;
+        if !enrichment.assays.is_empty() {
+            let mut qb = QueryBuilder::<Postgres>::new(
+                "INSERT INTO osdr_assays (item_id, name, measurement_type, technology_type, platform, raw) ",
+            );
+            qb.push_values(&enrichment.assays, |mut b, assay| {
+                b.push_bind(item.id)
+                    .push_bind(&assay.name)
+                    .push_bind(&assay.measurement_type)
+                    .push_bind(&assay.technology_type)
+                    .push_bind(&assay.platform)
+                    .push_bind(&assay.raw);
+            });
+            qb.build().execute(&mut *tx).await?;
+        }
+
+        sqlx::query("DELETE FROM osdr_files WHERE item_id = $1")
+            .bind(item.id)
+            .execute(&mut *tx)
+            .await?;
+        // Postgres ограничивает число параметров запроса, у больших наборов тысячи файлов
+        for chunk in enrichment.files.chunks(FILE_INSERT_CHUNK) {
+            let mut qb = QueryBuilder::<Postgres>::new(
+                "INSERT INTO osdr_files (item_id, file_name, category, subcategory, size_bytes, url, raw) ",
+            );
+            qb.push_values(chunk, |mut b, file| {
+                b.push_bind(item.id)
+                    .push_bind(&file.file_name)
+                    .push_bind(&file.category)
+                    .push_bind(&file.subcategory)
+                    .push_bind(file.size_bytes)
+                    .push_bind(&file.url)
+                    .push_bind(&file.raw);
+            });
+            qb.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn save_enrichment_error(&self, item: &OsdrItem, error: &str) -> Result<(), ApiError> {
+        sqlx::query(
+            r#"
+            INSERT INTO osdr_item_details (item_id, source_updated_at, fetched_at, error)
+            VALUES ($1, $2, NOW(), $3)
+            ON CONFLICT (item_id) DO UPDATE
+            SET fetched_at = EXCLUDED.fetched_at,
+                error = EXCLUDED.error
+            "#
+        )
+        .bind(item.id)
+        .bind(item.updated_at)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_assays(&self, item_id: i64) -> Result<Vec<OsdrAssay>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT name, measurement_type, technology_type, platform, raw
+            FROM osdr_assays
+            WHERE item_id = $1
+            ORDER BY id
+            "#
+        )
+        .bind(item_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| OsdrAssay {
+                name: r.get("name"),
+                measurement_type: r.get("measurement_type"),
+                technology_type: r.get("technology_type"),
+                platform: r.get("platform"),
+                raw: r.get("raw"),
+            })
+            .collect())
+    }
+
+    async fn list_files(&self, item_id: i64) -> Result<Vec<OsdrFile>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT file_name, category, subcategory, size_bytes, url, raw
+            FROM osdr_files
+            WHERE item_id = $1
+            ORDER BY file_name, id
+            "#
+        )
+        .bind(item_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| OsdrFile {
+                file_name: r.get("file_name"),
+                category: r.get("category"),
+                subcategory: r.get("subcategory"),
+                size_bytes: r.get("size_bytes"),
+                url: r.get("url"),
+                raw: r.get("raw"),
+            })
+            .collect())
+    }
+
+    async fn list_unidentified(&self) -> Result<Vec<OsdrItem>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, dataset_id, title, organism, study_type, status, updated_at, inserted_at, raw,
+                   last_seen_at, withdrawn_at, withdrawn_reason
+            FROM osdr_items
+            WHERE dataset_id IS NULL
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(map_item).collect())
+    }
+
+    async fn merge_duplicates(&self, dataset_id: &str, ids: &[i64]) -> Result<i64, ApiError> {
+        let mut tx = self.pool.begin().await?;
+
+        // Строка с этим идентификатором уже могла появиться при новой синхронизации —
+        // тогда остаётся она, иначе самая свежая из дублей
+        let row = sqlx::query(
+            r#"
+            SELECT id, dataset_id FROM osdr_items
+            WHERE dataset_id = $1 OR id = ANY($2)
+            ORDER BY (dataset_id = $1) DESC NULLS LAST, updated_at DESC NULLS LAST, id DESC
+            LIMIT 1
+            FOR UPDATE
+            "#
+        )
+        .bind(dataset_id)
+        .bind(ids)
+        .fetch_one(&mut *tx)
+        .await?;
+        let survivor: i64 = row.get("id");
+        let previous_id: Option<String> = row.get("dataset_id");
+        let losers: Vec<i64> = ids.iter().copied().filter(|id| *id != survivor).collect();
+
+        sqlx::query(
+            r#"
+            UPDATE osdr_items
+            SET dataset_id = $2,
+                inserted_at = LEAST(inserted_at, (SELECT MIN(inserted_at) FROM osdr_items WHERE id = ANY($3)))
+            WHERE id = $1
+            "#
+        )
+        .bind(survivor)
+        .bind(dataset_id)
+        .bind(&losers)
+        .execute(&mut *tx)
+        .await?;
+
+        let mut diff = Map::new();
+        if previous_id.as_deref() != Some(dataset_id) {
+            diff.insert("dataset_id".to_string(), json!({"old": previous_id, "new": dataset_id}));
+        }
+        if !losers.is_empty() {
+            diff.insert("merged_from".to_string(), json!({"old": losers, "new": survivor}));
+        }
+        if !diff.is_empty() {
             sqlx::query(
                 r#"
-                SELECT id, dataset_id, title, organism, study_type, status, updated_at, inserted_at, raw
-                FROM osdr_items
-                ORDER BY updated_at DESC NULLS LAST
-                LIMIT $1 OFFSET $2
+                INSERT INTO osdr_item_versions (item_id, version, diff)
+                SELECT $1, COALESCE(MAX(version), 0) + 1, $2
+                FROM osdr_item_versions
+                WHERE item_id = $1
                 "#
             )
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(&self.pool)
-            .await?
-        };
-
-        Ok(rows.into_iter().map(|r| OsdrItem {
-            id: r.get("id"),
-            dataset_id: r.get("dataset_id"),
-            title: r.get("title"),
-            organism: r.get("organism"),
-            study_type: r.get("study_type"),
-            status: r.get("status"),
-            updated_at: r.get("updated_at"),
-            inserted_at: r.get("inserted_at"),
-            raw: r.get("raw"),
-        }).collect())
-    }
-
-    async fn count(&self) -> Result<i64, ApiError> {
-        let row = sqlx::query(r#"SELECT COUNT(*) as count FROM osdr_items"#)
-            .fetch_one(&self.pool)
+            .bind(survivor)
+            .bind(Value::Object(diff))
+            .execute(&mut *tx)
             .await?;
+        }
 
-        Ok(row.get::<i64, _>("count"))
+        if !losers.is_empty() {
+            sqlx::query("DELETE FROM osdr_items WHERE id = ANY($1)")
+                .bind(&losers)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(survivor)
+    }
+
+    async fn save_sync_report(&self, report: &OsdrSyncReport) -> Result<i64, ApiError> {
+        let row = sqlx::query(
+            r#"
+            INSERT INTO osdr_sync_reports (
+                mode, status, started_at, finished_at, pages, items_seen,
+                inserted, updated, unchanged, skipped, failed, truncated, withdrawn,
+                watermark_before, watermark_after, error, errors, enrichment
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+            RETURNING id
+            "#
+        )
+        .bind(report.mode.as_str())
+        .bind(report.status.as_str())
+        .bind(report.started_at)
+        .bind(report.finished_at)
+        .bind(report.pages as i32)
+        .bind(report.items_seen as i64)
+        .bind(report.inserted as i64)
+        .bind(report.updated as i64)
+        .bind(report.unchanged as i64)
+        .bind(report.skipped as i64)
+        .bind(report.failed as i64)
+        .bind(report.truncated)
+        .bind(report.withdrawn as i64)
+        .bind(report.watermark_before)
+        .bind(report.watermark_after)
+        .bind(&report.error)
+        .bind(json!(report.errors))
+        .bind(report.enrichment.as_ref().map(|e| json!(e)))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.get("id"))
+    }
+
+    async fn list_sync_reports(&self, limit: i64) -> Result<Vec<OsdrSyncReport>, ApiError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, mode, status, started_at, finished_at, pages, items_seen,
+                   inserted, updated, unchanged, skipped, failed, truncated, withdrawn,
+                   watermark_before, watermark_after, error, errors, enrichment
+            FROM osdr_sync_reports
+            ORDER BY started_at DESC
+            LIMIT $1
+            "#
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| OsdrSyncReport {
+                id: Some(r.get("id")),
+                mode: OsdrSyncMode::parse(r.get("mode")).unwrap_or(OsdrSyncMode::Full),
+                status: OsdrSyncStatus::parse(r.get("status")).unwrap_or(OsdrSyncStatus::Failed),
+                started_at: r.get("started_at"),
+                finished_at: r.get("finished_at"),
+                pages: r.get::<i32, _>("pages") as u32,
+                items_seen: r.get::<i64, _>("items_seen") as u64,
+                inserted: r.get::<i64, _>("inserted") as u64,
+                updated: r.get::<i64, _>("updated") as u64,
+                unchanged: r.get::<i64, _>("unchanged") as u64,
+                skipped: r.get::<i64, _>("skipped") as u64,
+                failed: r.get::<i64, _>("failed") as u64,
+                truncated: r.get("truncated"),
+                withdrawn: r.get::<i64, _>("withdrawn") as u64,
+                watermark_before: r.get("watermark_before"),
+                watermark_after: r.get("watermark_after"),
+                error: r.get("error"),
+                errors: serde_json::from_value(r.get("errors")).unwrap_or_default(),
+                enrichment: r
+                    .get::<Option<Value>, _>("enrichment")
+                    .and_then(|v| serde_json::from_value(v).ok()),
+            })
+            .collect())
+    }
+}
+
+/// Сколько строк выгрузки может ждать потребителя
+const STREAM_BUFFER: usize = 256;
+
+/// Без поискового запроса сортировать по релевантности нечем
+fn effective_sort(sort: OsdrSort, tsquery: &Option<String>) -> OsdrSort {
+    match (sort.field, tsquery) {
+        (OsdrSortField::Relevance, None) => OsdrSort::default(),
+        _ => sort,
+    }
+}
+
+/// Строк файлов на один INSERT (7 параметров на строку, предел Postgres — 65535)
+const FILE_INSERT_CHUNK: usize = 1000;
+
+const ITEM_COLUMNS: &str = "SELECT id, dataset_id, title, organism, study_type, status, updated_at, inserted_at, raw, \
+     last_seen_at, withdrawn_at, withdrawn_reason";
+
+/// Откуда берётся фрагмент с подсветкой: описание набора из raw
+const SNIPPET_SOURCE: &str =
+    "COALESCE(p.raw->>'description', p.raw->>'summary', p.raw->>'abstract', '')";
+
+const HEADLINE_TITLE: &str = "StartSel=<mark>, StopSel=</mark>, HighlightAll=true";
+const HEADLINE_SNIPPET: &str = "StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MaxWords=30, MinWords=10";
+
+/// WHERE по фильтру; `skip` — измерение, для которого сейчас считаются фасеты.
+/// Списки значений сравниваются через `= ANY`, чтобы работали индексы по колонкам.
+fn push_filter(qb: &mut QueryBuilder<'_, Postgres>, filter: &OsdrFilter, skip: Option<OsdrFacet>) {
+    qb.push(" WHERE TRUE");
+
+    if !filter.include_withdrawn {
+        qb.push(" AND withdrawn_at IS NULL");
+    }
+
+    if let Some(tsquery) = filter.search.as_deref().and_then(build_tsquery) {
+        qb.push(" AND search_vector @@ to_tsquery(")
+            .push_bind(SEARCH_CONFIG)
+            .push("::regconfig, ")
+            .push_bind(tsquery)
+            .push(")");
+    }
+
+    for (facet, values) in [
+        (OsdrFacet::Organism, &filter.organisms),
+        (OsdrFacet::StudyType, &filter.study_types),
+        (OsdrFacet::Status, &filter.statuses),
+    ] {
+        if skip != Some(facet) && !values.is_empty() {
+            qb.push(format!(" AND {} = ANY(", facet.column()))
+                .push_bind(values.clone())
+                .push(")");
+        }
+    }
+
+    for (column, bound, op) in [
+        ("updated_at", filter.updated_from, ">="),
+        ("updated_at", filter.updated_to, "<"),
+        ("inserted_at", filter.inserted_from, ">="),
+        ("inserted_at", filter.inserted_to, "<"),
+    ] {
+        if let Some(bound) = bound {
+            qb.push(format!(" AND {} {} ", column, op)).push_bind(bound);
+        }
+    }
+}
+
+/// Вставка или обновление одного набора с записью версии при изменениях
+async fn upsert_item(
+    tx: &mut Transaction<'_, Postgres>,
+    fields: &OsdrFields,
+    seen_at: DateTime<Utc>,
+) -> Result<OsdrWriteOutcome, ApiError> {
+    // dataset_id задан всегда (при необходимости синтетический), иначе
+    // NULL обходил бы UNIQUE и каждая синхронизация плодила бы дубли
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO osdr_items (dataset_id, title, organism, study_type, status, updated_at, raw, last_seen_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (dataset_id) DO NOTHING
+        RETURNING id
+        "#
+    )
+    .bind(&fields.dataset_id)
+    .bind(&fields.title)
+    .bind(&fields.organism)
+    .bind(&fields.study_type)
+    .bind(&fields.status)
+    .bind(fields.updated_at)
+    .bind(&fields.raw)
+    .bind(seen_at)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    let (id, outcome, diff) = if let Some(row) = inserted {
+        (row.get::<i64, _>("id"), OsdrWriteOutcome::Inserted, fields.diff(None))
+    } else {
+        let row = sqlx::query(
+            r#"
+            SELECT id, dataset_id, title, organism, study_type, status, updated_at, inserted_at, raw,
+                   last_seen_at, withdrawn_at, withdrawn_reason
+            FROM osdr_items
+            WHERE dataset_id = $1
+            FOR UPDATE
+            "#
+        )
+        .bind(&fields.dataset_id)
+        .fetch_one(&mut **tx)
+        .await?;
+        let existing = map_item(&row);
+
+        let mut diff = fields.diff(Some(&OsdrFields::from_item(&existing)));
+        // Набор снова в выдаче — отметка об отзыве снимается
+        if existing.withdrawn_at.is_some() {
+            diff.insert("withdrawn_at".to_string(), json!({"old": existing.withdrawn_at, "new": null}));
+            diff.insert("withdrawn_reason".to_string(), json!({"old": existing.withdrawn_reason, "new": null}));
+        }
+        if diff.is_empty() {
+            sqlx::query("UPDATE osdr_items SET last_seen_at = $2, missed_syncs = 0 WHERE id = $1")
+                .bind(existing.id)
+                .bind(seen_at)
+                .execute(&mut **tx)
+                .await?;
+            return Ok(OsdrWriteOutcome::Unchanged);
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE osdr_items
+            SET title = $2, organism = $3, study_type = $4, status = $5, updated_at = $6, raw = $7,
+                last_seen_at = $8, missed_syncs = 0, withdrawn_at = NULL, withdrawn_reason = NULL
+            WHERE id = $1
+            "#
+        )
+        .bind(existing.id)
+        .bind(&fields.title)
+        .bind(&fields.organism)
+        .bind(&fields.study_type)
+        .bind(&fields.status)
+        .bind(fields.updated_at)
+        .bind(&fields.raw)
+        .bind(seen_at)
+        .execute(&mut **tx)
+        .await?;
+        (existing.id, OsdrWriteOutcome::Updated, diff)
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO osdr_item_versions (item_id, version, diff)
+        SELECT $1, COALESCE(MAX(version), 0) + 1, $2
+        FROM osdr_item_versions
+        WHERE item_id = $1
+        "#
+    )
+    .bind(id)
+    .bind(Value::Object(diff))
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(outcome)
+}
+
+fn map_item(r: &PgRow) -> OsdrItem {
+    OsdrItem {
+        id: r.get("id"),
+        dataset_id: r.get("dataset_id"),
+        title: r.get("title"),
+        organism: r.get("organism"),
+        study_type: r.get("study_type"),
+        status: r.get("status"),
+        updated_at: r.get("updated_at"),
+        inserted_at: r.get("inserted_at"),
+        raw: r.get("raw"),
+        last_seen_at: r.get("last_seen_at"),
+        withdrawn_at: r.get("withdrawn_at"),
+        withdrawn_reason: r.get("withdrawn_reason"),
+        search: None,
     }
 }
@@ -0,0 +1,164 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, PgPool, Row};
+
+use crate::domain::{GeoPoint, TleCheck, TleSet};
+use crate::errors::ApiError;
+
+#[async_trait]
+pub trait TleRepository: Send + Sync {
+    /// Сохраняет TLE; повторная загрузка той же эпохи ничего не меняет
+    async fn upsert(
+        &self,
+        norad_id: i64,
+        object_name: Option<String>,
+        line1: &str,
+        line2: &str,
+        epoch: DateTime<Utc>,
+        source: &str,
+    ) -> Result<i64, ApiError>;
+    async fn get_latest(&self, norad_id: i64) -> Result<Option<TleSet>, ApiError>;
+    async fn insert_check(&self, check: &TleCheck) -> Result<i64, ApiError>;
+    async fn get_last_check(&self, norad_id: i64) -> Result<Option<TleCheck>, ApiError>;
+}
+
+pub struct PgTleRepo {
+    pool: PgPool,
+}
+
+impl PgTleRepo {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl TleRepository for PgTleRepo {
+    async fn upsert(
+        &self,
+        norad_id: i64,
+        object_name: Option<String>,
+        line1: &str,
+        line2: &str,
+        epoch: DateTime<Utc>,
+        source: &str,
+    ) -> Result<i64, ApiError> {
+        let row = sqlx::query(
+            r#"
+            INSERT INTO tle_sets (norad_id, object_name, line1, line2, epoch, source)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (norad_id, epoch) DO UPDATE
+            SET object_name = COALESCE(EXCLUDED.object_name, tle_sets.object_name)
+            RETURNING id
+            "#
+        )
+        .bind(norad_id)
+        .bind(&object_name)
+        .bind(line1)
+        .bind(line2)
+        .bind(epoch)
+        .bind(source)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.get("id"))
+    }
+
+    async fn get_latest(&self, norad_id: i64) -> Result<Option<TleSet>, ApiError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, norad_id, object_name, line1, line2, epoch, source, fetched_at
+            FROM tle_sets
+            WHERE norad_id = $1
+            ORDER BY epoch DESC
+            LIMIT 1
+            "#
+        )
+        .bind(norad_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| TleSet {
+            id: r.get("id"),
+            norad_id: r.get("norad_id"),
+            object_name: r.get("object_name"),
+            line1: r.get("line1"),
+            line2: r.get("line2"),
+            epoch: r.get("epoch"),
+            source: r.get("source"),
+            fetched_at: r.get("fetched_at"),
+        }))
+    }
+
+    async fn insert_check(&self, check: &TleCheck) -> Result<i64, ApiError> {
+        let row = sqlx::query(
+            r#"
+            INSERT INTO tle_checks (
+                norad_id, tle_epoch, fix_at,
+                observed_lat, observed_lon, observed_alt,
+                predicted_lat, predicted_lon, predicted_alt,
+                error_km, stale
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING id
+            "#
+        )
+        .bind(check.norad_id)
+        .bind(check.tle_epoch)
+        .bind(check.fix_at)
+        .bind(check.observed.lat)
+        .bind(check.observed.lon)
+        .bind(check.observed.alt)
+        .bind(check.predicted.lat)
+        .bind(check.predicted.lon)
+        .bind(check.predicted.alt)
+        .bind(check.error_km)
+        .bind(check.stale)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.get("id"))
+    }
+
+    async fn get_last_check(&self, norad_id: i64) -> Result<Option<TleCheck>, ApiError> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, checked_at, norad_id, tle_epoch, fix_at,
+                   observed_lat, observed_lon, observed_alt,
+                   predicted_lat, predicted_lon, predicted_alt,
+                   error_km, stale
+            FROM tle_checks
+            WHERE norad_id = $1
+            ORDER BY checked_at DESC
+            LIMIT 1
+            "#
+        )
+        .bind(norad_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| map_check(&r)))
+    }
+}
+
+fn map_check(r: &PgRow) -> TleCheck {
+    TleCheck {
+        id: r.get("id"),
+        checked_at: r.get("checked_at"),
+        norad_id: r.get("norad_id"),
+        tle_epoch: r.get("tle_epoch"),
+        fix_at: r.get("fix_at"),
+        observed: GeoPoint {
+            lat: r.get("observed_lat"),
+            lon: r.get("observed_lon"),
+            alt: r.get("observed_alt"),
+        },
+        predicted: GeoPoint {
+            lat: r.get("predicted_lat"),
+            lon: r.get("predicted_lon"),
+            alt: r.get("predicted_alt"),
+        },
+        error_km: r.get("error_km"),
+        stale: r.get("stale"),
+    }
+}
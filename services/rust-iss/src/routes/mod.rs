@@ -5,42 +5,102 @@ use axum::{
 use std::sync::Arc;
 
 use crate::handlers::{
-    get_cache, get_latest, get_trend, health, list_datasets,
-    refresh_iss, refresh_space, sync_osdr,
-    IssServiceState, OsdrServiceState, SpaceServiceState,
+    create_geofence, delete_geofence, dry_run_mapping, delete_satellite, get_anomalies, get_assays, get_cache, get_cache_history, export_track, get_coverage, get_dataset, get_dwell, get_files, get_geofence, get_geofence_events, get_history, get_latest, get_mapping, get_passes, get_position_history, get_satellite, get_tle_status, get_trend,
+    events_stream, export_datasets, health, iss_ws, list_datasets, list_geofences, list_satellites, list_sync_reports, predict_iss, rebuild_rollups, refresh_iss, refresh_space, refresh_tle,
+    register_satellite, sync_osdr, update_geofence,
+    AnomalyServiceState, EventBusState, GeofenceServiceState, IssServiceState, OrbitServiceState, OsdrServiceState, SpaceServiceState,
 };
-use crate::repo::{CacheRepository, IssRepository, OsdrRepository};
-use crate::services::{IssService, OsdrService, SpaceService};
+use crate::repo::{AnomalyRepository, CacheRepository, GeofenceRepository, IssRepository, OsdrRepository, TleRepository};
+use crate::services::{AnomalyService, EventBus, GeofenceService, IssService, OrbitService, OsdrService, SpaceService};
 
-pub fn create_router<I, O, C>(
+pub fn create_router<I, O, C, T, A, G>(
     iss_service: Arc<IssService<I>>,
+    orbit_service: Arc<OrbitService<T, I>>,
+    anomaly_service: Arc<AnomalyService<A, I>>,
+    geofence_service: Arc<GeofenceService<G>>,
     osdr_service: Arc<OsdrService<O>>,
     space_service: Arc<SpaceService<C>>,
+    event_bus: Arc<EventBus>,
 ) -> Router
 where
     I: IssRepository + 'static,
     O: OsdrRepository + 'static,
     C: CacheRepository + 'static,
+    T: TleRepository + 'static,
+    A: AnomalyRepository + 'static,
+    G: GeofenceRepository + 'static,
 {
-    let iss_routes = Router::new()
+    let orbit_routes = Router::new()
+        .route("/predict", get(predict_iss::<T, I>))
+        .route("/passes", get(get_passes::<T, I>))
+        .route("/tle", get(get_tle_status::<T, I>))
+        .route("/tle/refresh", post(refresh_tle::<T, I>))
+        .with_state(orbit_service as OrbitServiceState<T, I>);
+
+    let anomaly_routes = Router::new()
+        .route("/anomalies", get(get_anomalies::<A, I>))
+        .with_state(anomaly_service as AnomalyServiceState<A, I>);
+
+    // Один набор маршрутов для /api/iss (МКС) и /api/satellites/:norad_id
+    let satellite_routes = Router::new()
         .route("/latest", get(get_latest::<I>))
+        .route("/history", get(get_position_history::<I>))
         .route("/trend", get(get_trend::<I>))
+        .route("/coverage", get(get_coverage::<I>))
+        .route("/dwell", get(get_dwell::<I>))
+        .route("/export", get(export_track::<I>))
         .route("/refresh", post(refresh_iss::<I>))
+        .route("/rollups/rebuild", post(rebuild_rollups::<I>))
+        .route("/ws", get(iss_ws::<I>))
+        .with_state(iss_service.clone() as IssServiceState<I>)
+        .merge(orbit_routes)
+        .merge(anomaly_routes);
+
+    let registry_routes = Router::new()
+        .route("/", get(list_satellites::<I>).post(register_satellite::<I>))
+        .route("/:norad_id", get(get_satellite::<I>).delete(delete_satellite::<I>))
         .with_state(iss_service as IssServiceState<I>);
 
+    let geofence_routes = Router::new()
+        .route("/", get(list_geofences::<G>).post(create_geofence::<G>))
+        .route(
+            "/:id",
+            get(get_geofence::<G>)
+                .put(update_geofence::<G>)
+                .delete(delete_geofence::<G>),
+        )
+        .route("/:id/events", get(get_geofence_events::<G>))
+        .with_state(geofence_service as GeofenceServiceState<G>);
+
     let osdr_routes = Router::new()
         .route("/", get(list_datasets::<O>))
-        .route("/sync", post(sync_osdr::<O>))
+        .route("/export", get(export_datasets::<O>))
+        .route("/sync", get(list_sync_reports::<O>).post(sync_osdr::<O>))
+        .route("/mapping", get(get_mapping::<O>))
+        .route("/mapping/dry-run", post(dry_run_mapping::<O>))
+        .route("/:dataset_id", get(get_dataset::<O>))
+        .route("/:dataset_id/history", get(get_history::<O>))
+        .route("/:dataset_id/assays", get(get_assays::<O>))
+        .route("/:dataset_id/files", get(get_files::<O>))
         .with_state(osdr_service as OsdrServiceState<O>);
 
     let space_routes = Router::new()
         .route("/cache/:source", get(get_cache::<C>))
+        .route("/cache/:source/history", get(get_cache_history::<C>))
         .route("/refresh", post(refresh_space::<C>))
         .with_state(space_service as SpaceServiceState<C>);
 
+    let event_routes = Router::new()
+        .route("/stream", get(events_stream))
+        .with_state(event_bus as EventBusState);
+
     Router::new()
         .route("/health", get(health))
-        .nest("/api/iss", iss_routes)
+        .nest("/api/iss", satellite_routes.clone())
+        .nest("/api/satellites/:norad_id", satellite_routes)
+        .nest("/api/satellites", registry_routes)
+        .nest("/api/geofences", geofence_routes)
         .nest("/api/osdr", osdr_routes)
         .nest("/api/space", space_routes)
+        .nest("/api/events", event_routes)
 }
@@ -0,0 +1,164 @@
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::domain::anomaly::{
+    decay_rate_km_per_day, detect, AnomalyKind, AnomalyThresholds, DetectedAnomaly,
+};
+use crate::domain::IssAnomaly;
+use crate::errors::ApiError;
+use crate::repo::{AnomalyRepository, IssRepository};
+use crate::services::EventBus;
+
+/// Сколько новых строк лога обрабатывается за один проход
+const ANOMALY_BATCH: i64 = 5000;
+/// История перед новыми точками: нужна для усреднения высоты за два витка
+const ANOMALY_CONTEXT_SECONDS: i64 = 4 * 3600;
+/// Окно оценки скорости снижения и минимальный охват данных в нём
+const DECAY_WINDOW_HOURS: i64 = 24;
+const DECAY_MIN_SPAN_HOURS: i64 = 12;
+
+pub struct AnomalyService<A: AnomalyRepository, I: IssRepository> {
+    anomaly_repo: Arc<A>,
+    iss_repo: Arc<I>,
+    thresholds: AnomalyThresholds,
+    events: Arc<EventBus>,
+}
+
+impl<A: AnomalyRepository, I: IssRepository> AnomalyService<A, I> {
+    pub fn new(
+        anomaly_repo: Arc<A>,
+        iss_repo: Arc<I>,
+        thresholds: AnomalyThresholds,
+        events: Arc<EventBus>,
+    ) -> Self {
+        Self {
+            anomaly_repo,
+            iss_repo,
+            thresholds,
+            events,
+        }
+    }
+
+    /// Проверяет новые позиции всех включённых спутников
+    pub async fn run(&self) -> Result<usize, ApiError> {
+        let satellites = self.iss_repo.list_satellites().await?;
+        let mut found = 0;
+
+        for sat in satellites.iter().filter(|s| s.enabled) {
+            match self.process(sat.norad_id).await {
+                Ok(n) => found += n,
+                Err(e) => error!("Anomaly detection for {} failed: {:?}", sat.norad_id, e),
+            }
+        }
+
+        if found > 0 {
+            info!("Anomaly detection: {} new anomalies", found);
+        }
+        Ok(found)
+    }
+
+    pub async fn process(&self, norad_id: i64) -> Result<usize, ApiError> {
+        let mut stored = 0;
+
+        loop {
+            let watermark = self.anomaly_repo.get_watermark(norad_id).await?;
+            let batch = self
+                .anomaly_repo
+                .load_new_samples(norad_id, watermark, ANOMALY_CONTEXT_SECONDS, ANOMALY_BATCH)
+                .await?;
+            let Some(last_fetch_id) = batch.last_fetch_id else {
+                break;
+            };
+
+            // Точки контекста уже проверены в прошлых проходах
+            let fresh: Vec<DetectedAnomaly> = detect(&batch.samples, &self.thresholds)
+                .into_iter()
+                .filter(|a| a.fetch_id > watermark)
+                .collect();
+            stored += self.store(norad_id, &fresh).await?;
+            self.anomaly_repo.set_watermark(norad_id, last_fetch_id).await?;
+        }
+
+        stored += self.check_decay(norad_id).await?;
+        Ok(stored)
+    }
+
+    pub async fn list(
+        &self,
+        norad_id: i64,
+        kind: Option<String>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<IssAnomaly>, ApiError> {
+        if let Some(k) = kind.as_deref() {
+            if AnomalyKind::parse(k).is_none() {
+                return Err(ApiError::validation(format!("Unknown anomaly kind: {}", k)));
+            }
+        }
+        self.anomaly_repo.list(norad_id, kind, from, to, limit).await
+    }
+
+    /// Текущая скорость снижения орбиты за последние сутки, км/сутки
+    pub async fn decay_rate(&self, norad_id: i64) -> Result<Option<f64>, ApiError> {
+        let to = Utc::now();
+        let samples = self
+            .anomaly_repo
+            .load_samples(norad_id, to - Duration::hours(DECAY_WINDOW_HOURS), to)
+            .await?;
+
+        let span = match (samples.first(), samples.last()) {
+            (Some(first), Some(last)) => last.at - first.at,
+            _ => return Ok(None),
+        };
+        if span < Duration::hours(DECAY_MIN_SPAN_HOURS) {
+            return Ok(None);
+        }
+
+        Ok(decay_rate_km_per_day(&samples))
+    }
+
+    /// Фиксирует ускоренное снижение не чаще раза за окно оценки
+    async fn check_decay(&self, norad_id: i64) -> Result<usize, ApiError> {
+        let Some(rate) = self.decay_rate(norad_id).await? else {
+            return Ok(0);
+        };
+        if rate <= self.thresholds.decay_km_per_day {
+            return Ok(0);
+        }
+
+        let kind = AnomalyKind::RapidDecay;
+        if let Some(last) = self.anomaly_repo.last_of_kind(norad_id, kind.as_str()).await? {
+            if Utc::now() - last.observed_at < Duration::hours(DECAY_WINDOW_HOURS) {
+                return Ok(0);
+            }
+        }
+
+        let Some(latest) = self.iss_repo.get_last(norad_id).await? else {
+            return Ok(0);
+        };
+        let anomaly = DetectedAnomaly {
+            kind,
+            fetch_id: latest.id,
+            observed_at: latest.position_at(),
+            value: rate,
+            threshold: self.thresholds.decay_km_per_day,
+            details: json!({"window_hours": DECAY_WINDOW_HOURS}),
+        };
+        self.store(norad_id, &[anomaly]).await
+    }
+
+    async fn store(&self, norad_id: i64, anomalies: &[DetectedAnomaly]) -> Result<usize, ApiError> {
+        if anomalies.is_empty() {
+            return Ok(0);
+        }
+
+        let stored = self.anomaly_repo.insert(norad_id, anomalies).await?;
+        for anomaly in &stored {
+            self.events.publish("iss.anomaly", json!(anomaly));
+        }
+        Ok(stored.len())
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+/// Сколько последних событий хранится для переподключения по Last-Event-ID
+const EVENT_HISTORY_SIZE: usize = 1000;
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Событие платформы: `kind` вида `<source>.<action>`, например `apod.updated`
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub id: u64,
+    pub kind: String,
+    pub at: DateTime<Utc>,
+    pub data: Value,
+}
+
+impl Event {
+    /// Источник события — часть `kind` до первой точки
+    pub fn source(&self) -> &str {
+        self.kind.split('.').next().unwrap_or_default()
+    }
+}
+
+/// Общая шина событий для SSE-подписчиков
+pub struct EventBus {
+    next_id: AtomicU64,
+    history: Mutex<VecDeque<Event>>,
+    tx: broadcast::Sender<Event>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        // id растут и между перезапусками, чтобы старый Last-Event-ID не совпал с новым событием
+        let start = Utc::now().timestamp_millis().max(0) as u64;
+
+        Self {
+            next_id: AtomicU64::new(start),
+            history: Mutex::new(VecDeque::with_capacity(EVENT_HISTORY_SIZE)),
+            tx: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    pub fn publish(&self, kind: &str, data: Value) {
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        // id выдаётся под блокировкой, чтобы история и канал шли в одном порядке
+        let event = Event {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            kind: kind.to_string(),
+            at: Utc::now(),
+            data,
+        };
+
+        if history.len() == EVENT_HISTORY_SIZE {
+            history.pop_front();
+        }
+        history.push_back(event.clone());
+        // Ошибка означает лишь отсутствие подписчиков
+        let _ = self.tx.send(event);
+    }
+
+    /// Подписка и события из истории после `last_id`, полученные атомарно
+    pub fn subscribe_since(&self, last_id: Option<u64>) -> (Vec<Event>, broadcast::Receiver<Event>) {
+        let history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let rx = self.tx.subscribe();
+        let missed = match last_id {
+            Some(last_id) => history.iter().filter(|e| e.id > last_id).cloned().collect(),
+            None => Vec::new(),
+        };
+        (missed, rx)
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,136 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+use crate::domain::geofence::GeofenceShape;
+use crate::domain::{Geofence, GeofenceCrossing, GeofenceEvent, IssFetchLog};
+use crate::errors::ApiError;
+use crate::repo::GeofenceRepository;
+use crate::services::EventBus;
+
+pub struct GeofenceService<G: GeofenceRepository> {
+    repo: Arc<G>,
+    events: Arc<EventBus>,
+}
+
+impl<G: GeofenceRepository> GeofenceService<G> {
+    pub fn new(repo: Arc<G>, events: Arc<EventBus>) -> Self {
+        Self { repo, events }
+    }
+
+    pub async fn list(&self) -> Result<Vec<Geofence>, ApiError> {
+        self.repo.list().await
+    }
+
+    pub async fn get(&self, id: i64) -> Result<Geofence, ApiError> {
+        self.repo
+            .get(id)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("Geofence {} not found", id)))
+    }
+
+    pub async fn create(
+        &self,
+        name: &str,
+        shape: &GeofenceShape,
+        norad_id: Option<i64>,
+        enabled: bool,
+    ) -> Result<Geofence, ApiError> {
+        validate(name, shape)?;
+        self.repo.create(name, shape, norad_id, enabled).await
+    }
+
+    pub async fn update(
+        &self,
+        id: i64,
+        name: &str,
+        shape: &GeofenceShape,
+        norad_id: Option<i64>,
+        enabled: bool,
+    ) -> Result<Geofence, ApiError> {
+        validate(name, shape)?;
+        self.repo
+            .update(id, name, shape, norad_id, enabled)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("Geofence {} not found", id)))
+    }
+
+    pub async fn delete(&self, id: i64) -> Result<(), ApiError> {
+        if self.repo.delete(id).await? {
+            Ok(())
+        } else {
+            Err(ApiError::not_found(format!("Geofence {} not found", id)))
+        }
+    }
+
+    pub async fn list_events(
+        &self,
+        id: i64,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<GeofenceEvent>, ApiError> {
+        self.get(id).await?;
+        self.repo.list_events(id, from, to, limit).await
+    }
+
+    /// Проверяет новую позицию по всем зонам спутника и фиксирует входы/выходы
+    ///
+    /// Первая проверка зоны только запоминает состояние, кроме случая, когда
+    /// спутник уже внутри — это считается входом.
+    pub async fn evaluate(&self, log: &IssFetchLog) -> Result<Vec<GeofenceEvent>, ApiError> {
+        let (Some(lat), Some(lon)) = (log.lat(), log.lon()) else {
+            return Ok(Vec::new());
+        };
+
+        let fences = self.repo.list_for_satellite(log.norad_id).await?;
+        if fences.is_empty() {
+            return Ok(Vec::new());
+        }
+        let states = self.repo.get_states(log.norad_id).await?;
+
+        let mut fired = Vec::new();
+        for fence in &fences {
+            let inside = fence.shape.contains(lat, lon);
+            let was_inside = states.get(&fence.id).copied();
+            if was_inside == Some(inside) {
+                continue;
+            }
+
+            let kind = match (was_inside, inside) {
+                (_, true) => Some("enter"),
+                (Some(true), false) => Some("exit"),
+                _ => None,
+            };
+            let crossing = kind.map(|event| GeofenceCrossing {
+                event,
+                fetch_id: log.id,
+                occurred_at: log.position_at(),
+                latitude: lat,
+                longitude: lon,
+            });
+
+            let Some(event) = self
+                .repo
+                .record_state(fence.id, log.norad_id, inside, crossing.as_ref())
+                .await?
+            else {
+                continue;
+            };
+            self.events.publish(
+                &format!("geofence.{}", event.event),
+                json!({"geofence": fence.name, "event": event}),
+            );
+            fired.push(event);
+        }
+
+        Ok(fired)
+    }
+}
+
+fn validate(name: &str, shape: &GeofenceShape) -> Result<(), ApiError> {
+    if name.trim().is_empty() {
+        return Err(ApiError::validation("name is required"));
+    }
+    shape.validate()
+}
@@ -1,68 +1,665 @@
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
-use chrono::Utc;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use futures_util::stream::{self, BoxStream, StreamExt};
 use serde_json::Value;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
+use tracing::{error, info, warn};
 
-use crate::domain::IssFetchLog;
-use crate::domain::IssTrend;
+use crate::domain::geocode::{GeoLocation, ReverseGeocoder};
+use crate::domain::pagination::{Page, PageRequest};
+use crate::domain::{
+    parse_number, CoverageBucket, CoverageReport, DataGap, DwellReport, FetchError, IssFetchLog,
+    IssTrend, NormalizedPosition, RetentionPolicy, RollupRebuild, RollupResolution, Satellite,
+    TrendBucket,
+};
+use crate::clients::IssPositionProvider;
 use crate::errors::ApiError;
 use crate::repo::IssRepository;
+use crate::services::EventBus;
+
+/// Сколько позиций держит broadcast-канал для отстающих подписчиков
+const POSITION_CHANNEL_CAPACITY: usize = 256;
+/// Ограничение на число интервалов в одном ответе тренда
+const MAX_TREND_BUCKETS: i64 = 5000;
+/// Промежуток между точками длиннее стольких интервалов опроса считается пропуском
+const GAP_INTERVAL_FACTOR: f64 = 2.0;
+/// Окна длиннее этого читаются из агрегатов, а не из сырого лога
+const ROLLUP_MIN_WINDOW_HOURS: i64 = 48;
+/// Интервал тренда с меньшей долей ожидаемых точек помечается как пропуск
+pub const GAP_COVERAGE_THRESHOLD: f64 = 0.5;
+/// Сколько спутников опрашивается одновременно
+const MAX_CONCURRENT_POLLS: usize = 8;
 
 pub struct IssService<R: IssRepository> {
     iss_repo: Arc<R>,
-    iss_url: String,
-    http_client: reqwest::Client,
-    fetch_mutex: Arc<Mutex<()>>,
+    /// Источники позиции в порядке предпочтения
+    providers: Vec<Arc<dyn IssPositionProvider>>,
+    stale_seconds: i64,
+    retention: RetentionPolicy,
+    /// Блокировка на спутник: параллельные запросы одного спутника идут по очереди,
+    /// разные спутники не ждут друг друга
+    fetch_locks: Mutex<HashMap<i64, Arc<Mutex<()>>>>,
+    last_polled: Mutex<HashMap<i64, Instant>>,
+    positions: broadcast::Sender<IssFetchLog>,
+    geocoder: Arc<ReverseGeocoder>,
+    events: Arc<EventBus>,
 }
 
 impl<R: IssRepository> IssService<R> {
-    pub fn new(iss_repo: Arc<R>, iss_url: String) -> Self {
-        let http_client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(20))
-            .user_agent("KosmoStars-Space/1.0")
-            .build()
-            .expect("Failed to build HTTP client");
-
+    pub fn new(
+        iss_repo: Arc<R>,
+        providers: Vec<Arc<dyn IssPositionProvider>>,
+        stale_seconds: i64,
+        retention: RetentionPolicy,
+        geocoder: Arc<ReverseGeocoder>,
+        events: Arc<EventBus>,
+    ) -> Self {
         Self {
             iss_repo,
-            iss_url,
-            http_client,
-            fetch_mutex: Arc::new(Mutex::new(())),
+            providers,
+            stale_seconds,
+            retention,
+            fetch_locks: Mutex::new(HashMap::new()),
+            last_polled: Mutex::new(HashMap::new()),
+            positions: broadcast::channel(POSITION_CHANNEL_CAPACITY).0,
+            geocoder,
+            events,
+        }
+    }
+
+    /// Подписка на новые позиции сразу после их сохранения
+    pub fn subscribe(&self) -> broadcast::Receiver<IssFetchLog> {
+        self.positions.subscribe()
+    }
+
+    /// Регистрирует спутник при первом запуске; существующую запись не трогает
+    pub async fn ensure_satellite(
+        &self,
+        norad_id: i64,
+        name: &str,
+        source_url: &str,
+        interval_seconds: u64,
+    ) -> Result<(), ApiError> {
+        let interval = i32::try_from(interval_seconds).unwrap_or(i32::MAX);
+        self.iss_repo.ensure_satellite(norad_id, name, source_url, interval).await
+    }
+
+    pub async fn list_satellites(&self) -> Result<Vec<Satellite>, ApiError> {
+        self.iss_repo.list_satellites().await
+    }
+
+    pub async fn get_satellite(&self, norad_id: i64) -> Result<Satellite, ApiError> {
+        self.iss_repo
+            .get_satellite(norad_id)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("Satellite {} is not registered", norad_id)))
+    }
+
+    pub async fn register_satellite(
+        &self,
+        norad_id: i64,
+        name: &str,
+        source_url: &str,
+        interval_seconds: i32,
+        enabled: bool,
+    ) -> Result<Satellite, ApiError> {
+        if norad_id <= 0 {
+            return Err(ApiError::validation("norad_id must be positive"));
+        }
+        if interval_seconds < 10 {
+            return Err(ApiError::validation("interval_seconds must be at least 10"));
         }
+        if !source_url.starts_with("http://") && !source_url.starts_with("https://") {
+            return Err(ApiError::validation("source_url must be an http(s) URL"));
+        }
+
+        self.iss_repo
+            .upsert_satellite(norad_id, name, source_url, interval_seconds, enabled)
+            .await
+    }
+
+    pub async fn delete_satellite(&self, norad_id: i64) -> Result<(), ApiError> {
+        if self.iss_repo.delete_satellite(norad_id).await? {
+            self.last_polled.lock().await.remove(&norad_id);
+            self.fetch_locks.lock().await.remove(&norad_id);
+            Ok(())
+        } else {
+            Err(ApiError::not_found(format!("Satellite {} is not registered", norad_id)))
+        }
+    }
+
+    pub async fn get_latest(&self, norad_id: i64) -> Result<Option<IssFetchLog>, ApiError> {
+        self.iss_repo.get_last(norad_id).await
     }
 
-    pub async fn get_latest(&self) -> Result<Option<IssFetchLog>, ApiError> {
-        self.iss_repo.get_last().await
+    /// Последняя сохранённая позиция каждого спутника реестра
+    pub async fn latest_positions(&self) -> Result<Vec<IssFetchLog>, ApiError> {
+        let mut logs = Vec::new();
+        for sat in self.iss_repo.list_satellites().await? {
+            logs.extend(self.iss_repo.get_last(sat.norad_id).await?);
+        }
+        Ok(logs)
     }
 
-    pub async fn get_trend(&self, hours: i64) -> Result<Vec<IssTrend>, ApiError> {
-        self.iss_repo.get_trend(hours).await
+    /// Последние `n` позиций, от старых к новым
+    pub async fn get_recent(&self, norad_id: i64, n: i64) -> Result<Vec<IssFetchLog>, ApiError> {
+        let mut logs = self.iss_repo.get_last_n(norad_id, n).await?;
+        logs.reverse();
+        Ok(logs)
     }
 
-    pub async fn fetch_and_store(&self) -> Result<IssFetchLog, ApiError> {
-        // Mutex для защиты от параллельных запросов
-        let _guard = self.fetch_mutex.lock().await;
+    /// Лог позиций от новых к старым, постранично, с пропусками между точками страницы
+    pub async fn history(
+        &self,
+        norad_id: i64,
+        page: &PageRequest,
+    ) -> Result<(Page<IssFetchLog>, Vec<DataGap>), ApiError> {
+        let mut result = self.iss_repo.history(norad_id, page.cursor.as_ref(), page.limit).await?;
+        if page.include_total {
+            result.total = Some(self.iss_repo.count(norad_id).await?);
+        }
+        let gaps = match (result.items.last(), result.items.first()) {
+            // Окно [from, to) должно включать и самую новую точку страницы
+            (Some(oldest), Some(newest)) => {
+                let to = newest.fetched_at + chrono::Duration::microseconds(1);
+                self.gaps_for(norad_id, oldest.fetched_at, to).await?
+            }
+            _ => Vec::new(),
+        };
+        Ok((result, gaps))
+    }
 
-        let response = self.http_client
-            .get(&self.iss_url)
-            .send()
+    /// Тренд от новых интервалов к старым; для спутников из реестра пустые интервалы
+    /// добавляются с нулевым покрытием
+    pub async fn get_trend(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket: TrendBucket,
+    ) -> Result<Vec<IssTrend>, ApiError> {
+        validate_window(from, to, bucket)?;
+        let rollup = self.rollup_for(from, to, bucket);
+        let trends = self
+            .iss_repo
+            .get_trend(norad_id, from, to, bucket.seconds(), rollup)
             .await?;
 
-        if !response.status().is_success() {
-            return Err(ApiError::upstream(
-                response.status().as_u16(),
-                format!("ISS API returned {}", response.status()),
-            ));
+        let Some(sat) = self.iss_repo.get_satellite(norad_id).await? else {
+            return Ok(trends);
+        };
+        let interval = f64::from(sat.interval_seconds.max(1));
+        let mut by_start: HashMap<i64, IssTrend> = trends
+            .into_iter()
+            .map(|t| (t.bucket_start.timestamp(), t))
+            .collect();
+
+        let mut filled: Vec<IssTrend> = bucket_spans(from, to, bucket)
+            .map(|(start, seconds)| {
+                let mut trend = by_start
+                    .remove(&start.timestamp())
+                    .unwrap_or_else(|| IssTrend::empty(start));
+                trend.coverage = Some(coverage_ratio(trend.cnt, seconds / interval));
+                trend
+            })
+            .collect();
+        filled.reverse();
+        Ok(filled)
+    }
+
+    /// Заполненность лога по интервалам и пропуски с вероятными причинами
+    pub async fn get_coverage(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket: TrendBucket,
+    ) -> Result<CoverageReport, ApiError> {
+        validate_window(from, to, bucket)?;
+        let sat = self.get_satellite(norad_id).await?;
+        let interval = f64::from(sat.interval_seconds.max(1));
+
+        let counts: HashMap<i64, i64> = self
+            .iss_repo
+            .get_sample_counts(norad_id, from, to, bucket.seconds(), self.rollup_for(from, to, bucket))
+            .await?
+            .into_iter()
+            .map(|(start, cnt)| (start.timestamp(), cnt))
+            .collect();
+        let buckets: Vec<CoverageBucket> = bucket_spans(from, to, bucket)
+            .map(|(start, seconds)| {
+                let samples = counts.get(&start.timestamp()).copied().unwrap_or(0);
+                let expected = seconds / interval;
+                CoverageBucket {
+                    bucket_start: start,
+                    samples,
+                    expected,
+                    coverage: coverage_ratio(samples, expected),
+                }
+            })
+            .collect();
+
+        let samples: i64 = buckets.iter().map(|b| b.samples).sum();
+        let expected = (to - from).num_seconds() as f64 / interval;
+        let gaps = self.find_gaps(&sat, from, to).await?;
+
+        Ok(CoverageReport {
+            norad_id,
+            from,
+            to,
+            interval_seconds: sat.interval_seconds,
+            samples,
+            expected,
+            coverage: coverage_ratio(samples, expected),
+            buckets,
+            gaps,
+        })
+    }
+
+    /// Длинные окна и окна за пределами хранения сырых точек читаются из агрегатов
+    fn rollup_for(&self, from: DateTime<Utc>, to: DateTime<Utc>, bucket: TrendBucket) -> Option<RollupResolution> {
+        let long = to - from > chrono::Duration::hours(ROLLUP_MIN_WINDOW_HOURS);
+        let expired = self.retention.raw_days > 0
+            && from < Utc::now() - chrono::Duration::days(self.retention.raw_days);
+        (long || expired).then(|| RollupResolution::for_bucket(bucket))
+    }
+
+    /// Пересчитывает агрегаты всех разрешений за окно из сырого лога.
+    /// Интервалы, начавшиеся до срока хранения сырых точек, не трогаются:
+    /// их исходные точки уже могли быть удалены
+    pub async fn rebuild_rollups(
+        &self,
+        norad_id: Option<i64>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<RollupRebuild>, ApiError> {
+        if from >= to {
+            return Err(ApiError::validation("from must be earlier than to"));
         }
+        let raw_cutoff = (self.retention.raw_days > 0)
+            .then(|| (Utc::now() - chrono::Duration::days(self.retention.raw_days)).timestamp());
 
-        let payload: Value = response.json().await?;
-        let id = self.iss_repo.insert(&self.iss_url, payload.clone()).await?;
+        let mut report = Vec::new();
+        for resolution in RollupResolution::ALL {
+            let step = resolution.seconds();
+            let mut start = from.timestamp().div_euclid(step) * step;
+            if let Some(cutoff) = raw_cutoff {
+                start = start.max((cutoff + step - 1).div_euclid(step) * step);
+            }
+            let end = (to.timestamp() + step - 1).div_euclid(step) * step;
+            if start >= end {
+                continue;
+            }
 
-        Ok(IssFetchLog {
+            let (start, end) = (timestamp(start)?, timestamp(end)?);
+            let buckets = self.iss_repo.rebuild_rollup(resolution, norad_id, start, end).await?;
+            report.push(RollupRebuild {
+                resolution,
+                from: start,
+                to: end,
+                buckets,
+            });
+        }
+        Ok(report)
+    }
+
+    /// Первичное заполнение агрегатов по уже накопленному логу
+    pub async fn ensure_rollups(&self) -> Result<(), ApiError> {
+        if self.iss_repo.has_rollups().await? {
+            return Ok(());
+        }
+        let Some(raw_start) = self.iss_repo.get_raw_start().await? else {
+            return Ok(());
+        };
+        let report = self
+            .rebuild_rollups(None, raw_start, Utc::now() + chrono::Duration::days(1))
+            .await?;
+        for r in &report {
+            info!("Built {} {:?} rollups from {} to {}", r.buckets, r.resolution, r.from, r.to);
+        }
+        Ok(())
+    }
+
+    /// Удаляет сырые точки и агрегаты старше своих сроков хранения
+    pub async fn apply_retention(&self) -> Result<(), ApiError> {
+        let now = Utc::now();
+        if self.retention.raw_days > 0 {
+            let cutoff = now - chrono::Duration::days(self.retention.raw_days);
+            let deleted = self.iss_repo.delete_raw_before(cutoff).await?;
+            if deleted > 0 {
+                info!("Retention: removed {} raw positions before {}", deleted, cutoff);
+            }
+        }
+        for resolution in RollupResolution::ALL {
+            let days = self.retention.rollup_days(resolution);
+            if days <= 0 {
+                continue;
+            }
+            let cutoff = now - chrono::Duration::days(days);
+            let deleted = self.iss_repo.delete_rollups_before(resolution, cutoff).await?;
+            if deleted > 0 {
+                info!("Retention: removed {} {:?} rollups before {}", deleted, resolution, cutoff);
+            }
+        }
+        Ok(())
+    }
+
+    /// Позиции окна для выгрузки; строки читаются из БД по мере отдачи
+    pub async fn export_track(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<(BoxStream<'static, Result<IssFetchLog, ApiError>>, Vec<DataGap>), ApiError> {
+        if from >= to {
+            return Err(ApiError::validation("from must be earlier than to"));
+        }
+        let gaps = self.gaps_for(norad_id, from, to).await?;
+        Ok((self.iss_repo.stream_range(norad_id, from, to), gaps))
+    }
+
+    /// Сколько времени спутник провёл над каждой страной и океаном
+    pub async fn get_dwell(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<DwellReport, ApiError> {
+        if from >= to {
+            return Err(ApiError::validation("from must be earlier than to"));
+        }
+        let sat = self.get_satellite(norad_id).await?;
+        // Точка «длится» не дольше порога пропуска, чтобы простои не приписывались стране
+        let max_step = f64::from(sat.interval_seconds.max(1)) * GAP_INTERVAL_FACTOR;
+
+        let mut regions = self.iss_repo.get_dwell(norad_id, from, to, max_step).await?;
+        let total_seconds: f64 = regions.iter().map(|r| r.seconds).sum();
+        if total_seconds > 0.0 {
+            for region in &mut regions {
+                region.share = region.seconds / total_seconds;
+            }
+        }
+
+        Ok(DwellReport {
+            norad_id,
+            from,
+            to,
+            total_seconds,
+            regions,
+        })
+    }
+
+    /// Проставляет страну/океан записям, сохранённым до появления геокодирования
+    pub async fn backfill_locations(&self, batch: i64) -> Result<u64, ApiError> {
+        let mut after_id = 0;
+        let mut updated = 0;
+        loop {
+            let logs = self.iss_repo.get_unlocated(after_id, batch).await?;
+            let Some(last) = logs.last() else {
+                return Ok(updated);
+            };
+            after_id = last.id;
+
+            for log in &logs {
+                if let Some(location) = self.locate(&log.payload) {
+                    self.iss_repo.set_location(log.id, log.fetched_at, &location).await?;
+                    updated += 1;
+                }
+            }
+        }
+    }
+
+    fn locate(&self, payload: &Value) -> Option<GeoLocation> {
+        let lat = parse_number(&payload["latitude"])?;
+        let lon = parse_number(&payload["longitude"])?;
+        self.geocoder.locate(lat, lon)
+    }
+
+    /// Пропуски окна; у спутника вне реестра нет интервала опроса, искать не по чему
+    async fn gaps_for(&self, norad_id: i64, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<DataGap>, ApiError> {
+        match self.iss_repo.get_satellite(norad_id).await? {
+            Some(sat) => self.find_gaps(&sat, from, to).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn find_gaps(
+        &self,
+        sat: &Satellite,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<DataGap>, ApiError> {
+        let interval = f64::from(sat.interval_seconds.max(1));
+        let threshold = interval * GAP_INTERVAL_FACTOR;
+        let longer = |a: DateTime<Utc>, b: DateTime<Utc>| (b - a).num_seconds() as f64 > threshold;
+
+        // (начало, конец, закрыт ли пропуск точкой)
+        let mut spans = Vec::new();
+        match self.iss_repo.get_sample_bounds(sat.norad_id, from, to).await? {
+            None => spans.push((from, to, false)),
+            Some((first, last)) => {
+                if longer(from, first) {
+                    spans.push((from, first, true));
+                }
+                for (start, end) in self.iss_repo.get_gaps(sat.norad_id, from, to, threshold).await? {
+                    spans.push((start, end, true));
+                }
+                if longer(last, to) {
+                    spans.push((last, to, false));
+                }
+            }
+        }
+
+        let errors = self.iss_repo.get_fetch_errors(sat.norad_id, from, to).await?;
+        Ok(spans
+            .into_iter()
+            .map(|(start, end, closed)| {
+                let inside: Vec<&FetchError> = errors
+                    .iter()
+                    .filter(|e| e.occurred_at >= start && e.occurred_at <= end)
+                    .collect();
+                let codes: BTreeSet<String> = inside.iter().map(|e| e.code.clone()).collect();
+                let duration = (end - start).num_seconds();
+
+                DataGap {
+                    start,
+                    end,
+                    duration_seconds: duration,
+                    missing_samples: ((duration as f64 / interval).round() as i64 - 1).max(0),
+                    cause: gap_cause(&codes, sat.enabled, closed).to_string(),
+                    error_count: inside.len() as i64,
+                    error_codes: codes.into_iter().collect(),
+                    open: !closed,
+                }
+            })
+            .collect())
+    }
+
+    /// Опрашивает спутники реестра, у которых истёк интервал опроса;
+    /// медленный источник одного спутника не задерживает остальные
+    pub async fn poll_due(&self) -> Result<usize, ApiError> {
+        let satellites = self.iss_repo.list_satellites().await?;
+
+        let due: Vec<Satellite> = {
+            let mut last = self.last_polled.lock().await;
+            let now = Instant::now();
+            satellites
+                .into_iter()
+                .filter(|s| s.enabled)
+                .filter(|sat| {
+                    let due = last.get(&sat.norad_id).is_none_or(|t| {
+                        now.duration_since(*t) >= Duration::from_secs(sat.interval_seconds.max(1) as u64)
+                    });
+                    if due {
+                        last.insert(sat.norad_id, now);
+                    }
+                    due
+                })
+                .collect()
+        };
+
+        let polled = stream::iter(due)
+            .map(|sat| async move {
+                match self.fetch_satellite(&sat).await {
+                    Ok(_) => true,
+                    Err(e) => {
+                        error!("Satellite {} fetch error: {:?}", sat.norad_id, e);
+                        false
+                    }
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_POLLS)
+            .collect::<Vec<bool>>()
+            .await
+            .into_iter()
+            .filter(|ok| *ok)
+            .count();
+
+        Ok(polled)
+    }
+
+    pub async fn fetch_and_store(&self, norad_id: i64) -> Result<IssFetchLog, ApiError> {
+        let sat = self.get_satellite(norad_id).await?;
+        self.fetch_satellite(&sat).await
+    }
+
+    async fn fetch_satellite(&self, sat: &Satellite) -> Result<IssFetchLog, ApiError> {
+        let result = self.try_fetch_satellite(sat).await;
+        // Ошибки опроса сохраняются, чтобы объяснять пропуски в логе
+        if let Err(e) = &result {
+            if let Err(log_err) = self.iss_repo.insert_fetch_error(sat.norad_id, &e.code, &e.message).await {
+                error!("Cannot record fetch error for {}: {:?}", sat.norad_id, log_err);
+            }
+        }
+        result
+    }
+
+    async fn try_fetch_satellite(&self, sat: &Satellite) -> Result<IssFetchLog, ApiError> {
+        let lock = self.fetch_lock(sat.norad_id).await;
+        let _guard = lock.lock().await;
+
+        let (provider, position) = self.fetch_position(sat).await?;
+        let payload = position.to_payload();
+        let location = self.locate(&payload);
+        // Время точки — то, что записала база, как и в истории
+        let (id, fetched_at) = self
+            .iss_repo
+            .insert(sat.norad_id, &position.source, payload.clone(), provider, location.as_ref())
+            .await?;
+
+        let log = IssFetchLog {
             id,
-            fetched_at: Utc::now(),
-            source_url: self.iss_url.clone(),
+            norad_id: sat.norad_id,
+            fetched_at,
+            source_url: position.source,
             payload,
+            provider: Some(provider.to_string()),
+            location,
+        };
+        self.events.publish("iss.position", log.position_json());
+        // Ошибка означает лишь отсутствие подписчиков
+        let _ = self.positions.send(log.clone());
+
+        Ok(log)
+    }
+
+    async fn fetch_lock(&self, norad_id: i64) -> Arc<Mutex<()>> {
+        self.fetch_locks.lock().await.entry(norad_id).or_default().clone()
+    }
+
+    /// Опрашивает источники по порядку; ошибка или устаревшая позиция — переход к следующему
+    async fn fetch_position(&self, sat: &Satellite) -> Result<(&'static str, NormalizedPosition), ApiError> {
+        let mut first_error: Option<ApiError> = None;
+        let mut failures = Vec::new();
+
+        for provider in self.providers.iter().filter(|p| p.supports(sat.norad_id)) {
+            let result = provider.fetch(sat).await.and_then(|position| {
+                let age = (Utc::now() - position.timestamp).num_seconds();
+                if age > self.stale_seconds {
+                    Err(ApiError::new(
+                        "STALE_POSITION",
+                        format!("Position is {} s old (limit {} s)", age, self.stale_seconds),
+                    ))
+                } else {
+                    Ok(position)
+                }
+            });
+
+            match result {
+                Ok(position) => return Ok((provider.name(), position)),
+                Err(e) => {
+                    warn!("Provider {} failed for {}: {}", provider.name(), sat.norad_id, e.message);
+                    failures.push(format!("{}: {}", provider.name(), e.message));
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        // Код берётся у основного источника, чтобы причины пропусков в отчёте покрытия не менялись
+        Err(match first_error {
+            Some(e) => ApiError::new(e.code, failures.join("; ")),
+            None => ApiError::validation(format!("No position provider supports NORAD {}", sat.norad_id)),
         })
     }
 }
+
+fn timestamp(secs: i64) -> Result<DateTime<Utc>, ApiError> {
+    DateTime::from_timestamp(secs, 0).ok_or_else(|| ApiError::validation("Timestamp out of range"))
+}
+
+fn validate_window(from: DateTime<Utc>, to: DateTime<Utc>, bucket: TrendBucket) -> Result<(), ApiError> {
+    if from >= to {
+        return Err(ApiError::validation("from must be earlier than to"));
+    }
+    let buckets = (to - from).num_seconds() / bucket.seconds();
+    if buckets > MAX_TREND_BUCKETS {
+        return Err(ApiError::validation(format!(
+            "Window spans {} buckets, at most {} allowed; use a coarser bucket",
+            buckets, MAX_TREND_BUCKETS
+        )));
+    }
+    Ok(())
+}
+
+/// Начала интервалов, пересекающих окно, и длина пересечения в секундах
+fn bucket_spans(
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    bucket: TrendBucket,
+) -> impl Iterator<Item = (DateTime<Utc>, f64)> {
+    let step = bucket.seconds();
+    let (from_ts, to_ts) = (from.timestamp(), to.timestamp());
+    let first = from_ts.div_euclid(step) * step;
+
+    (first..to_ts).step_by(step as usize).filter_map(move |start| {
+        let overlap = (start + step).min(to_ts) - start.max(from_ts);
+        DateTime::from_timestamp(start, 0).map(|dt| (dt, overlap.max(0) as f64))
+    })
+}
+
+fn coverage_ratio(samples: i64, expected: f64) -> f64 {
+    if expected <= 0.0 {
+        return 1.0;
+    }
+    (samples as f64 / expected).min(1.0)
+}
+
+/// Наиболее вероятная причина пропуска по ошибкам опроса внутри него
+fn gap_cause(codes: &BTreeSet<String>, enabled: bool, closed: bool) -> &'static str {
+    if codes.contains("UPSTREAM_429") {
+        "rate_limited"
+    } else if codes.iter().any(|c| c.starts_with("UPSTREAM_")) {
+        "upstream_error"
+    } else if codes.contains("DATABASE_ERROR") {
+        "database_error"
+    } else if !codes.is_empty() {
+        "fetch_error"
+    } else if !enabled && !closed {
+        "polling_disabled"
+    } else {
+        // Ни точек, ни ошибок: сборщик не работал
+        "collector_down"
+    }
+}
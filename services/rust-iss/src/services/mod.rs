@@ -1,7 +1,15 @@
+pub mod anomaly_service;
+pub mod event_bus;
+pub mod geofence_service;
 pub mod iss_service;
+pub mod orbit_service;
 pub mod osdr_service;
 pub mod space_service;
 
+pub use anomaly_service::AnomalyService;
+pub use event_bus::{Event, EventBus};
+pub use geofence_service::GeofenceService;
 pub use iss_service::IssService;
+pub use orbit_service::OrbitService;
 pub use osdr_service::OsdrService;
 pub use space_service::SpaceService;
@@ -0,0 +1,261 @@
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use tracing::{info, warn};
+
+use crate::domain::orbit::{find_passes, parse_tle_text, Propagator};
+use crate::domain::{
+    haversine_distance_km, GeoPoint, Observer, OrbitPosition, SatellitePass, TleCheck, TleSet,
+};
+use crate::errors::ApiError;
+use crate::repo::{IssRepository, TleRepository};
+
+/// Максимум точек в одном запросе прогноза
+const MAX_PREDICT_POINTS: i64 = 5000;
+
+pub struct OrbitService<T: TleRepository, I: IssRepository> {
+    tle_repo: Arc<T>,
+    iss_repo: Arc<I>,
+    tle_url: String,
+    tle_file: Option<String>,
+    stale_km: f64,
+    http_client: reqwest::Client,
+}
+
+impl<T: TleRepository, I: IssRepository> OrbitService<T, I> {
+    pub fn new(
+        tle_repo: Arc<T>,
+        iss_repo: Arc<I>,
+        tle_url: String,
+        tle_file: Option<String>,
+        stale_km: f64,
+    ) -> Self {
+        let http_client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(20))
+            .user_agent("KosmoStars-Space/1.0")
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            tle_repo,
+            iss_repo,
+            tle_url,
+            tle_file,
+            stale_km,
+            http_client,
+        }
+    }
+
+    /// Загружает TLE из локального файла (если задан) или по URL и сохраняет их эпохи
+    ///
+    /// `{norad_id}` в TLE_URL подставляется для каждого спутника из реестра.
+    pub async fn refresh_tle(&self) -> Result<usize, ApiError> {
+        let mut sources = Vec::new();
+        match &self.tle_file {
+            Some(path) => {
+                let text = tokio::fs::read_to_string(path)
+                    .await
+                    .map_err(|e| ApiError::internal(format!("Cannot read TLE file {}: {}", path, e)))?;
+                sources.push((text, format!("file:{}", path)));
+            }
+            None => {
+                let urls: Vec<String> = if self.tle_url.contains("{norad_id}") {
+                    self.iss_repo
+                        .list_satellites()
+                        .await?
+                        .iter()
+                        .filter(|s| s.enabled)
+                        .map(|s| self.tle_url.replace("{norad_id}", &s.norad_id.to_string()))
+                        .collect()
+                } else {
+                    vec![self.tle_url.clone()]
+                };
+
+                for url in urls {
+                    match self.fetch_tle_text(&url).await {
+                        Ok(text) => sources.push((text, url)),
+                        Err(e) => warn!("TLE fetch from {} failed: {}", url, e),
+                    }
+                }
+            }
+        }
+
+        let mut stored = 0;
+        for (text, source) in &sources {
+            for tle in parse_tle_text(text) {
+                let propagator = match Propagator::from_tle(tle.name.clone(), &tle.line1, &tle.line2) {
+                    Ok(p) => p,
+                    Err(e) => {
+                        warn!("Skipping TLE {:?}: {}", tle.name, e);
+                        continue;
+                    }
+                };
+
+                self.tle_repo
+                    .upsert(
+                        propagator.norad_id() as i64,
+                        propagator.object_name(),
+                        &tle.line1,
+                        &tle.line2,
+                        propagator.epoch(),
+                        source,
+                    )
+                    .await?;
+                stored += 1;
+            }
+        }
+
+        if stored == 0 {
+            return Err(ApiError::validation("No valid TLE found in configured sources"));
+        }
+
+        info!("TLE refreshed: {} set(s) from {} source(s)", stored, sources.len());
+        Ok(stored)
+    }
+
+    async fn fetch_tle_text(&self, url: &str) -> Result<String, ApiError> {
+        let response = self.http_client.get(url).send().await?;
+        if !response.status().is_success() {
+            return Err(ApiError::upstream(
+                response.status().as_u16(),
+                format!("TLE source returned {}", response.status()),
+            ));
+        }
+        Ok(response.text().await?)
+    }
+
+    pub async fn get_tle(&self, norad_id: i64) -> Result<Option<TleSet>, ApiError> {
+        self.tle_repo.get_latest(norad_id).await
+    }
+
+    pub async fn get_last_check(&self, norad_id: i64) -> Result<Option<TleCheck>, ApiError> {
+        self.tle_repo.get_last_check(norad_id).await
+    }
+
+    pub async fn predict_at(&self, norad_id: i64, at: DateTime<Utc>) -> Result<OrbitPosition, ApiError> {
+        self.propagator(norad_id).await?.position_at(at)
+    }
+
+    pub async fn predict_range(
+        &self,
+        norad_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        step_seconds: i64,
+    ) -> Result<Vec<OrbitPosition>, ApiError> {
+        if step_seconds <= 0 {
+            return Err(ApiError::validation("step must be positive"));
+        }
+        if to < from {
+            return Err(ApiError::validation("to must not be earlier than from"));
+        }
+        let points = (to - from).num_seconds() / step_seconds + 1;
+        if points > MAX_PREDICT_POINTS {
+            return Err(ApiError::validation(format!(
+                "Range produces {} points, max is {}",
+                points, MAX_PREDICT_POINTS
+            )));
+        }
+
+        let propagator = self.propagator(norad_id).await?;
+        (0..points)
+            .map(|i| propagator.position_at(from + Duration::seconds(i * step_seconds)))
+            .collect()
+    }
+
+    /// Пролёты над наблюдателем на ближайшие `days` суток
+    pub async fn passes(
+        &self,
+        norad_id: i64,
+        observer: Observer,
+        days: i64,
+        min_elevation: f64,
+    ) -> Result<Vec<SatellitePass>, ApiError> {
+        if !(-90.0..=90.0).contains(&observer.lat) || !(-180.0..=180.0).contains(&observer.lon) {
+            return Err(ApiError::validation("lat must be within ±90 and lon within ±180"));
+        }
+
+        let propagator = self.propagator(norad_id).await?;
+        let from = Utc::now();
+        let to = from + Duration::days(days);
+
+        // Расчёт занимает десятки тысяч вызовов SGP4 — уводим с async-потоков
+        let passes = tokio::task::spawn_blocking(move || find_passes(&propagator, &observer, from, to))
+            .await
+            .map_err(|e| ApiError::internal(e.to_string()))??;
+
+        Ok(passes
+            .into_iter()
+            .filter(|p| p.max_elevation >= min_elevation)
+            .collect())
+    }
+
+    /// Сверяет прогнозы по всем спутникам реестра, для которых есть TLE
+    pub async fn check_all(&self) -> Result<Vec<TleCheck>, ApiError> {
+        let mut checks = Vec::new();
+        for sat in self.iss_repo.list_satellites().await?.iter().filter(|s| s.enabled) {
+            if self.tle_repo.get_latest(sat.norad_id).await?.is_none() {
+                continue;
+            }
+            if let Some(check) = self.check_accuracy(sat.norad_id).await? {
+                checks.push(check);
+            }
+        }
+        Ok(checks)
+    }
+
+    /// Сверяет SGP4-прогноз с последней фактической позицией спутника
+    pub async fn check_accuracy(&self, norad_id: i64) -> Result<Option<TleCheck>, ApiError> {
+        let Some(fix) = self.iss_repo.get_last(norad_id).await? else {
+            return Ok(None);
+        };
+        let (Some(lat), Some(lon), Some(alt)) = (fix.lat(), fix.lon(), fix.altitude()) else {
+            return Ok(None);
+        };
+
+        let tle = self.latest_tle(norad_id).await?;
+        let propagator = Propagator::from_tle(tle.object_name.clone(), &tle.line1, &tle.line2)?;
+        let fix_at = fix.position_at();
+        let predicted = propagator.position_at(fix_at)?;
+
+        let ground_km = haversine_distance_km(lat, lon, predicted.latitude, predicted.longitude);
+        let error_km = (ground_km.powi(2) + (alt - predicted.altitude).powi(2)).sqrt();
+
+        let mut check = TleCheck {
+            id: 0,
+            checked_at: Utc::now(),
+            norad_id,
+            tle_epoch: tle.epoch,
+            fix_at,
+            observed: GeoPoint { lat, lon, alt },
+            predicted: GeoPoint {
+                lat: predicted.latitude,
+                lon: predicted.longitude,
+                alt: predicted.altitude,
+            },
+            error_km,
+            stale: error_km > self.stale_km,
+        };
+        check.id = self.tle_repo.insert_check(&check).await?;
+
+        if check.stale {
+            warn!(
+                "TLE for {} (epoch {}) looks stale: prediction off by {:.1} km",
+                norad_id, tle.epoch, error_km
+            );
+        }
+
+        Ok(Some(check))
+    }
+
+    async fn latest_tle(&self, norad_id: i64) -> Result<TleSet, ApiError> {
+        self.tle_repo
+            .get_latest(norad_id)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("No TLE loaded for NORAD {}", norad_id)))
+    }
+
+    async fn propagator(&self, norad_id: i64) -> Result<Propagator, ApiError> {
+        let tle = self.latest_tle(norad_id).await?;
+        Propagator::from_tle(tle.object_name, &tle.line1, &tle.line2)
+    }
+}
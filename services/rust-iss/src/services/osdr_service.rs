@@ -1,18 +1,70 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use serde_json::Value;
+use chrono::{DateTime, Duration, Utc};
+use futures_util::stream::{self, BoxStream, StreamExt};
+use reqwest::Url;
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
 
-use crate::domain::{OsdrItem, extract_string, extract_timestamp};
+use crate::domain::osdr::{referenced_ids, OsdrFilter, OsdrSort, OsdrSortField};
+use crate::domain::osdr_enrichment::{
+    expand_url, extract_assays, extract_files, OsdrEnrichConfig, OsdrEnrichment,
+};
+use crate::domain::osdr_export::{OsdrExportFormat, OsdrExportWriter};
+use crate::domain::osdr_mapping::OsdrMapping;
+use crate::domain::pagination::{Page, PageRequest};
+use crate::domain::{
+    extract_string, OsdrAssay, OsdrDedupReport, OsdrDuplicateGroup, OsdrEnrichError, OsdrEnrichReport,
+    OsdrFacets, OsdrFile, OsdrItem, OsdrItemVersion, OsdrRelated, OsdrSyncItemError, OsdrSyncMode, OsdrSyncReport, OsdrSyncStatus, OsdrWriteOutcome,
+};
 use crate::errors::ApiError;
 use crate::repo::OsdrRepository;
+use crate::services::EventBus;
+
+/// Сколько похожих наборов отдавать в карточке
+const RELATED_LIMIT: i64 = 5;
+/// Сколько причин ошибок хранить в отчёте; счётчик failed считает все
+const MAX_REPORTED_ERRORS: usize = 200;
+/// Сколько значений каждого фасета отдавать
+const FACET_LIMIT: i64 = 50;
+/// Плановые выгрузки упорядочены по идентификатору, чтобы их было удобно сравнивать
+const EXPORT_SORT: OsdrSort = OsdrSort {
+    field: OsdrSortField::DatasetId,
+    descending: false,
+};
 
 pub struct OsdrService<R: OsdrRepository> {
     repo: Arc<R>,
     osdr_url: String,
+    /// Query-параметр, которым upstream фильтрует по updated_at (если умеет)
+    since_param: Option<String>,
+    max_pages: u32,
+    /// Сколько полных синхронизаций подряд набор может отсутствовать; 0 — не отзывать
+    withdraw_after: u32,
+    /// Как часто плановый прогон делается полным; None — только по запросу
+    full_sync_every: Option<Duration>,
+    mapping: Arc<OsdrMapping>,
+    /// None — стадия обогащения выключена
+    enrichment: Option<OsdrEnrichConfig>,
     http_client: reqwest::Client,
+    events: Arc<EventBus>,
 }
 
 impl<R: OsdrRepository> OsdrService<R> {
-    pub fn new(repo: Arc<R>, osdr_url: String) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        repo: Arc<R>,
+        osdr_url: String,
+        since_param: Option<String>,
+        max_pages: u32,
+        withdraw_after: u32,
+        full_sync_every: u64,
+        mapping: Arc<OsdrMapping>,
+        enrichment: Option<OsdrEnrichConfig>,
+        events: Arc<EventBus>,
+    ) -> Self {
         let http_client = reqwest::Client::builder()
             .timeout(std::time::Duration::from_secs(30))
             .user_agent("KosmoStars-Space/1.0")
@@ -23,60 +75,541 @@ impl<R: OsdrRepository> OsdrService<R> {
         Self {
             repo,
             osdr_url,
+            since_param,
+            max_pages: max_pages.max(1),
+            withdraw_after,
+            full_sync_every: (full_sync_every > 0).then(|| Duration::seconds(full_sync_every as i64)),
+            mapping,
+            enrichment,
             http_client,
+            events,
         }
     }
 
-    pub async fn sync_datasets(&self) -> Result<usize, ApiError> {
-        let response = self.http_client
-            .get(&self.osdr_url)
-            .send()
+    /// Проходит все страницы upstream. Первый прогон (или `force_full`) полный,
+    /// дальше пишутся только записи не старше водяного знака updated_at.
+    /// Раз в `full_sync_every` прогон снова полный — только по нему определяется отзыв.
+    /// Каждая страница — своя транзакция; отчёт сохраняется и при сбое.
+    pub async fn sync_datasets(&self, force_full: bool) -> Result<OsdrSyncReport, ApiError> {
+        let state = self.repo.get_sync_state(&self.osdr_url).await?;
+        let full_due = self.full_sync_every.is_some_and(|every| {
+            state.last_full_sync_at.is_none_or(|at| Utc::now() - at >= every)
+        });
+        if full_due && !force_full && state.watermark.is_some() {
+            info!("OSDR full sync is due (last: {:?})", state.last_full_sync_at);
+        }
+        let watermark = if force_full || full_due { None } else { state.watermark };
+
+        let mut report = OsdrSyncReport {
+            id: None,
+            mode: if watermark.is_some() {
+                OsdrSyncMode::Incremental
+            } else {
+                OsdrSyncMode::Full
+            },
+            status: OsdrSyncStatus::Completed,
+            started_at: Utc::now(),
+            finished_at: Utc::now(),
+            pages: 0,
+            items_seen: 0,
+            inserted: 0,
+            updated: 0,
+            unchanged: 0,
+            skipped: 0,
+            failed: 0,
+            truncated: false,
+            withdrawn: 0,
+            enrichment: None,
+            watermark_before: watermark,
+            watermark_after: watermark,
+            error: None,
+            errors: Vec::new(),
+        };
+
+        let mut newest = None;
+        let mut unwritten = Vec::new();
+        let result = match self.sync_pages(&mut report, &mut newest, &mut unwritten).await {
+            // При обрыве по лимиту хвост не пройден, сдвигать знак нельзя
+            Ok(()) if !report.truncated => self
+                .repo
+                .save_watermark(&self.osdr_url, newest, report.mode == OsdrSyncMode::Full)
+                .await
+                .map(|_| report.watermark_after = watermark.max(newest)),
+            other => other,
+        };
+        // Отзыв определяется только по полной выдаче, пройденной до конца
+        let result = match result {
+            Ok(()) if report.mode == OsdrSyncMode::Full && !report.truncated && self.withdraw_after > 0 => {
+                self.mark_withdrawn(&mut report, &unwritten).await
+            }
+            other => other,
+        };
+        let result = match (result, &self.enrichment) {
+            (Ok(()), Some(config)) => self.enrich(config).await.map(|e| report.enrichment = Some(e)),
+            (other, _) => other,
+        };
+        if let Err(e) = result {
+            report.status = OsdrSyncStatus::Failed;
+            report.error = Some(e.message);
+        }
+        report.finished_at = Utc::now();
+        report.id = Some(self.repo.save_sync_report(&report).await?);
+
+        info!(
+            "OSDR {} sync {}: {} pages, {} seen, {} inserted, {} updated, {} unchanged, {} skipped, {} failed, {} withdrawn",
+            report.mode.as_str(),
+            report.status.as_str(),
+            report.pages,
+            report.items_seen,
+            report.inserted,
+            report.updated,
+            report.unchanged,
+            report.skipped,
+            report.failed,
+            report.withdrawn
+        );
+        self.events.publish(
+            "osdr.synced",
+            json!({
+                "report_id": report.id,
+                "status": report.status,
+                "mode": report.mode,
+                "written": report.written(),
+                "pages": report.pages,
+                "items_seen": report.items_seen,
+                "failed": report.failed,
+                "withdrawn": report.withdrawn,
+                "enriched": report.enrichment.as_ref().map(|e| e.datasets),
+            }),
+        );
+        Ok(report)
+    }
+
+    async fn mark_withdrawn(&self, report: &mut OsdrSyncReport, unwritten: &[String]) -> Result<(), ApiError> {
+        let withdrawn = self
+            .repo
+            .mark_unseen(report.started_at, unwritten, self.withdraw_after)
             .await?;
+        report.withdrawn = withdrawn.len() as u64;
+        if !withdrawn.is_empty() {
+            warn!("OSDR datasets withdrawn upstream: {}", withdrawn.join(", "));
+            self.events.publish("osdr.withdrawn", json!({ "dataset_ids": withdrawn }));
+        }
+        Ok(())
+    }
 
-        if !response.status().is_success() {
-            return Err(ApiError::upstream(
-                response.status().as_u16(),
-                format!("OSDR API returned {}", response.status()),
-            ));
+    /// Карточки, ассаи и файлы наборов, которые этого ждут; не больше
+    /// `concurrency` запросов к OSDR одновременно
+    async fn enrich(&self, config: &OsdrEnrichConfig) -> Result<OsdrEnrichReport, ApiError> {
+        let due = self.repo.list_enrichment_due(config.batch).await?;
+        let mut report = OsdrEnrichReport::default();
+
+        let mut results = stream::iter(due)
+            .map(|item| async move {
+                let fetched = self.fetch_enrichment(config, &item).await;
+                (item, fetched)
+            })
+            .buffer_unordered(config.concurrency.max(1));
+
+        while let Some((item, fetched)) = results.next().await {
+            let dataset_id = item.dataset_id.clone().unwrap_or_default();
+            match fetched {
+                Ok(enrichment) => {
+                    self.repo.save_enrichment(&item, &enrichment).await?;
+                    report.datasets += 1;
+                    report.assays += enrichment.assays.len() as u64;
+                    report.files += enrichment.files.len() as u64;
+                }
+                Err(e) => {
+                    self.repo.save_enrichment_error(&item, &e.message).await?;
+                    report.failed += 1;
+                    if report.errors.len() < MAX_REPORTED_ERRORS {
+                        report.errors.push(OsdrEnrichError { dataset_id, reason: e.message });
+                    }
+                }
+            }
+        }
+
+        info!(
+            "OSDR enrichment: {} datasets, {} assays, {} files, {} failed",
+            report.datasets, report.assays, report.files, report.failed
+        );
+        Ok(report)
+    }
+
+    async fn fetch_enrichment(&self, config: &OsdrEnrichConfig, item: &OsdrItem) -> Result<OsdrEnrichment, ApiError> {
+        let dataset_id = item.dataset_id.as_deref().unwrap_or_default();
+        let detail = self.fetch_json(&expand_url(&config.detail_url, dataset_id)).await?;
+        let assays = match &config.assays_url {
+            Some(template) => extract_assays(&self.fetch_json(&expand_url(template, dataset_id)).await?),
+            None => extract_assays(&detail),
+        };
+
+        let files_url = expand_url(&config.files_url, dataset_id);
+        let mut files = extract_files(&self.fetch_json(&files_url).await?);
+        // Ссылки на скачивание бывают относительными
+        if let Ok(base) = Url::parse(&files_url) {
+            for file in &mut files {
+                file.url = file.url.take().map(|u| base.join(&u).map(String::from).unwrap_or(u));
+            }
         }
 
-        let json: Value = response.json().await?;
-        let items = self.extract_items(&json);
+        Ok(OsdrEnrichment { detail, assays, files })
+    }
+
+    async fn fetch_json(&self, url: &str) -> Result<Value, ApiError> {
+        let url = Url::parse(url).map_err(|e| ApiError::validation(format!("Invalid OSDR URL {}: {}", url, e)))?;
+        self.fetch_page(&url).await.map(|(json, _)| json)
+    }
+
+    /// Страницы до конца выдачи; счётчики копятся в `report`, в `unwritten` —
+    /// dataset_id наборов, которые были в выдаче, но не записались
+    async fn sync_pages(
+        &self,
+        report: &mut OsdrSyncReport,
+        newest: &mut Option<DateTime<Utc>>,
+        unwritten: &mut Vec<String>,
+    ) -> Result<(), ApiError> {
+        let watermark = report.watermark_before;
+        let mut url = Url::parse(&self.osdr_url)
+            .map_err(|e| ApiError::validation(format!("Invalid OSDR URL {}: {}", self.osdr_url, e)))?;
+        if let (Some(param), Some(since)) = (&self.since_param, watermark) {
+            url.query_pairs_mut().append_pair(param, &since.to_rfc3339());
+        }
+        let mut visited = HashSet::new();
+
+        loop {
+            visited.insert(url.to_string());
+            let (json, link) = self.fetch_page(&url).await?;
+            let items = extract_items(&json);
+            report.pages += 1;
+            report.items_seen += items.len() as u64;
 
-        let mut written = 0;
-        for item in items {
-            let dataset_id = extract_string(&item, &["dataset_id", "id", "uuid", "studyId", "accession", "osdr_id"]);
-            let title = extract_string(&item, &["title", "name", "label"]);
-            let organism = extract_string(&item, &["organism", "species", "model_organism"]);
-            let study_type = extract_string(&item, &["study_type", "type", "experiment_type"]);
-            let status = extract_string(&item, &["status", "state", "lifecycle"]);
-            let updated_at = extract_timestamp(&item, &["updated", "updated_at", "modified", "lastUpdated", "timestamp"]);
+            let mut batch = Vec::with_capacity(items.len());
+            for item in &items {
+                if !item.is_object() {
+                    record_failure(report, None, format!("Item is not a JSON object: {}", item));
+                    continue;
+                }
+                let fields = self.mapping.normalize(item);
+                if let (Some(mark), Some(updated)) = (watermark, fields.updated_at) {
+                    if updated < mark {
+                        report.skipped += 1;
+                        continue;
+                    }
+                }
+                batch.push(fields);
+            }
 
-            self.repo.upsert(dataset_id, title, organism, study_type, status, updated_at, item).await?;
-            written += 1;
+            let keys: Vec<_> = batch.iter().map(|f| (f.dataset_id.clone(), f.updated_at)).collect();
+            if !batch.is_empty() {
+                let outcomes = self.repo.upsert_page(batch, report.started_at).await?;
+                for ((dataset_id, updated_at), outcome) in keys.into_iter().zip(outcomes) {
+                    match outcome {
+                        Ok(OsdrWriteOutcome::Inserted) => report.inserted += 1,
+                        Ok(OsdrWriteOutcome::Updated) => report.updated += 1,
+                        Ok(OsdrWriteOutcome::Unchanged) => report.unchanged += 1,
+                        Err(reason) => {
+                            unwritten.extend(dataset_id.clone());
+                            record_failure(report, dataset_id, reason);
+                            continue;
+                        }
+                    }
+                    *newest = (*newest).max(updated_at);
+                }
+            }
+
+            if items.is_empty() {
+                return Ok(());
+            }
+            let Some(next) = next_page(&url, &json, link.as_deref(), items.len()) else {
+                return Ok(());
+            };
+            if visited.contains(next.as_str()) {
+                warn!("OSDR pagination loops back to {}, stopping", next);
+                return Ok(());
+            }
+            if report.pages >= self.max_pages {
+                warn!("OSDR sync stopped at page limit {}", self.max_pages);
+                report.truncated = true;
+                return Ok(());
+            }
+            url = next;
         }
+    }
+
+    pub fn mapping(&self) -> &OsdrMapping {
+        &self.mapping
+    }
 
+    /// Как записи образца были бы приведены к колонкам: значение и сработавший путь.
+    /// `mapping` позволяет проверить правила до выкладки файла.
+    pub fn dry_run(&self, sample: &Value, mapping: Option<&OsdrMapping>) -> Vec<Value> {
+        let mapping = mapping.unwrap_or(&self.mapping);
+        let mut items = extract_items(sample);
+        if items.is_empty() && sample.is_object() {
+            items.push(sample.clone());
+        }
+        items
+            .iter()
+            .map(|item| json!({"fields": mapping.explain(item), "raw": item}))
+            .collect()
+    }
+
+    /// Разовая чистка записей без dataset_id: каждой назначается тот же
+    /// идентификатор, что дала бы синхронизация, совпавшие сливаются в одну.
+    /// Без `apply` только показывает группы.
+    pub async fn dedup(&self, apply: bool) -> Result<OsdrDedupReport, ApiError> {
+        let items = self.repo.list_unidentified().await?;
+
+        let mut groups: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+        for item in &items {
+            if let Some(dataset_id) = self.mapping.normalize(&item.raw).dataset_id {
+                groups.entry(dataset_id).or_default().push(item.id);
+            }
+        }
+
+        let mut report = OsdrDedupReport {
+            applied: apply,
+            unidentified: items.len() as u64,
+            merged: 0,
+            groups: Vec::with_capacity(groups.len()),
+        };
+        for (dataset_id, ids) in groups {
+            let survivor = if apply {
+                let survivor = self.repo.merge_duplicates(&dataset_id, &ids).await?;
+                report.merged += ids.iter().filter(|id| **id != survivor).count() as u64;
+                Some(survivor)
+            } else {
+                None
+            };
+            report.groups.push(OsdrDuplicateGroup { dataset_id, ids, survivor });
+        }
+        Ok(report)
+    }
+
+    /// Наборы по фильтру потоком, для выгрузки
+    pub fn export(&self, filter: &OsdrFilter, sort: OsdrSort) -> BoxStream<'static, Result<OsdrItem, ApiError>> {
+        self.repo.stream(filter, sort)
+    }
+
+    /// Выгрузка всех действующих наборов в `dir`, по файлу на формат:
+    /// osdr_export_<YYYYmmdd_HHMMSS>.<ext>. Файл пишется под временным именем
+    /// и переименовывается, так что недописанных выгрузок в каталоге не видно.
+    pub async fn export_to_dir(&self, dir: &Path, formats: &[OsdrExportFormat]) -> Result<Vec<PathBuf>, ApiError> {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| ApiError::internal(format!("Cannot create export dir {}: {}", dir.display(), e)))?;
+
+        let stamp = Utc::now().format("%Y%m%d_%H%M%S");
+        let mut written = Vec::with_capacity(formats.len());
+        for format in formats {
+            let path = dir.join(format!("osdr_export_{}.{}", stamp, format.extension()));
+            let partial = path.with_extension(format!("{}.part", format.extension()));
+            let rows = match self.write_export(&partial, *format).await {
+                Ok(rows) => rows,
+                Err(e) => {
+                    let _ = tokio::fs::remove_file(&partial).await;
+                    return Err(e);
+                }
+            };
+            tokio::fs::rename(&partial, &path)
+                .await
+                .map_err(|e| ApiError::internal(format!("Cannot write {}: {}", path.display(), e)))?;
+            info!("OSDR export written: {} ({} rows)", path.display(), rows);
+            written.push(path);
+        }
         Ok(written)
     }
 
-    pub async fn list(&self, limit: i32, offset: i32, search: Option<String>) -> Result<Vec<OsdrItem>, ApiError> {
-        self.repo.list(limit, offset, search).await
+    async fn write_export(&self, path: &Path, format: OsdrExportFormat) -> Result<u64, ApiError> {
+        let io_error = |e: std::io::Error| ApiError::internal(format!("Cannot write {}: {}", path.display(), e));
+        let file = tokio::fs::File::create(path).await.map_err(io_error)?;
+        let mut file = tokio::io::BufWriter::new(file);
+        let mut writer = OsdrExportWriter::new(format);
+
+        file.write_all(&writer.header()?).await.map_err(io_error)?;
+        let mut items = self.repo.stream(&OsdrFilter::default(), EXPORT_SORT);
+        let mut rows = 0;
+        while let Some(item) = items.next().await {
+            file.write_all(&writer.row(&item?)?).await.map_err(io_error)?;
+            rows += 1;
+        }
+        file.write_all(&writer.footer()?).await.map_err(io_error)?;
+        file.flush().await.map_err(io_error)?;
+        Ok(rows)
+    }
+
+    /// Последние отчёты синхронизации
+    pub async fn sync_reports(&self, limit: i64) -> Result<Vec<OsdrSyncReport>, ApiError> {
+        self.repo.list_sync_reports(limit).await
+    }
+
+    /// Страница списка; `total` — отдельный COUNT по фильтру, поэтому только по запросу
+    pub async fn list(
+        &self,
+        filter: &OsdrFilter,
+        sort: OsdrSort,
+        page: &PageRequest,
+        offset: i64,
+    ) -> Result<Page<OsdrItem>, ApiError> {
+        let mut result = self.repo.list(filter, sort, page.cursor.as_ref(), page.limit, offset).await?;
+        if page.include_total {
+            result.total = Some(self.repo.count(filter).await?);
+        }
+        Ok(result)
+    }
+
+    pub async fn facets(&self, filter: &OsdrFilter) -> Result<OsdrFacets, ApiError> {
+        self.repo.facets(filter, FACET_LIMIT).await
     }
 
-    pub async fn count(&self) -> Result<i64, ApiError> {
-        self.repo.count().await
+    /// Набор и связанные с ним наборы
+    pub async fn get(&self, dataset_id: &str) -> Result<(OsdrItem, Vec<OsdrRelated>), ApiError> {
+        let item = self.find(dataset_id).await?;
+        let referenced = referenced_ids(&item.raw);
+        let related = self.repo.related(&item, &referenced, RELATED_LIMIT).await?;
+        Ok((item, related))
     }
 
-    fn extract_items(&self, json: &Value) -> Vec<Value> {
-        if let Some(arr) = json.as_array() {
-            return arr.clone();
+    pub async fn assays(&self, dataset_id: &str) -> Result<Vec<OsdrAssay>, ApiError> {
+        let item = self.find(dataset_id).await?;
+        self.repo.list_assays(item.id).await
+    }
+
+    pub async fn files(&self, dataset_id: &str) -> Result<Vec<OsdrFile>, ApiError> {
+        let item = self.find(dataset_id).await?;
+        self.repo.list_files(item.id).await
+    }
+
+    async fn find(&self, dataset_id: &str) -> Result<OsdrItem, ApiError> {
+        self.repo
+            .get_by_dataset_id(dataset_id)
+            .await?
+            .ok_or_else(|| ApiError::not_found(format!("Dataset {} not found", dataset_id)))
+    }
+
+    pub async fn history(&self, dataset_id: &str) -> Result<Vec<OsdrItemVersion>, ApiError> {
+        let versions = self.repo.history(dataset_id).await?;
+        if versions.is_empty() {
+            return Err(ApiError::not_found(format!("Dataset {} not found", dataset_id)));
+        }
+        Ok(versions)
+    }
+
+    /// JSON страницы и ссылка rel="next" из заголовка Link, если есть
+    async fn fetch_page(&self, url: &Url) -> Result<(Value, Option<String>), ApiError> {
+        let response = self.http_client
+            .get(url.clone())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ApiError::upstream(
+                response.status().as_u16(),
+                format!("OSDR API returned {} for {}", response.status(), url),
+            ));
         }
-        if let Some(items) = json.get("items").and_then(|x| x.as_array()) {
+
+        let link = response
+            .headers()
+            .get(reqwest::header::LINK)
+            .and_then(|v| v.to_str().ok())
+            .and_then(link_next);
+        Ok((response.json().await?, link))
+    }
+}
+
+fn record_failure(report: &mut OsdrSyncReport, dataset_id: Option<String>, reason: String) {
+    report.failed += 1;
+    if report.errors.len() < MAX_REPORTED_ERRORS {
+        report.errors.push(OsdrSyncItemError {
+            page: report.pages,
+            dataset_id,
+            reason,
+        });
+    }
+}
+
+/// Записи страницы: массив, обёртка items/results/data или объект
+/// вида {"OSD-1": {...}}, где ключ — идентификатор набора
+fn extract_items(json: &Value) -> Vec<Value> {
+    if let Some(arr) = json.as_array() {
+        return arr.clone();
+    }
+    for key in ["items", "results", "data"] {
+        if let Some(items) = json.get(key).and_then(|x| x.as_array()) {
             return items.clone();
         }
-        if let Some(results) = json.get("results").and_then(|x| x.as_array()) {
-            return results.clone();
+    }
+    if let Some(map) = json.as_object() {
+        if !map.is_empty() && map.values().all(|v| v.is_object()) {
+            return map
+                .iter()
+                .map(|(key, value)| {
+                    let mut item = value.clone();
+                    if extract_string(&item, &["dataset_id", "id", "accession"]).is_none() {
+                        item["dataset_id"] = json!(key);
+                    }
+                    item
+                })
+                .collect();
         }
-        vec![json.clone()]
     }
+    warn!("OSDR page has no recognizable item list");
+    Vec::new()
+}
+
+/// Следующая страница: явная ссылка в теле, заголовок Link,
+/// затем page/total_pages и offset/total
+fn next_page(current: &Url, json: &Value, link: Option<&str>, fetched: usize) -> Option<Url> {
+    let explicit = ["/next", "/links/next", "/links/next/href", "/_links/next/href", "/meta/next", "/pagination/next"]
+        .iter()
+        .find_map(|ptr| json.pointer(ptr).and_then(|v| v.as_str()))
+        .filter(|s| !s.is_empty())
+        .or(link);
+    if let Some(href) = explicit {
+        return current.join(href).ok();
+    }
+
+    let meta = json.get("meta").or_else(|| json.get("pagination")).unwrap_or(json);
+    let number = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|k| meta.get(*k).or_else(|| json.get(*k)))
+            .and_then(|v| v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
+    };
+
+    if let (Some(page), Some(total_pages)) = (number(&["page", "current_page"]), number(&["total_pages", "pages", "last_page"])) {
+        return (page < total_pages).then(|| with_param(current, "page", page + 1));
+    }
+    if let (Some(offset), Some(total)) = (number(&["offset", "start"]), number(&["total", "count", "total_count"])) {
+        let next = offset + fetched as u64;
+        return (next < total).then(|| with_param(current, "offset", next));
+    }
+    None
+}
+
+fn with_param(url: &Url, key: &str, value: u64) -> Url {
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| k != key)
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    let mut next = url.clone();
+    next.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(key, &value.to_string());
+    next
+}
+
+/// `<https://...?page=2>; rel="next", <...>; rel="last"`
+fn link_next(header: &str) -> Option<String> {
+    header.split(',').find_map(|part| {
+        let (target, params) = part.split_once(';')?;
+        let is_next = params
+            .split(';')
+            .any(|p| p.trim().trim_start_matches("rel=").trim_matches('"') == "next");
+        is_next.then(|| target.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+    })
 }
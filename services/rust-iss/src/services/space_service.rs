@@ -1,19 +1,22 @@
 use std::sync::Arc;
 use chrono::Utc;
-use serde_json::Value;
+use serde_json::{json, Value};
 
+use crate::domain::pagination::{Page, PageRequest};
 use crate::domain::SpaceCache;
 use crate::errors::ApiError;
 use crate::repo::CacheRepository;
+use crate::services::EventBus;
 
 pub struct SpaceService<C: CacheRepository> {
     cache_repo: Arc<C>,
     nasa_key: String,
     http_client: reqwest::Client,
+    events: Arc<EventBus>,
 }
 
 impl<C: CacheRepository> SpaceService<C> {
-    pub fn new(cache_repo: Arc<C>, nasa_key: String) -> Self {
+    pub fn new(cache_repo: Arc<C>, nasa_key: String, events: Arc<EventBus>) -> Self {
         let http_client = reqwest::Client::builder()
             .timeout(std::time::Duration::from_secs(30))
             .user_agent("KosmoStars-Space/1.0")
@@ -26,6 +29,7 @@ impl<C: CacheRepository> SpaceService<C> {
             cache_repo,
             nasa_key,
             http_client,
+            events,
         }
     }
 
@@ -33,6 +37,15 @@ impl<C: CacheRepository> SpaceService<C> {
         self.cache_repo.get_latest(source).await
     }
 
+    /// История кэша источника от новых записей к старым
+    pub async fn history(&self, source: &str, page: &PageRequest) -> Result<Page<SpaceCache>, ApiError> {
+        let mut result = self.cache_repo.history(source, page.cursor.as_ref(), page.limit).await?;
+        if page.include_total {
+            result.total = Some(self.cache_repo.count(source).await?);
+        }
+        Ok(result)
+    }
+
     pub async fn fetch_apod(&self) -> Result<(), ApiError> {
         let url = "https://api.nasa.gov/planetary/apod";
         
@@ -50,8 +63,7 @@ impl<C: CacheRepository> SpaceService<C> {
         }
 
         let json: Value = response.json().await?;
-        self.cache_repo.insert("apod", json).await?;
-        Ok(())
+        self.store("apod.updated", "apod", json).await
     }
 
     pub async fn fetch_neo(&self) -> Result<(), ApiError> {
@@ -78,8 +90,7 @@ impl<C: CacheRepository> SpaceService<C> {
         }
 
         let json: Value = response.json().await?;
-        self.cache_repo.insert("neo", json).await?;
-        Ok(())
+        self.store("neo.updated", "neo", json).await
     }
 
     pub async fn fetch_donki_flr(&self) -> Result<(), ApiError> {
@@ -95,8 +106,7 @@ impl<C: CacheRepository> SpaceService<C> {
 
         let response = req.send().await?;
         let json: Value = response.json().await?;
-        self.cache_repo.insert("flr", json).await?;
-        Ok(())
+        self.store("donki.updated", "flr", json).await
     }
 
     pub async fn fetch_donki_cme(&self) -> Result<(), ApiError> {
@@ -112,8 +122,7 @@ impl<C: CacheRepository> SpaceService<C> {
 
         let response = req.send().await?;
         let json: Value = response.json().await?;
-        self.cache_repo.insert("cme", json).await?;
-        Ok(())
+        self.store("donki.updated", "cme", json).await
     }
 
     pub async fn fetch_spacex(&self) -> Result<(), ApiError> {
@@ -121,47 +130,44 @@ impl<C: CacheRepository> SpaceService<C> {
         
         let response = self.http_client.get(url).send().await?;
         let json: Value = response.json().await?;
-        self.cache_repo.insert("spacex", json).await?;
-        Ok(())
+        self.store("spacex.updated", "spacex", json).await
     }
 
     pub async fn refresh(&self, sources: Vec<&str>) -> Result<Vec<String>, ApiError> {
         let mut done = Vec::new();
 
         for source in sources {
-            match source {
-                "apod" => {
-                    if self.fetch_apod().await.is_ok() {
-                        done.push("apod".to_string());
-                    }
-                }
-                "neo" => {
-                    if self.fetch_neo().await.is_ok() {
-                        done.push("neo".to_string());
-                    }
-                }
-                "flr" => {
-                    if self.fetch_donki_flr().await.is_ok() {
-                        done.push("flr".to_string());
-                    }
-                }
-                "cme" => {
-                    if self.fetch_donki_cme().await.is_ok() {
-                        done.push("cme".to_string());
-                    }
-                }
-                "spacex" => {
-                    if self.fetch_spacex().await.is_ok() {
-                        done.push("spacex".to_string());
-                    }
-                }
-                _ => {}
+            let ok = match source {
+                "apod" => self.fetch_apod().await.is_ok(),
+                "neo" => self.fetch_neo().await.is_ok(),
+                "flr" => self.fetch_donki_flr().await.is_ok(),
+                "cme" => self.fetch_donki_cme().await.is_ok(),
+                "spacex" => self.fetch_spacex().await.is_ok(),
+                _ => false,
+            };
+            if ok {
+                done.push(source.to_string());
             }
         }
 
         Ok(done)
     }
 
+    /// Сохраняет ответ в кэш; подписчиков оповещает, только если ответ
+    /// отличается от последней записи источника
+    async fn store(&self, kind: &str, source: &str, payload: Value) -> Result<(), ApiError> {
+        let changed = self
+            .cache_repo
+            .get_latest(source)
+            .await?
+            .is_none_or(|latest| latest.payload != payload);
+        let id = self.cache_repo.insert(source, payload).await?;
+        if changed {
+            self.events.publish(kind, json!({"source": source, "cache_id": id}));
+        }
+        Ok(())
+    }
+
     fn last_days(n: u64) -> (String, String) {
         let to = Utc::now().date_naive();
         let from = to - chrono::Days::new(n);